@@ -1,13 +1,365 @@
-use anyhow::{Context, Result};
-use reqwest::Client;
+use anyhow::Result;
+use reqwest::{Client, StatusCode};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::models::{Ticket, AnthropicRequest, AnthropicResponse, AnthropicMessage};
+use crate::models::{
+    format_estimate, AnthropicContentBlock, AnthropicMessage, AnthropicRequest, AnthropicResponse,
+    AnthropicUsage, Comment, Ticket,
+};
 
-const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
+/// Default Anthropic API base URL, overridable with ANTHROPIC_API_BASE or --anthropic-api-base
+pub const DEFAULT_ANTHROPIC_API_BASE: &str = "https://api.anthropic.com";
+
+/// Default anthropic-version header, overridable with ANTHROPIC_VERSION or --anthropic-version
+pub const DEFAULT_ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Path of the Messages endpoint relative to the API base URL
+const MESSAGES_PATH: &str = "/v1/messages";
+
+/// Default rubric for the --review pass, overridable with ANTHROPIC_REVIEW_RUBRIC
+pub const DEFAULT_REVIEW_RUBRIC: &str =
+    "- Is there a concrete testing strategy covering the main paths and edge cases?\n\
+     - Is every implementation step specific and actionable, in a sensible order?\n\
+     - Is there a rollback or mitigation plan for risky changes?\n\
+     - Are the acceptance criteria and stated requirements all addressed?\n\
+     - Is the effort estimate justified by the steps?";
+
+/// Instructions for --summary, which asks for a brief approach instead of a full plan
+const SUMMARY_INSTRUCTIONS: &str =
+    "You are a software engineering expert helping to scope software development tickets.\n\n\
+     I'm going to provide you with a ticket from our project management system. Reply with a brief\n\
+     summary of it: two sentences on the suggested approach, followed by a one-line risk note\n\
+     starting with \"Risk:\". Do not write a full implementation plan.";
+
+/// Instructions for --describe, which explains a ticket instead of planning it
+const DESCRIBE_INSTRUCTIONS: &str =
+    "You are a software engineering expert helping newcomers understand software development tickets.\n\n\
+     I'm going to provide you with a ticket from our project management system. Explain it in plain\n\
+     English, spelling out jargon and acronyms, in three short markdown sections: What (what the\n\
+     ticket asks for), Why (the problem or goal behind it) and Who's affected (users, teams or\n\
+     systems). Do not write an implementation plan.";
+
+/// Instructions for condensing a long comment thread with --compact-comments
+const COMPACT_COMMENTS_INSTRUCTIONS: &str =
+    "You condense discussion threads from a project management system. Reply with at most 8\n\
+     short markdown bullet points covering the decisions made, open questions, constraints and\n\
+     blockers raised in the comments. Mention who said what only when it matters. Reply with\n\
+     the bullet points only.";
+
+/// Small, cheap model used to condense comment threads
+pub const COMPACT_COMMENTS_MODEL: &str = "claude-3-haiku-20240307";
+
+/// Maximum tokens for a condensed comment thread
+const COMPACT_COMMENTS_MAX_TOKENS: u32 = 500;
+
+/// Maximum tokens for a full implementation plan
+const PLAN_MAX_TOKENS: u32 = 4000;
+
+/// Maximum tokens for a concise plan of a small ticket
+const CONCISE_PLAN_MAX_TOKENS: u32 = 1500;
+
+/// Maximum tokens for a detailed plan of a large ticket
+const DETAILED_PLAN_MAX_TOKENS: u32 = 8000;
+
+/// Default estimate below which a concise plan is requested
+pub const DEFAULT_CONCISE_PLAN_BELOW: f64 = 2.0;
+
+/// Default estimate from which a detailed plan is requested
+pub const DEFAULT_DETAILED_PLAN_FROM: f64 = 8.0;
+
+/// Maximum tokens for a --summary response, kept low for cost
+const SUMMARY_MAX_TOKENS: u32 = 300;
+
+/// Maximum tokens for a --describe explanation
+const DESCRIBE_MAX_TOKENS: u32 = 600;
+
+/// Characters of a related ticket's description kept in the prompt (--deep-relations)
+const RELATED_DESCRIPTION_CHARS: usize = 500;
+
+/// Characters of the description kept as background with --plan-from-comments-only
+const COMMENTS_LEAD_DESCRIPTION_CHARS: usize = 1000;
+
+/// Added to the plan request with --plan-from-comments-only
+const PLAN_FROM_COMMENTS_REQUEST: &str =
+    " Base the plan on the latest comments, which reflect the current direction of the work; \
+     use the original description only for background the comments don't contradict.";
+
+/// Final request of the ticket prompt for a full plan
+const PLAN_REQUEST: &str = "Please provide a detailed implementation plan for this ticket.";
+
+/// Added to the plan request for tickets estimated below the concise threshold
+const CONCISE_PLAN_REQUEST: &str =
+    " This is a small ticket: keep the plan short, with a brief approach and a handful of \
+     steps, and leave out sections that don't apply.";
+
+/// Added to the plan request for tickets estimated at or above the detailed threshold
+const DETAILED_PLAN_REQUEST: &str =
+    " This is a large ticket: give a full breakdown, splitting the work into milestones that \
+     can be shipped separately, with the steps, risks and tests of each.";
+
+/// Added to the plan request with --checklist-output so the steps can be extracted
+const CHECKLIST_REQUEST: &str =
+    " Write the step-by-step implementation approach as a markdown checkbox list, one \
+     self-contained step per line in the form `- [ ] step`, and don't use checkboxes anywhere else.";
+
+/// Instructions for --verify, which compares a pull request with the saved plan
+const VERIFY_INSTRUCTIONS: &str =
+    "You are a software engineering expert reviewing whether an implementation matches its plan.\n\n\
+     I'm going to provide you with a ticket from our project management system, the implementation\n\
+     plan that was written for it, and a summary of the pull request that implements it. Write a\n\
+     short gap report in markdown with these sections: Verdict (one line: matches, partially matches\n\
+     or diverges), Covered (planned steps the pull request implements), Gaps (planned steps or\n\
+     tests that are missing), and Unplanned changes (changes the plan didn't mention). Base it only\n\
+     on the information given, and say so when the file list is too limited to tell.";
+
+/// Maximum tokens for a --verify gap report
+const VERIFY_MAX_TOKENS: u32 = 1500;
+
+/// Final request of the ticket prompt for --verify
+const VERIFY_REQUEST: &str = "Please compare the pull request below with the implementation plan.";
+
+/// Final request of the ticket prompt for --summary
+const SUMMARY_REQUEST: &str = "Please provide a brief summary of the approach and its main risk.";
+
+/// Final request of the ticket prompt for --describe
+const DESCRIBE_REQUEST: &str = "Please explain what this ticket means in plain English.";
+
+/// Price in dollars per million input and output tokens, by model family. Checked in
+/// order against the model name, so more specific names come first.
+const MODEL_PRICES: [(&str, f64, f64); 4] = [
+    ("opus", 15.0, 75.0),
+    ("3-5-haiku", 0.8, 4.0),
+    ("haiku", 0.25, 1.25),
+    ("sonnet", 3.0, 15.0),
+];
+
+/// Cache writes and reads are billed as a multiple of the model's input price
+const CACHE_WRITE_PRICE_FACTOR: f64 = 1.25;
+const CACHE_READ_PRICE_FACTOR: f64 = 0.1;
+
+/// Default number of retries for overloaded, rate-limited or failing requests
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry; doubled on every further attempt
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Upper bound for a single retry delay, including one requested via retry-after
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Rough number of characters per token used to estimate prompt size
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Opening of the instructions shared by every implementation plan request, followed by
+/// the numbered plan sections
+const IMPLEMENTATION_PLAN_PREAMBLE: &str =
+    "You are a software engineering expert helping to create implementation plans for software development tickets.\n\n\
+     I'm going to provide you with a ticket from our project management system. Based on the ticket details,\n\
+     generate a detailed implementation plan. The plan should include:\n\n";
+
+/// Plan sections that --plan-sections accepts by name, and how each is asked for
+pub const PLAN_SECTIONS: [(&str, &str); 9] = [
+    ("overview", "An overview of the task"),
+    ("requirements", "Technical requirements and considerations"),
+    ("approach", "Step-by-step implementation approach"),
+    ("challenges", "Potential challenges and solutions"),
+    ("testing", "Testing strategy"),
+    ("effort", "Estimated effort (in hours or story points)"),
+    ("risks", "Risks and how to mitigate them"),
+    ("rollback", "Rollback plan"),
+    ("security", "Security considerations"),
+];
+
+/// Plan sections requested unless --plan-sections says otherwise
+pub const DEFAULT_PLAN_SECTIONS: &str = "overview,requirements,approach,challenges,testing,effort";
+
+/// Parse a comma-separated --plan-sections list into the sections to request. Single words
+/// must be known section names, so typos are caught; anything with a space, such as
+/// "Data migration steps", is requested as written.
+pub fn parse_plan_sections(value: &str) -> Result<Vec<String>> {
+    let sections: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|section| !section.is_empty())
+        .map(|section| {
+            if section.contains(char::is_whitespace) {
+                return Ok(section.to_string());
+            }
+            PLAN_SECTIONS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(section))
+                .map(|(_, description)| description.to_string())
+                .ok_or_else(|| {
+                    let names: Vec<&str> = PLAN_SECTIONS.iter().map(|(name, _)| *name).collect();
+                    anyhow::anyhow!(
+                        "Unknown plan section '{}'. Known sections: {}. Custom sections need more than one word.",
+                        section,
+                        names.join(", ")
+                    )
+                })
+        })
+        .collect::<Result<_>>()?;
+
+    if sections.is_empty() {
+        anyhow::bail!("At least one plan section is needed");
+    }
+    Ok(sections)
+}
+
+/// Instructions shared by every implementation plan request, asking for the given sections
+/// in order. They are sent as a cached system block so multi-ticket runs aren't charged for
+/// the same prefix repeatedly.
+pub fn implementation_plan_instructions(sections: &[String]) -> String {
+    let numbered: Vec<String> = sections
+        .iter()
+        .enumerate()
+        .map(|(index, section)| format!("{}. {}", index + 1, section))
+        .collect();
+    format!("{}{}", IMPLEMENTATION_PLAN_PREAMBLE, numbered.join("\n"))
+}
+
+/// Failures of an Anthropic API request. They reach callers wrapped in `anyhow::Error`,
+/// where the kind can be told apart with `downcast_ref::<AnthropicError>()`.
+#[derive(Debug, thiserror::Error)]
+pub enum AnthropicError {
+    /// The API key is missing, invalid or lacks access
+    #[error("Anthropic API rejected the API key (status {status}): {body}")]
+    Unauthorized { status: StatusCode, body: String },
+    /// Still rate limited or overloaded after the configured retries
+    #[error("Anthropic API request was rate limited (status {status}){}: {body}", after_retries(.retries))]
+    RateLimited { status: StatusCode, retries: u32, body: String },
+    /// Usually an unknown model name
+    #[error("Anthropic API could not find the requested resource (status {status}): {body}")]
+    NotFound { status: StatusCode, body: String },
+    /// Any other unsuccessful HTTP status
+    #[error("Anthropic API request failed with status {status}{}: {body}", after_retries(.retries))]
+    Http { status: StatusCode, retries: u32, body: String },
+    #[error("Failed to send request to Anthropic API: {0}")]
+    Network(reqwest::Error),
+    #[error("Failed to deserialize Anthropic API response: {0}")]
+    Deserialize(serde_json::Error),
+    /// No content block had text; `block_types` are the types of those there were
+    #[error("Anthropic API returned no text{}", content_blocks(.block_types))]
+    EmptyResponse { block_types: Vec<String> },
+    #[error(transparent)]
+    Recording(#[from] crate::recording::RecordingError),
+}
+
+impl AnthropicError {
+    /// Classify a failed response by its HTTP status
+    fn from_response(status: StatusCode, retries: u32, body: String) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => AnthropicError::Unauthorized { status, body },
+            StatusCode::NOT_FOUND => AnthropicError::NotFound { status, body },
+            // 529 is Anthropic's "overloaded" status
+            _ if status == StatusCode::TOO_MANY_REQUESTS || status.as_u16() == 529 => {
+                AnthropicError::RateLimited { status, retries, body }
+            }
+            _ => AnthropicError::Http { status, retries, body },
+        }
+    }
+    
+    /// What to do about this error when it happens while testing the connection
+    pub fn connection_advice(&self) -> &'static str {
+        match self {
+            AnthropicError::Unauthorized { .. } => {
+                "Your Anthropic API key looks invalid or lacks access. Check ANTHROPIC_API_KEY \
+                 against the keys in the Anthropic Console."
+            }
+            AnthropicError::RateLimited { .. } => {
+                "Anthropic is rate limiting this API key or is overloaded. Wait a minute and try again."
+            }
+            AnthropicError::Http { status, .. } if status.is_server_error() => {
+                "Anthropic's API is having problems. Try again later."
+            }
+            AnthropicError::Network(error) => crate::linear::network_advice(error),
+            _ => "Check ANTHROPIC_API_KEY and, if you changed it, ANTHROPIC_API_BASE.",
+        }
+    }
+    
+    /// Overloaded, rate-limited and server errors are worth retrying; other client
+    /// errors (bad request, invalid key) would fail the same way again
+    fn is_retryable(&self) -> bool {
+        match self {
+            AnthropicError::RateLimited { .. } => true,
+            AnthropicError::Http { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+fn content_blocks(block_types: &[String]) -> String {
+    if block_types.is_empty() {
+        " (empty response)".to_string()
+    } else {
+        format!(" (content blocks: {})", block_types.join(", "))
+    }
+}
+
+fn after_retries(retries: &u32) -> String {
+    if *retries > 0 {
+        format!(" after {} retries", retries)
+    } else {
+        String::new()
+    }
+}
 
 pub struct AnthropicClient {
     client: Client,
     api_key: String,
+    api_base: String,
+    /// Sent as the anthropic-version header of every request
+    api_version: String,
+    context_budget: Option<usize>,
+    max_retries: u32,
+    review_rubric: String,
+    /// System prompt of plan requests, listing the plan sections to write
+    plan_instructions: String,
+    /// Shared background sent before the ticket in every prompt (--context-file)
+    project_context: Option<String>,
+    max_description_length: Option<usize>,
+    limit_comments_chars: Option<usize>,
+    /// Latest comments that lead the prompt, with the description as background
+    /// (--plan-from-comments-only)
+    plan_from_comments: Option<usize>,
+    beta_features: Vec<String>,
+    blocker_keywords: Vec<String>,
+    checklist_output: bool,
+    concise_plan_below: f64,
+    detailed_plan_from: f64,
+    /// Token usage of every request made so far, for the run report
+    usage: Mutex<Vec<RequestUsage>>,
+    /// Records or replays requests (--record, --replay)
+    recorder: Option<Arc<crate::recording::Recorder>>,
+    /// Paces requests under the account's per-minute limits (--anthropic-rpm, --anthropic-tpm)
+    rate_limiter: Option<crate::rate_limit::RateLimiter>,
+}
+
+/// A plan shown during --interactive-refine and the instruction the user gave to change it
+#[derive(Debug, Clone)]
+pub struct PlanTurn {
+    pub plan: String,
+    pub instruction: String,
+}
+
+/// Token usage of a single request, attributed to the ticket it was made for
+#[derive(Debug, Clone)]
+pub struct RequestUsage {
+    pub ticket_id: Option<String>,
+    pub model: String,
+    pub tokens: AnthropicUsage,
+}
+
+impl RequestUsage {
+    /// Cost in dollars at the model's listed price, if it is known
+    pub fn cost(&self) -> Option<f64> {
+        let (input_price, output_price) = model_price(&self.model)?;
+        let input = self.tokens.input_tokens as f64
+            + self.tokens.cache_creation_input_tokens as f64 * CACHE_WRITE_PRICE_FACTOR
+            + self.tokens.cache_read_input_tokens as f64 * CACHE_READ_PRICE_FACTOR;
+        
+        Some((input * input_price + self.tokens.output_tokens as f64 * output_price) / 1_000_000.0)
+    }
 }
 
 impl AnthropicClient {
@@ -15,22 +367,63 @@ impl AnthropicClient {
         Self {
             client: Client::new(),
             api_key: api_key.to_string(),
+            api_base: DEFAULT_ANTHROPIC_API_BASE.to_string(),
+            api_version: DEFAULT_ANTHROPIC_VERSION.to_string(),
+            context_budget: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            review_rubric: DEFAULT_REVIEW_RUBRIC.to_string(),
+            plan_instructions: implementation_plan_instructions(
+                &parse_plan_sections(DEFAULT_PLAN_SECTIONS).unwrap_or_default(),
+            ),
+            project_context: None,
+            max_description_length: None,
+            limit_comments_chars: None,
+            plan_from_comments: None,
+            beta_features: Vec::new(),
+            blocker_keywords: Vec::new(),
+            checklist_output: false,
+            concise_plan_below: DEFAULT_CONCISE_PLAN_BELOW,
+            detailed_plan_from: DEFAULT_DETAILED_PLAN_FROM,
+            usage: Mutex::new(Vec::new()),
+            recorder: None,
+            rate_limiter: None,
         }
     }
     
     /// Create a new client from AppConfig
     pub fn from_config(app_config: &crate::config::AppConfig) -> Option<Self> {
-        app_config.anthropic_api_key.as_ref().map(|key| Self {
-            client: Client::new(),
-            api_key: key.clone(),
+        app_config.anthropic_api_key.as_deref().map(|key| Self {
+            // The proxy settings were validated when the config was loaded
+            client: app_config.http_client().unwrap_or_default(),
+            api_base: app_config.anthropic_api_base.trim_end_matches('/').to_string(),
+            api_version: app_config.anthropic_version.clone(),
+            context_budget: Some(app_config.context_budget),
+            max_retries: app_config.anthropic_max_retries,
+            review_rubric: app_config.review_rubric.clone(),
+            plan_instructions: implementation_plan_instructions(&app_config.plan_sections),
+            project_context: app_config.project_context.clone(),
+            max_description_length: app_config.max_description_length,
+            limit_comments_chars: app_config.limit_comments_chars,
+            plan_from_comments: app_config.plan_from_comments,
+            beta_features: app_config.anthropic_beta.clone(),
+            blocker_keywords: app_config.blocker_keywords.clone(),
+            checklist_output: app_config.checklist_output,
+            concise_plan_below: app_config.concise_plan_below,
+            detailed_plan_from: app_config.detailed_plan_from,
+            recorder: crate::recording::Recorder::from_config(app_config),
+            rate_limiter: crate::rate_limit::RateLimiter::new(app_config.anthropic_rpm, app_config.anthropic_tpm),
+            ..Self::new(key)
         })
     }
     
     /// Test the connection to the Anthropic API
     pub async fn test_connection(&self) -> Result<String> {
         let response = self.generate_text(
+            None,
             "claude-3-7-sonnet-20250219",
+            None,
             "Hello, this is a test message. Please respond with a short greeting.",
+            PLAN_MAX_TOKENS,
         ).await?;
         
         Ok(response)
@@ -38,58 +431,447 @@ impl AnthropicClient {
     
     /// Generate implementation plan for a ticket
     pub async fn generate_implementation_plan(&self, ticket: &Ticket, model: &str) -> Result<String> {
-        // Build the prompt
-        let prompt = self.build_implementation_plan_prompt(ticket);
+        let prompt = self.implementation_plan_prompt(ticket);
         
-        // Call the API
-        let implementation_plan = self.generate_text(model, &prompt).await?;
+        // Call the API, with the shared instructions as a cacheable system prompt
+        let implementation_plan = self
+            .generate_text(Some(ticket), model, Some(&self.plan_instructions), &prompt, self.plan_depth(ticket).max_tokens())
+            .await?;
         
         Ok(implementation_plan)
     }
     
-    /// Build prompt for implementation plan generation
-    fn build_implementation_plan_prompt(&self, ticket: &Ticket) -> String {
-        let mut prompt = String::from(
-            "You are a software engineering expert helping to create implementation plans for software development tickets.\n\n\
-             I'm going to provide you with a ticket from our project management system. Based on the ticket details,\n\
-             generate a detailed implementation plan. The plan should include:\n\n\
-             1. An overview of the task\n\
-             2. Technical requirements and considerations\n\
-             3. Step-by-step implementation approach\n\
-             4. Potential challenges and solutions\n\
-             5. Testing strategy\n\
-             6. Estimated effort (in hours or story points)\n\n\
-             Here's the ticket information:\n\n"
+    /// Refine a plan over several turns (--interactive-refine): the ticket prompt, then
+    /// each earlier plan as the assistant's reply followed by the user's instruction for it
+    pub async fn refine_implementation_plan(
+        &self,
+        ticket: &Ticket,
+        turns: &[PlanTurn],
+        model: &str,
+    ) -> Result<String> {
+        let refined_plan = self
+            .generate_reply(
+                Some(ticket),
+                model,
+                Some(&self.plan_instructions),
+                self.refinement_messages(ticket, turns),
+                self.plan_depth(ticket).max_tokens(),
+            )
+            .await?;
+        
+        Ok(refined_plan)
+    }
+    
+    /// Critique a draft plan against the review rubric and return an improved version.
+    /// This is a second full-size request, so it roughly doubles the cost per ticket.
+    pub async fn review_implementation_plan(
+        &self,
+        ticket: &Ticket,
+        draft_plan: &str,
+        model: &str,
+    ) -> Result<String> {
+        let mut prompt = self.build_ticket_prompt(
+            ticket,
+            &self.plan_instructions,
+            &self.plan_request(ticket),
+            estimate_tokens(draft_plan) + estimate_tokens(&self.review_rubric),
         );
+        prompt.push_str(&format!(
+            "\n\nA first draft of the plan has already been written:\n\n\
+             <draft_plan>\n{}\n</draft_plan>\n\n\
+             Review the draft against this rubric:\n{}\n\n\
+             Fix every gap you find and return only the complete, improved plan.",
+            draft_plan.trim(),
+            self.review_rubric.trim()
+        ));
+        
+        let reviewed_plan = self
+            .generate_text(Some(ticket), model, Some(&self.plan_instructions), &prompt, self.plan_depth(ticket).max_tokens())
+            .await?;
+        
+        Ok(reviewed_plan)
+    }
+    
+    /// Compare a pull request with the ticket's saved plan and return a short gap report (--verify)
+    pub async fn verify_implementation(
+        &self,
+        ticket: &Ticket,
+        plan: &str,
+        pull_request: &str,
+        model: &str,
+    ) -> Result<String> {
+        let mut prompt = self.build_ticket_prompt(
+            ticket,
+            VERIFY_INSTRUCTIONS,
+            VERIFY_REQUEST,
+            estimate_tokens(plan) + estimate_tokens(pull_request),
+        );
+        prompt.push_str(&format!(
+            "\n\n<implementation_plan>\n{}\n</implementation_plan>\n\n\
+             <pull_request>\n{}\n</pull_request>",
+            plan.trim(),
+            pull_request.trim()
+        ));
+        
+        let report = self
+            .generate_text(Some(ticket), model, Some(VERIFY_INSTRUCTIONS), &prompt, VERIFY_MAX_TOKENS)
+            .await?;
+        
+        Ok(report)
+    }
+    
+    /// Generate a brief approach and risk note for a ticket (--summary)
+    pub async fn generate_summary(&self, ticket: &Ticket, model: &str) -> Result<String> {
+        let prompt = self.build_ticket_prompt(ticket, SUMMARY_INSTRUCTIONS, SUMMARY_REQUEST, 0);
+        
+        let summary = self
+            .generate_text(Some(ticket), model, Some(SUMMARY_INSTRUCTIONS), &prompt, SUMMARY_MAX_TOKENS)
+            .await?;
+        
+        Ok(summary)
+    }
+    
+    /// Explain a ticket in plain English: what, why and who's affected (--describe)
+    pub async fn describe_ticket(&self, ticket: &Ticket, model: &str) -> Result<String> {
+        let prompt = self.build_ticket_prompt(ticket, DESCRIBE_INSTRUCTIONS, DESCRIBE_REQUEST, 0);
+        
+        let description = self
+            .generate_text(Some(ticket), model, Some(DESCRIBE_INSTRUCTIONS), &prompt, DESCRIBE_MAX_TOKENS)
+            .await?;
+        
+        Ok(description)
+    }
+    
+    /// Condense the ticket's comment thread into a few bullet points with a small model
+    /// (--compact-comments)
+    pub async fn summarize_comments(&self, ticket: &Ticket) -> Result<String> {
+        let mut comments = ticket.comments.clone();
+        comments.sort_by_key(|c| c.created_at);
+        
+        let mut prompt = format!("Comments on the ticket \"{}\", oldest first:\n\n", ticket.title);
+        for comment in &comments {
+            prompt.push_str(&format!(
+                "- {} ({}): {}\n",
+                comment.user.as_deref().unwrap_or("Unknown"),
+                comment.created_at.format("%Y-%m-%d"),
+                comment.body
+            ));
+        }
+        
+        let summary = self
+            .generate_text(
+                Some(ticket),
+                COMPACT_COMMENTS_MODEL,
+                Some(COMPACT_COMMENTS_INSTRUCTIONS),
+                &prompt,
+                COMPACT_COMMENTS_MAX_TOKENS,
+            )
+            .await?;
+        
+        Ok(summary)
+    }
+    
+    /// Revise an existing implementation plan with the ticket's current information
+    pub async fn revise_implementation_plan(
+        &self,
+        ticket: &Ticket,
+        existing_plan: &str,
+        model: &str,
+    ) -> Result<String> {
+        let prompt = self.revision_prompt(ticket, existing_plan);
+        
+        let implementation_plan = self
+            .generate_text(Some(ticket), model, Some(&self.plan_instructions), &prompt, self.plan_depth(ticket).max_tokens())
+            .await?;
+        
+        Ok(implementation_plan)
+    }
+    
+    /// The message sent (after the `plan_instructions` system prompt) to
+    /// generate a plan for the ticket
+    pub fn implementation_plan_prompt(&self, ticket: &Ticket) -> String {
+        self.build_ticket_prompt(ticket, &self.plan_instructions, &self.plan_request(ticket), 0)
+    }
+    
+    /// The conversation sent to refine a plan: the ticket prompt, then each earlier plan
+    /// and the instruction given for it
+    pub fn refinement_messages(&self, ticket: &Ticket, turns: &[PlanTurn]) -> Vec<AnthropicMessage> {
+        let mut messages = vec![AnthropicMessage::user(&self.implementation_plan_prompt(ticket))];
+        for turn in turns {
+            messages.push(AnthropicMessage::assistant(&turn.plan));
+            messages.push(AnthropicMessage::user(&format!(
+                "Revise the plan following this instruction:\n{}\n\nReturn the complete revised plan.",
+                turn.instruction
+            )));
+        }
+        messages
+    }
+    
+    /// The message sent to revise an existing plan with the ticket's current information
+    pub fn revision_prompt(&self, ticket: &Ticket, existing_plan: &str) -> String {
+        // Leave room in the budget for the existing plan
+        let mut prompt = self.build_ticket_prompt(
+            ticket,
+            &self.plan_instructions,
+            &self.plan_request(ticket),
+            estimate_tokens(existing_plan),
+        );
+        prompt.push_str(&format!(
+            "\n\nAn implementation plan already exists for this ticket:\n\n\
+             <existing_plan>\n{}\n</existing_plan>\n\n\
+             Revise and extend the existing plan rather than starting from scratch: keep what is \
+             still accurate, update what the ticket information above has changed, and add \
+             anything missing. Return the complete revised plan.",
+            existing_plan.trim()
+        ));
+        prompt
+    }
+    
+    /// Upper estimate in dollars of generating a plan for the ticket: the estimated prompt
+    /// size plus the full output token limit. `None` for models without a known price.
+    pub fn estimate_plan_cost(&self, ticket: &Ticket, model: &str) -> Option<f64> {
+        let (input_price, output_price) = model_price(model)?;
+        
+        let prompt = self.build_ticket_prompt(ticket, &self.plan_instructions, &self.plan_request(ticket), 0);
+        let input_tokens = estimate_tokens(&prompt) + estimate_tokens(&self.plan_instructions);
+        let output_tokens = self.plan_depth(ticket).max_tokens() as usize;
+        
+        Some((input_tokens as f64 * input_price + output_tokens as f64 * output_price) / 1_000_000.0)
+    }
+    
+    /// System prompt of plan requests
+    pub fn plan_instructions(&self) -> &str {
+        &self.plan_instructions
+    }
+    
+    /// Token usage of every request made by this client so far
+    pub fn usage(&self) -> Vec<RequestUsage> {
+        self.usage.lock().map(|usage| usage.clone()).unwrap_or_default()
+    }
+    
+    /// How detailed the plan for a ticket should be, based on its estimate.
+    /// Unestimated tickets get a standard plan.
+    fn plan_depth(&self, ticket: &Ticket) -> PlanDepth {
+        match ticket.estimate {
+            Some(estimate) if estimate < self.concise_plan_below => PlanDepth::Concise,
+            Some(estimate) if estimate >= self.detailed_plan_from => PlanDepth::Detailed,
+            _ => PlanDepth::Standard,
+        }
+    }
+    
+    /// Final request of the prompt for a full plan, scaled to the ticket's estimate and
+    /// asking for checkbox steps if enabled
+    fn plan_request(&self, ticket: &Ticket) -> String {
+        let mut request = PLAN_REQUEST.to_string();
+        match self.plan_depth(ticket) {
+            PlanDepth::Concise => request.push_str(CONCISE_PLAN_REQUEST),
+            PlanDepth::Standard => {}
+            PlanDepth::Detailed => request.push_str(DETAILED_PLAN_REQUEST),
+        }
+        if self.latest_comments(ticket).is_some() {
+            request.push_str(PLAN_FROM_COMMENTS_REQUEST);
+        }
+        if self.checklist_output {
+            request.push_str(CHECKLIST_REQUEST);
+        }
+        request
+    }
+    
+    /// The latest comments, oldest first, when they lead the prompt (--plan-from-comments-only).
+    /// Tickets without comments, or whose comments were condensed, are prompted as usual.
+    fn latest_comments<'a>(&self, ticket: &'a Ticket) -> Option<Vec<&'a Comment>> {
+        let count = self.plan_from_comments?;
+        if ticket.comments.is_empty() || ticket.comment_summary.is_some() {
+            return None;
+        }
+        let mut comments: Vec<&Comment> = ticket.comments.iter().collect();
+        comments.sort_by_key(|comment| comment.created_at);
+        Some(comments.split_off(comments.len().saturating_sub(count)))
+    }
+    
+    /// Build the per-ticket prompt ending with `request`, trimming the least important
+    /// sections (oldest comments, then related and child tickets) so that it fits the
+    /// context budget together with the `instructions` and `reserved_tokens`
+    fn build_ticket_prompt(
+        &self,
+        ticket: &Ticket,
+        instructions: &str,
+        request: &str,
+        reserved_tokens: usize,
+    ) -> String {
+        let prompt = self.render_ticket_prompt(ticket, request, &[]);
+        let budget = match self.context_budget {
+            Some(budget) => budget
+                .saturating_sub(estimate_tokens(instructions))
+                .saturating_sub(reserved_tokens),
+            None => return prompt,
+        };
+        if estimate_tokens(&prompt) <= budget {
+            return prompt;
+        }
+        
+        let mut trimmed = ticket.clone();
+        trimmed.comments.sort_by_key(|c| c.created_at);
+        
+        let mut dropped_comments = 0;
+        let mut dropped_related = 0;
+        let mut dropped_children = 0;
+        let mut prompt = prompt;
+        
+        while estimate_tokens(&prompt) > budget {
+            // A comment summary replaces the comments in the prompt, so dropping them won't help
+            if !trimmed.comments.is_empty() && trimmed.comment_summary.is_none() {
+                trimmed.comments.remove(0);
+                dropped_comments += 1;
+            } else if trimmed.related_tickets.pop().is_some() {
+                dropped_related += 1;
+            } else if trimmed.children.pop().is_some() {
+                dropped_children += 1;
+            } else {
+                // Only the essential sections are left; send them as they are
+                break;
+            }
+            
+            let mut notes = Vec::new();
+            if dropped_comments > 0 {
+                notes.push(format!("{} oldest comment(s)", dropped_comments));
+            }
+            if dropped_related > 0 {
+                notes.push(format!("{} related ticket(s)", dropped_related));
+            }
+            if dropped_children > 0 {
+                notes.push(format!("{} child ticket(s)", dropped_children));
+            }
+            prompt = self.render_ticket_prompt(&trimmed, request, &notes);
+        }
+        
+        prompt
+    }
+    
+    /// Render the ticket prompt, noting any sections that were trimmed
+    fn render_ticket_prompt(&self, ticket: &Ticket, request: &str, trimmed: &[String]) -> String {
+        let mut prompt = String::new();
+        // The project context is never trimmed; it is shared by every ticket of the run
+        if let Some(context) = &self.project_context {
+            prompt.push_str(&format!(
+                "Background on the project this ticket belongs to, shared by every ticket planned \
+                 with it. Use it for context only; the ticket below is what to work on.\n\n\
+                 <project_context>\n{}\n</project_context>\n\n",
+                context
+            ));
+        }
+        prompt.push_str("Here's the ticket information:\n\n");
+        
+        let latest_comments = self.latest_comments(ticket);
+        let format_comment = |comment: &Comment| {
+            format!(
+                "- {} ({}): {}\n",
+                comment.user.as_deref().unwrap_or("Unknown"),
+                comment.created_at.format("%Y-%m-%d"),
+                comment.limited_body(self.limit_comments_chars)
+            )
+        };
         
         // Add ticket details
         prompt.push_str(&format!("Title: {}\n", ticket.title));
-        prompt.push_str(&format!("Description: {}\n", ticket.description));
+        if let Some(latest) = &latest_comments {
+            // The discussion has moved on from the description, so it leads and the
+            // description only gives background
+            prompt.push_str(&format!(
+                "\nLatest comments ({} of {}, oldest first; they reflect the current direction of the work):\n",
+                latest.len(),
+                ticket.comments.len()
+            ));
+            for comment in latest {
+                prompt.push_str(&format_comment(comment));
+            }
+            let description = match &ticket.description {
+                Some(description) => truncate_middle(description, COMMENTS_LEAD_DESCRIPTION_CHARS),
+                None => "None".to_string(),
+            };
+            prompt.push_str(&format!(
+                "\nOriginal description (may be outdated; where it disagrees with the comments above, \
+                 follow the comments): {}\n\n",
+                description
+            ));
+        } else {
+            let description = match (&ticket.description, self.max_description_length) {
+                (None, _) => "None".to_string(),
+                (Some(description), Some(max_chars)) => truncate_middle(description, max_chars),
+                (Some(description), None) => description.clone(),
+            };
+            prompt.push_str(&format!("Description: {}\n", description));
+        }
+        
+        // Repeat the acceptance criteria on their own so they aren't lost in the description;
+        // outdated criteria would pull a comments-led plan back to the description
+        if let Some(criteria) = ticket.acceptance_criteria().filter(|_| latest_comments.is_none()) {
+            prompt.push_str(&format!(
+                "\nACCEPTANCE CRITERIA (from the description above; the plan must satisfy all of them):\n{}\n\n",
+                criteria
+            ));
+        }
+        // Empty template sections invite made-up detail; name them so the model doesn't fill them in
+        let empty_sections = ticket.empty_template_sections();
+        if !empty_sections.is_empty() && latest_comments.is_none() {
+            prompt.push_str(&format!(
+                "\nNote: these sections of the description are unfilled template placeholders: {}. \
+                 Don't invent details for them; list what is missing as open questions instead.\n\n",
+                empty_sections.join(", ")
+            ));
+        }
         prompt.push_str(&format!("Priority: {}\n", ticket.priority));
-        prompt.push_str(&format!("Estimate: {}\n", ticket.estimate.map_or("Not estimated".to_string(), |e| e.to_string())));
+        prompt.push_str(&format!("Estimate: {}\n", format_estimate(ticket.estimate)));
+        if let Some(rollup) = ticket.children_estimate() {
+            prompt.push_str(&format!("{}\n", rollup));
+        }
         prompt.push_str(&format!("State: {}\n", ticket.state));
         prompt.push_str(&format!("Labels: {}\n", if ticket.labels.is_empty() { "None".to_string() } else { ticket.labels.join(", ") }));
+        prompt.push_str(&format!("Stakeholders: {}\n", ticket.stakeholders()));
+        // An attached pull request tells whether work has started, and the branch name shows
+        // the team's branch convention
+        if let Some(branch) = &ticket.branch_name {
+            prompt.push_str(&format!("Git branch: {}\n", branch));
+        }
+        if ticket.pull_requests.is_empty() {
+            if ticket.branch_name.is_some() {
+                prompt.push_str("Pull requests: None linked, so work has likely not started\n");
+            }
+        } else {
+            prompt.push_str("Pull requests:\n");
+            for pull_request in &ticket.pull_requests {
+                prompt.push_str(&format!("{}\n", pull_request.to_markdown()));
+            }
+        }
         prompt.push_str(&format!("Created: {}\n", ticket.created_at.format("%Y-%m-%d")));
         prompt.push_str(&format!("Updated: {}\n\n", ticket.updated_at.format("%Y-%m-%d")));
         
-        // Add comments
-        prompt.push_str("Comments:\n");
-        if ticket.comments.is_empty() {
-            prompt.push_str("No comments\n");
-        } else {
-            for comment in &ticket.comments {
-                let user_str = match &comment.user {
-                    Some(u) => u.clone(),
-                    None => "Unknown".to_string()
-                };
-                prompt.push_str(&format!("- {} ({}): {}\n", 
-                    user_str, 
-                    comment.created_at.format("%Y-%m-%d"), 
-                    comment.body
+        // Add comments, unless the latest ones already lead the prompt
+        if latest_comments.is_none() {
+            prompt.push_str("Comments:\n");
+            if let Some(summary) = &ticket.comment_summary {
+                prompt.push_str(&format!(
+                    "(condensed from {} comment(s))\n{}\n",
+                    ticket.comments.len(),
+                    summary.trim()
                 ));
+            } else if ticket.comments.is_empty() {
+                prompt.push_str("No comments\n");
+            } else {
+                for comment in &ticket.comments {
+                    prompt.push_str(&format_comment(comment));
+                }
             }
+            prompt.push('\n');
+        }
+        
+        // Call out comments that look like blockers; they are also in the full list above
+        if let Some(blockers) = ticket.format_potential_blockers(&self.blocker_keywords) {
+            prompt.push_str(&format!(
+                "Potential blockers from discussion (address these in the plan's risks):\n{}\n\n",
+                blockers
+            ));
         }
-        prompt.push_str("\n");
         
         // Add parent ticket
         if let Some(parent) = &ticket.parent {
@@ -98,16 +880,34 @@ impl AnthropicClient {
             prompt.push_str("No parent ticket\n\n");
         }
         
+        // Add the repository overview (--repo-path)
+        if let Some(summary) = &ticket.repo_summary {
+            prompt.push_str(&format!(
+                "Repository the ticket will be implemented in (an overview, not the full code). \
+                 Refer to its actual modules and files where they fit:\n\n\
+                 <repository>\n{}</repository>\n\n",
+                summary
+            ));
+        }
+        
         // Add child tickets
         prompt.push_str("Child Tickets:\n");
         if ticket.children.is_empty() {
             prompt.push_str("No child tickets\n");
         } else {
             for child in &ticket.children {
-                prompt.push_str(&format!("- {} (State: {})\n", child.title, child.state));
+                match child.estimate {
+                    Some(estimate) => prompt.push_str(&format!(
+                        "- {} (State: {}, Estimate: {})\n",
+                        child.title,
+                        child.state,
+                        format_estimate(Some(estimate))
+                    )),
+                    None => prompt.push_str(&format!("- {} (State: {})\n", child.title, child.state)),
+                }
             }
         }
-        prompt.push_str("\n");
+        prompt.push('\n');
         
         // Add related tickets
         prompt.push_str("Related Tickets:\n");
@@ -119,58 +919,583 @@ impl AnthropicClient {
                     Some(a) => a.clone(),
                     None => "Unassigned".to_string()
                 };
-                prompt.push_str(&format!("- {} (State: {}, Assignee: {})\n", 
+                prompt.push_str(&format!("- {}: {} {} (State: {}, Assignee: {})\n", 
+                    related.relation_label(),
+                    related.id,
                     related.title, 
                     related.state, 
                     assignee_str
                 ));
+                // Fetched with --deep-relations
+                if let Some(description) = &related.description {
+                    let description = crate::ui::truncate_chars(description.trim(), RELATED_DESCRIPTION_CHARS);
+                    for line in description.lines() {
+                        prompt.push_str(&format!("  > {}\n", line));
+                    }
+                }
             }
         }
-        prompt.push_str("\n");
+        prompt.push('\n');
+        
+        // Let the model know the ticket context is incomplete
+        if !trimmed.is_empty() {
+            prompt.push_str(&format!(
+                "Note: to fit the context budget, the following were omitted: {}.\n\n",
+                trimmed.join(", ")
+            ));
+        }
         
         // Final instruction
-        prompt.push_str("Please provide a detailed implementation plan for this ticket.");
+        prompt.push_str(request);
         
         prompt
     }
     
-    /// Generate text using the Anthropic API
-    async fn generate_text(&self, model: &str, prompt: &str) -> Result<String> {
+    /// Generate text using the Anthropic API. The optional system prompt is marked
+    /// for prompt caching since it is repeated verbatim across requests.
+    async fn generate_text(
+        &self,
+        ticket: Option<&Ticket>,
+        model: &str,
+        system: Option<&str>,
+        prompt: &str,
+        max_tokens: u32,
+    ) -> Result<String, AnthropicError> {
+        self.generate_reply(ticket, model, system, vec![AnthropicMessage::user(prompt)], max_tokens)
+            .await
+    }
+    
+    /// Generate the next reply of a conversation, which alternates user and assistant
+    /// messages and ends with a user message
+    async fn generate_reply(
+        &self,
+        ticket: Option<&Ticket>,
+        model: &str,
+        system: Option<&str>,
+        messages: Vec<AnthropicMessage>,
+        max_tokens: u32,
+    ) -> Result<String, AnthropicError> {
         let request = AnthropicRequest {
             model: model.to_string(),
-            max_tokens: 4000,
-            messages: vec![
-                AnthropicMessage {
-                    role: "user".to_string(),
-                    content: prompt.to_string(),
-                },
-            ],
+            max_tokens,
+            system: system
+                .map(|text| vec![AnthropicContentBlock::text(text).cached()])
+                .unwrap_or_default(),
+            messages,
         };
         
-        let response = self.client.post(ANTHROPIC_API_URL)
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("content-type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send request to Anthropic API")?;
+        // Counted against --anthropic-tpm: the prompt's estimated size and the longest reply
+        // allowed, corrected with the actual usage once known
+        let prompt_text = request.system.iter()
+            .chain(request.messages.iter().flat_map(|message| &message.content))
+            .map(|block| block.text.as_str())
+            .collect::<String>();
+        let reserved_tokens = (estimate_tokens(&prompt_text) as u32).saturating_add(max_tokens);
+        
+        let mut attempt = 0;
+        let response = loop {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire(reserved_tokens).await;
+            }
+            let (status, retry_delay, response_text) = match self.post(&request).await {
+                Ok(posted) => posted,
+                Err(e) => {
+                    self.settle_tokens(reserved_tokens, 0);
+                    return Err(e);
+                }
+            };
+            if status.is_success() {
+                break response_text;
+            }
+            self.settle_tokens(reserved_tokens, 0);
             
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            anyhow::bail!("Anthropic API request failed with status {}: {}", status, error_text);
+            let delay = retry_delay.unwrap_or_else(|| backoff_delay(attempt));
+            let error = AnthropicError::from_response(status, attempt, response_text);
+            if attempt < self.max_retries && error.is_retryable() {
+                attempt += 1;
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+            
+            return Err(error);
+        };
+        
+        let anthropic_response: AnthropicResponse = serde_json::from_str(&response)
+            .map_err(AnthropicError::Deserialize)?;
+        
+        // Cache reads don't count towards the input token limits
+        let tokens = &anthropic_response.usage;
+        let used_tokens = tokens.input_tokens + tokens.cache_creation_input_tokens + tokens.output_tokens;
+        self.settle_tokens(reserved_tokens, u32::try_from(used_tokens).unwrap_or(u32::MAX));
+        
+        if let Ok(mut usage) = self.usage.lock() {
+            usage.push(RequestUsage {
+                ticket_id: ticket.map(|ticket| ticket.id.clone()),
+                model: model.to_string(),
+                tokens: anthropic_response.usage.clone(),
+            });
         }
         
-        let anthropic_response: AnthropicResponse = response.json()
+        // Join the text blocks; other blocks (tool use, thinking) aren't part of the reply
+        anthropic_response.text().ok_or_else(|| AnthropicError::EmptyResponse {
+            block_types: anthropic_response.content.iter().map(|block| block.block_type.clone()).collect(),
+        })
+    }
+    
+    /// Correct the tokens counted by the rate limiter for a request with what it used
+    fn settle_tokens(&self, reserved: u32, used: u32) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.settle(reserved, used);
+        }
+    }
+    
+    /// Send a request and read the raw response with the delay asked for by its
+    /// retry-after header, or take it from the recording when replaying. Responses are
+    /// recorded when recording.
+    async fn post(&self, request: &AnthropicRequest) -> Result<(StatusCode, Option<Duration>, String), AnthropicError> {
+        let payload = serde_json::to_value(request).map_err(AnthropicError::Deserialize)?;
+        if let Some(recorder) = self.recorder.as_ref().filter(|recorder| recorder.is_replaying()) {
+            let (status, response_text) = recorder.replay("anthropic", &payload)?;
+            return Ok((status, None, response_text));
+        }
+        
+        let url = format!("{}{}", self.api_base, MESSAGES_PATH);
+        let mut request_builder = self.client.post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", &self.api_version)
+            .header("content-type", "application/json");
+        // Beta features are passed through verbatim; the API rejects unknown ones
+        if !self.beta_features.is_empty() {
+            request_builder = request_builder.header("anthropic-beta", self.beta_features.join(","));
+        }
+        
+        let response = request_builder
+            .json(&payload)
+            .send()
             .await
-            .context("Failed to deserialize Anthropic API response")?;
-            
-        // Extract the text from the first content item
-        if let Some(content) = anthropic_response.content.first() {
-            Ok(content.text.clone())
-        } else {
-            anyhow::bail!("Anthropic API returned empty response")
+            .map_err(AnthropicError::Network)?;
+        let status = response.status();
+        let delay = retry_after(&response);
+        let response_text = response.text().await.map_err(AnthropicError::Network)?;
+        
+        if let Some(recorder) = &self.recorder {
+            recorder.record("anthropic", &url, &payload, status, &response_text)?;
+        }
+        Ok((status, delay, response_text))
+    }
+}
+
+/// Listed price in dollars per million input and output tokens for a model
+fn model_price(model: &str) -> Option<(f64, f64)> {
+    MODEL_PRICES
+        .iter()
+        .find(|(family, _, _)| model.contains(family))
+        .map(|&(_, input_price, output_price)| (input_price, output_price))
+}
+
+/// Level of detail requested for a plan, chosen from the ticket's estimate
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlanDepth {
+    Concise,
+    Standard,
+    Detailed,
+}
+
+impl PlanDepth {
+    fn max_tokens(self) -> u32 {
+        match self {
+            PlanDepth::Concise => CONCISE_PLAN_MAX_TOKENS,
+            PlanDepth::Standard => PLAN_MAX_TOKENS,
+            PlanDepth::Detailed => DETAILED_PLAN_MAX_TOKENS,
+        }
+    }
+}
+
+/// Extract the items of a markdown checkbox list (`- [ ] step`, also `* [x] step`) from a plan
+pub fn parse_checklist(plan: &str) -> Vec<String> {
+    plan.lines()
+        .filter_map(|line| {
+            let item = line.trim_start().strip_prefix(['-', '*'])?.trim_start();
+            let item = item
+                .strip_prefix("[ ]")
+                .or_else(|| item.strip_prefix("[x]"))
+                .or_else(|| item.strip_prefix("[X]"))?;
+            let item = item.trim();
+            (!item.is_empty()).then(|| item.to_string())
+        })
+        .collect()
+}
+
+/// Delay requested by the server through the retry-after header (in seconds)
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let seconds: f64 = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    
+    if !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+    Some(Duration::from_secs_f64(seconds).min(MAX_RETRY_DELAY))
+}
+
+/// Exponential backoff with up to 50% jitter so concurrent runs don't retry in lockstep
+fn backoff_delay(attempt: u32) -> Duration {
+    let base = INITIAL_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_RETRY_DELAY);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let jitter = base.mul_f64(f64::from(nanos % 1000) / 2000.0);
+    base + jitter
+}
+
+/// Shorten text to about `max_chars` characters, keeping the head and the tail so both
+/// the context and the conclusion survive, with a marker where the middle was cut
+fn truncate_middle(text: &str, max_chars: usize) -> String {
+    let total = text.chars().count();
+    if total <= max_chars {
+        return text.to_string();
+    }
+    
+    let head_chars = max_chars.div_ceil(2);
+    let tail_chars = max_chars - head_chars;
+    let head: String = text.chars().take(head_chars).collect();
+    let tail: String = text.chars().skip(total - tail_chars).collect();
+    
+    format!(
+        "{}\n\n[description truncated: {} characters omitted]\n\n{}",
+        head.trim_end(),
+        total - head_chars - tail_chars,
+        tail.trim_start()
+    )
+}
+
+/// Estimate the number of tokens in a piece of text
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client_for(server: &MockServer) -> AnthropicClient {
+        AnthropicClient {
+            api_base: server.uri(),
+            ..AnthropicClient::new("sk-test-key")
         }
     }
-}
\ No newline at end of file
+
+    fn ticket(title: &str, description: Option<&str>) -> Ticket {
+        Ticket {
+            description: description.map(str::to_string),
+            priority: 2,
+            ..crate::models::test_ticket("T-1", title)
+        }
+    }
+
+    fn text_response(text: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "content": [{ "type": "text", "text": text }]
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_connection_returns_the_response_text() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(MESSAGES_PATH))
+            .and(header("x-api-key", "sk-test-key"))
+            .and(header("anthropic-version", DEFAULT_ANTHROPIC_VERSION))
+            .respond_with(text_response("Hello!"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let text = client_for(&server).test_connection().await.unwrap();
+        assert_eq!(text, "Hello!");
+    }
+
+    #[tokio::test]
+    async fn text_blocks_are_joined_and_other_blocks_skipped() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "content": [
+                    { "type": "thinking", "thinking": "Let me greet them" },
+                    { "type": "text", "text": "Hello, " },
+                    { "type": "tool_use", "id": "tool_1", "name": "lookup", "input": {} },
+                    { "type": "text", "text": "world!" }
+                ]
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "content": [{ "type": "tool_use", "id": "tool_1", "name": "lookup", "input": {} }]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = client_for(&server);
+        assert_eq!(client.test_connection().await.unwrap(), "Hello, world!");
+        let err = client.test_connection().await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<AnthropicError>(), Some(AnthropicError::EmptyResponse { .. })));
+        assert_eq!(err.to_string(), "Anthropic API returned no text (content blocks: tool_use)");
+    }
+
+    #[tokio::test]
+    async fn plan_instructions_are_sent_as_a_cached_system_prompt() {
+        let server = MockServer::start().await;
+        let client = client_for(&server);
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({
+                "system": [{
+                    "type": "text",
+                    "text": client.plan_instructions,
+                    "cache_control": { "type": "ephemeral" }
+                }]
+            })))
+            .respond_with(text_response("The plan"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let ticket = ticket("Add caching", None);
+        let plan = client.generate_implementation_plan(&ticket, "claude-3-7-sonnet-20250219").await.unwrap();
+        assert_eq!(plan, "The plan");
+
+        // The ticket itself changes every request and is never marked for caching
+        let message = serde_json::to_value(AnthropicMessage::user("Ticket")).unwrap();
+        assert!(message["content"][0].get("cache_control").is_none());
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "type": "error",
+                "error": { "type": "invalid_request_error", "message": "bad model" }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client_for(&server).test_connection().await.unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("400"), "{}", message);
+        assert!(message.contains("bad model"), "{}", message);
+        assert!(matches!(err.downcast_ref::<AnthropicError>(), Some(AnthropicError::Http { .. })));
+    }
+
+    #[tokio::test]
+    async fn overloaded_requests_are_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(529).insert_header("retry-after", "0"))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(text_response("Hello after retries"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let text = client_for(&server).test_connection().await.unwrap();
+        assert_eq!(text, "Hello after retries");
+    }
+
+    #[test]
+    fn default_plan_sections_keep_the_original_instructions() {
+        let sections = parse_plan_sections(DEFAULT_PLAN_SECTIONS).unwrap();
+        assert_eq!(
+            implementation_plan_instructions(&sections),
+            "You are a software engineering expert helping to create implementation plans for software development tickets.\n\n\
+             I'm going to provide you with a ticket from our project management system. Based on the ticket details,\n\
+             generate a detailed implementation plan. The plan should include:\n\n\
+             1. An overview of the task\n\
+             2. Technical requirements and considerations\n\
+             3. Step-by-step implementation approach\n\
+             4. Potential challenges and solutions\n\
+             5. Testing strategy\n\
+             6. Estimated effort (in hours or story points)"
+        );
+    }
+
+    #[test]
+    fn plan_sections_accept_known_names_and_free_text() {
+        let sections = parse_plan_sections("Overview, rollback,Data migration steps").unwrap();
+        assert_eq!(sections, ["An overview of the task", "Rollback plan", "Data migration steps"]);
+
+        let error = parse_plan_sections("overview,testng").unwrap_err().to_string();
+        assert!(error.contains("'testng'"), "{}", error);
+        assert!(parse_plan_sections(" , ").is_err());
+    }
+
+    #[test]
+    fn project_context_comes_first_and_is_never_trimmed() {
+        let mut ticket = ticket("Add caching", Some("Cache the ticket list"));
+        ticket.comments = (0..50)
+            .map(|i| crate::models::Comment {
+                id: i.to_string(),
+                body: format!("Comment number {} {}", i, "padding ".repeat(20)),
+                created_at: chrono::Utc::now(),
+                user: None,
+            })
+            .collect();
+        let client = AnthropicClient {
+            project_context: Some("We are moving the API to Rust.".to_string()),
+            context_budget: Some(1_000),
+            ..AnthropicClient::new("test-key")
+        };
+
+        let prompt = client.implementation_plan_prompt(&ticket);
+        let context = prompt.find("<project_context>\nWe are moving the API to Rust.\n</project_context>").unwrap();
+        assert!(context < prompt.find("Here's the ticket information").unwrap());
+        assert!(prompt.contains("oldest comment(s)"));
+    }
+
+    #[test]
+    fn long_descriptions_are_truncated_in_the_middle() {
+        let description = format!("Start of the report. {} End of the report.", "log line ".repeat(200));
+        let mut ticket = ticket("Fix the crash", Some(&description));
+        let client = AnthropicClient {
+            max_description_length: Some(100),
+            ..AnthropicClient::new("test-key")
+        };
+
+        let prompt = client.implementation_plan_prompt(&ticket);
+        assert!(prompt.contains("Description: Start of the report."), "{}", prompt);
+        assert!(prompt.contains("[description truncated: 1740 characters omitted]"), "{}", prompt);
+        assert!(prompt.contains("End of the report.\n"));
+        assert!(!prompt.contains(&description));
+
+        // A description within the limit is sent as it is
+        ticket.description = Some("Short description".to_string());
+        let prompt = client.implementation_plan_prompt(&ticket);
+        assert!(prompt.contains("Description: Short description\n"));
+        assert!(!prompt.contains("[description truncated"));
+    }
+
+    #[test]
+    fn oversized_prompts_are_trimmed_to_the_budget() {
+        let mut ticket = ticket("Add caching", Some("Cache the ticket list"));
+        let start = chrono::Utc::now() - chrono::Duration::days(300);
+        ticket.comments = (0..300)
+            .map(|i| crate::models::Comment {
+                id: i.to_string(),
+                body: format!("Comment number {} {}", i, "padding ".repeat(40)),
+                created_at: start + chrono::Duration::days(i),
+                user: None,
+            })
+            .collect();
+        let related = |i: usize| crate::models::RelatedTicket {
+            id: format!("T-{}", i + 100),
+            title: format!("Related ticket {} {}", i, "padding ".repeat(10)),
+            state: "Todo".to_string(),
+            assignee: None,
+            relation_type: "related".to_string(),
+            resolved: false,
+            estimate: None,
+            description: None,
+        };
+        ticket.related_tickets = (0..100).map(related).collect();
+        ticket.children = (0..100).map(related).collect();
+        let budget = 2_000;
+        let client = AnthropicClient {
+            context_budget: Some(budget),
+            ..AnthropicClient::new("test-key")
+        };
+
+        let untrimmed = AnthropicClient::new("test-key").implementation_plan_prompt(&ticket);
+        assert!(estimate_tokens(&untrimmed) > 10 * budget);
+        let prompt = client.implementation_plan_prompt(&ticket);
+        assert!(estimate_tokens(&prompt) + estimate_tokens(&client.plan_instructions) <= budget);
+        // The oldest comments go first, and the essential sections are never trimmed
+        assert!(prompt.contains("oldest comment(s)"));
+        assert!(!prompt.contains("Comment number 0 "));
+        assert!(prompt.contains("Title: Add caching\nDescription: Cache the ticket list\n"));
+        assert!(prompt.contains("State: Todo"));
+        assert!(prompt.ends_with(PLAN_REQUEST));
+    }
+
+    #[test]
+    fn latest_comments_lead_the_prompt_when_planning_from_comments() {
+        let mut ticket = ticket("Add caching", Some("Cache the ticket list in Redis\n\n## Acceptance Criteria\n- Redis is used"));
+        let start = chrono::Utc::now() - chrono::Duration::days(10);
+        ticket.comments = (0..4)
+            .rev()
+            .map(|i| crate::models::Comment {
+                id: i.to_string(),
+                body: format!("Comment number {}", i),
+                created_at: start + chrono::Duration::days(i),
+                user: Some("Ana".to_string()),
+            })
+            .collect();
+        let client = AnthropicClient {
+            plan_from_comments: Some(2),
+            ..AnthropicClient::new("test-key")
+        };
+
+        let prompt = client.implementation_plan_prompt(&ticket);
+        assert!(prompt.contains("Latest comments (2 of 4"), "{}", prompt);
+        assert!(!prompt.contains("Comment number 1") && !prompt.contains("ACCEPTANCE CRITERIA"));
+        let latest = prompt.find("Comment number 3").unwrap();
+        assert!(prompt.find("Comment number 2").unwrap() < latest);
+        assert!(latest < prompt.find("Original description (may be outdated").unwrap());
+        assert!(prompt.contains(PLAN_FROM_COMMENTS_REQUEST));
+
+        // Without comments left, the ticket is planned as usual
+        ticket.comments.clear();
+        let prompt = client.implementation_plan_prompt(&ticket);
+        assert!(prompt.contains("Description: Cache the ticket list") && prompt.contains("ACCEPTANCE CRITERIA"));
+    }
+
+    #[tokio::test]
+    async fn refinements_send_earlier_plans_as_assistant_turns() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(text_response("Third plan"))
+            .mount(&server)
+            .await;
+
+        let ticket = ticket("Add caching", Some("Cache the ticket list"));
+        let turns = [
+            PlanTurn { plan: "First plan".to_string(), instruction: "Use Redis".to_string() },
+            PlanTurn { plan: "Second plan".to_string(), instruction: "Add tests".to_string() },
+        ];
+
+        let plan = client_for(&server)
+            .refine_implementation_plan(&ticket, &turns, "claude-test")
+            .await
+            .unwrap();
+        assert_eq!(plan, "Third plan");
+
+        let requests = server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        let messages = body["messages"].as_array().unwrap();
+        let roles: Vec<&str> = messages.iter().map(|m| m["role"].as_str().unwrap()).collect();
+        assert_eq!(roles, ["user", "assistant", "user", "assistant", "user"]);
+
+        let text = |i: usize| messages[i]["content"][0]["text"].as_str().unwrap();
+        assert!(text(0).contains("Add caching"));
+        assert_eq!(text(1), "First plan");
+        assert!(text(2).contains("Use Redis"));
+        assert_eq!(text(3), "Second plan");
+        assert!(text(4).contains("Add tests"));
+    }
+}
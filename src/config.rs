@@ -1,24 +1,393 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use colored::Color;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use dirs::home_dir;
 use std::env;
 use std::fs;
 use std::io::Write;
 
+use crate::linear::{StateFilter, StateSelection, STATE_TYPES};
+use crate::models::Ticket;
 use crate::Args;
 
 const DEFAULT_ENV_FILENAME: &str = ".env";
+/// Optional file with per-team settings, looked up next to the .env locations
+const CONFIG_FILENAME: &str = "config.toml";
 const DEFAULT_CONFIG_DIR: &str = ".linear-agent";
+/// Config directory name under $XDG_CONFIG_HOME (or ~/.config)
+const XDG_CONFIG_DIR: &str = "linear-agent";
+const DEFAULT_CONTEXT_BUDGET: usize = 150_000;
+const DEFAULT_MAX_TICKETS_PER_RUN: usize = 20;
+const DEFAULT_BLOCKER_KEYWORDS: [&str; 9] = [
+    "blocked",
+    "blocker",
+    "waiting on",
+    "waiting for",
+    "depends on",
+    "dependency",
+    "on hold",
+    "can't proceed",
+    "cannot proceed",
+];
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AppConfig {
     pub linear_api_key: String,
     pub anthropic_api_key: Option<String>,
+    /// Empty when no team is configured, in which case it is looked up after connecting
     pub linear_team_name: String,
     pub linear_agent_user: String,
     pub linear_agent_states: Vec<String>,
+    /// Workflow state types (e.g. "started"); when set they are used instead of state names
+    pub linear_agent_state_types: Vec<String>,
+    /// State names never fetched, e.g. "Canceled,Done"; without explicit states, every
+    /// other state is fetched
+    pub linear_agent_exclude_states: Vec<String>,
     pub anthropic_model: String,
+    /// Maximum estimated prompt size in tokens before less important sections are trimmed
+    pub context_budget: usize,
+    /// Colors used for each priority level in the ticket listing
+    pub priority_colors: PriorityColors,
+    /// Per-priority Anthropic models, falling back to `anthropic_model`
+    pub model_map: ModelMap,
+    /// Slack incoming webhook notified with a summary at the end of a run
+    pub slack_webhook_url: Option<String>,
+    /// Linear GraphQL endpoint (e.g. a corporate gateway or a local mock server)
+    pub linear_api_url: String,
+    /// User-Agent sent with Linear requests, to identify the traffic in audit logs
+    pub linear_user_agent: String,
+    /// Proxy for all HTTP requests, e.g. "http://proxy.corp:3128"; without it the
+    /// HTTP_PROXY, HTTPS_PROXY and NO_PROXY environment variables apply
+    pub proxy: Option<String>,
+    /// Proxy credentials as "user:password", for proxies that require authentication
+    pub proxy_auth: Option<String>,
+    /// GitHub token for reading pull requests of private repositories (--verify)
+    pub github_token: Option<String>,
+    /// GitHub REST API base URL, for GitHub Enterprise
+    pub github_api_url: String,
+    /// Base URL of the Anthropic API, without the /v1/messages path
+    pub anthropic_api_base: String,
+    /// How many times an overloaded, rate-limited or failing Anthropic request is retried
+    pub anthropic_max_retries: u32,
+    /// Anthropic requests sent per minute at most, to stay under the account's tier limits
+    pub anthropic_rpm: Option<u32>,
+    /// Anthropic tokens (prompt and reply) used per minute at most
+    pub anthropic_tpm: Option<u32>,
+    /// Nodes requested per page from paginated Linear connections (1 to 250)
+    pub page_size: usize,
+    /// Checklist the --review pass uses to critique a draft plan
+    pub review_rubric: String,
+    /// Sections each plan is asked to include, in order
+    pub plan_sections: Vec<String>,
+    /// Shared background (a project brief) sent before the ticket in every prompt
+    pub project_context: Option<String>,
+    /// Local repository summarized in prompts so that plans refer to real files
+    pub repo_path: Option<PathBuf>,
+    /// Maximum size in characters of that summary
+    pub repo_summary_chars: usize,
+    /// Descriptions longer than this many characters are shortened in the prompt
+    pub max_description_length: Option<usize>,
+    /// Comments longer than this many characters are shortened in the prompt and ticket files
+    pub limit_comments_chars: Option<usize>,
+    /// Only comments made since then are sent in the prompt
+    pub comments_since: Option<DateTime<Utc>>,
+    /// Plan from this many of the latest comments, with the description as background only
+    pub plan_from_comments: Option<usize>,
+    /// Value of the anthropic-version header; other versions may change response shapes
+    pub anthropic_version: String,
+    /// Values sent in the anthropic-beta header to opt into beta API features
+    pub anthropic_beta: Vec<String>,
+    /// Comments containing any of these (case-insensitive) are flagged as potential blockers
+    pub blocker_keywords: Vec<String>,
+    /// Ask for the plan steps as a checkbox list and save them to a separate .tasks.md
+    pub checklist_output: bool,
+    /// Write the metadata of ticket files as a markdown table
+    pub gfm_tables: bool,
+    /// Tickets estimated below this get a concise plan with a lower token limit
+    pub concise_plan_below: f64,
+    /// Tickets estimated at or above this get a detailed plan with a higher token limit
+    pub detailed_plan_from: f64,
+    /// Ask for confirmation before generating plans estimated to cost more than this (dollars)
+    pub confirm_above: Option<f64>,
+    /// Ask for confirmation before planning more tickets than this in one run; 0 for no limit
+    pub max_tickets_per_run: usize,
+    /// Similarity (0 to 1) from which --detect-duplicates flags two selected tickets
+    pub duplicate_threshold: f64,
+    /// Save every Linear and Anthropic request and its response to this directory
+    pub record_dir: Option<PathBuf>,
+    /// Answer Linear and Anthropic requests from recordings in this directory
+    pub replay_dir: Option<PathBuf>,
+}
+
+/// Colors for each Linear priority level (0 = No priority, 1 = Urgent ... 4 = Low)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PriorityColors {
+    pub urgent: String,
+    pub high: String,
+    pub medium: String,
+    pub low: String,
+    pub none: String,
+}
+
+impl Default for PriorityColors {
+    fn default() -> Self {
+        Self {
+            urgent: "red".to_string(),
+            high: "red".to_string(),
+            medium: "yellow".to_string(),
+            low: "green".to_string(),
+            none: "white".to_string(),
+        }
+    }
+}
+
+impl PriorityColors {
+    /// Parse overrides like "urgent=magenta,low=blue" on top of the defaults
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut colors = Self::default();
+        
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (level, color) = entry
+                .split_once('=')
+                .context(format!("Invalid priority color '{}', expected level=color", entry))?;
+            let color = color.trim().to_string();
+            
+            if color.parse::<Color>().is_err() {
+                anyhow::bail!("Unknown color '{}' for priority '{}'", color, level.trim());
+            }
+            
+            match level.trim().to_lowercase().as_str() {
+                "urgent" => colors.urgent = color,
+                "high" => colors.high = color,
+                "medium" => colors.medium = color,
+                "low" => colors.low = color,
+                "none" => colors.none = color,
+                other => anyhow::bail!(
+                    "Unknown priority level '{}', expected one of: urgent, high, medium, low, none",
+                    other
+                ),
+            }
+        }
+        
+        Ok(colors)
+    }
+    
+    /// Color for a Linear priority value
+    pub fn color_for(&self, priority: i32) -> Color {
+        let name = match priority {
+            1 => &self.urgent,
+            2 => &self.high,
+            3 => &self.medium,
+            4 => &self.low,
+            _ => &self.none,
+        };
+        // Colors are validated when parsed, so this only falls back for hand-built configs
+        name.parse().unwrap_or(Color::White)
+    }
+}
+
+/// Anthropic model to use per ticket priority, e.g. Opus for urgent tickets and Haiku for
+/// the rest. Priorities without a model use `default`, then the configured model.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ModelMap {
+    pub urgent: Option<String>,
+    pub high: Option<String>,
+    pub medium: Option<String>,
+    pub low: Option<String>,
+    pub none: Option<String>,
+    pub default: Option<String>,
+}
+
+impl ModelMap {
+    /// Parse a mapping like "urgent=claude-3-opus-20240229,default=claude-3-haiku-20240307"
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut map = Self::default();
+        
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (level, model) = entry
+                .split_once('=')
+                .context(format!("Invalid model mapping '{}', expected level=model", entry))?;
+            let model = model.trim().to_string();
+            
+            if model.is_empty() {
+                anyhow::bail!("Missing model for priority '{}'", level.trim());
+            }
+            
+            match level.trim().to_lowercase().as_str() {
+                "urgent" => map.urgent = Some(model),
+                "high" => map.high = Some(model),
+                "medium" => map.medium = Some(model),
+                "low" => map.low = Some(model),
+                "none" => map.none = Some(model),
+                "default" => map.default = Some(model),
+                other => anyhow::bail!(
+                    "Unknown priority level '{}', expected one of: urgent, high, medium, low, none, default",
+                    other
+                ),
+            }
+        }
+        
+        Ok(map)
+    }
+    
+    /// Model mapped to a Linear priority value, if any
+    pub fn model_for(&self, priority: i32) -> Option<&str> {
+        let model = match priority {
+            1 => &self.urgent,
+            2 => &self.high,
+            3 => &self.medium,
+            4 => &self.low,
+            _ => &self.none,
+        };
+        model.as_ref().or(self.default.as_ref()).map(String::as_str)
+    }
+}
+
+/// Read a secret from the file named by `<name>_FILE` (the container secrets convention),
+/// falling back to the `<name>` environment variable itself
+fn read_secret(name: &str) -> Result<Option<String>> {
+    read_secret_from(name, |var| env::var(var).ok())
+}
+
+/// `read_secret` with the variables looked up by `var`
+fn read_secret_from(name: &str, var: impl Fn(&str) -> Option<String>) -> Result<Option<String>> {
+    let file_var = format!("{}_FILE", name);
+    if let Some(path) = var(&file_var) {
+        let secret = fs::read_to_string(&path)
+            .context(format!("Failed to read {} from {} (set by {})", name, path, file_var))?;
+        return Ok(Some(secret.trim().to_string()));
+    }
+    
+    Ok(var(name))
+}
+
+/// The project brief in a --context-file, or `None` if the file is empty
+fn read_project_context(path: &Path) -> Result<Option<String>> {
+    let context = fs::read_to_string(path)
+        .context(format!("Failed to read the project context from {}", path.display()))?;
+    Ok(Some(context.trim().to_string()).filter(|context| !context.is_empty()))
+}
+
+/// Settings of a `[teams.<name>]` section of config.toml. When that team is selected
+/// they override the global settings from the environment, and are themselves
+/// overridden by command line options.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TeamSettings {
+    pub states: Option<Vec<String>>,
+    pub state_types: Option<Vec<String>>,
+    pub exclude_states: Option<Vec<String>>,
+    pub model: Option<String>,
+    /// Same format as ANTHROPIC_MODEL_MAP, e.g. "urgent=claude-3-opus-20240229"
+    pub model_map: Option<String>,
+    pub review_rubric: Option<String>,
+    pub blocker_keywords: Option<Vec<String>>,
+}
+
+impl TeamSettings {
+    /// Settings for the team from `config_file` (LINEAR_AGENT_CONFIG), or else the first
+    /// config.toml found in the .env locations, if there is a section for it
+    fn load(team: &str, config_file: Option<&Path>) -> Result<Option<Self>> {
+        let path = match config_file {
+            Some(path) => path.to_path_buf(),
+            None => match AppConfig::get_env_locations()
+                .into_iter()
+                .map(|location| location.with_file_name(CONFIG_FILENAME))
+                .find(|location| location.exists())
+            {
+                Some(path) => path,
+                None => return Ok(None),
+            },
+        };
+        
+        let content = fs::read_to_string(&path)
+            .context(format!("Failed to read config file {}", path.display()))?;
+        team_settings_from_toml(&content, team).context(format!("Invalid config file {}", path.display()))
+    }
+}
+
+/// The `[teams.<name>]` section for the team, matching its name case-insensitively
+fn team_settings_from_toml(content: &str, team: &str) -> Result<Option<TeamSettings>> {
+    let file = ::config::Config::builder()
+        .add_source(::config::File::from_str(content, ::config::FileFormat::Toml))
+        .build()?;
+    let teams: HashMap<String, TeamSettings> = match file.get("teams") {
+        Ok(teams) => teams,
+        Err(::config::ConfigError::NotFound(_)) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    
+    Ok(teams
+        .into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(team.trim()))
+        .map(|(_, settings)| settings))
+}
+
+/// Hide a secret except for its last 4 characters, or entirely if it is short
+fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    format!("****{}", chars[chars.len() - 4..].iter().collect::<String>())
+}
+
+/// Split a comma-separated list, trimming entries and dropping empty ones
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Parse a --comments-since cutoff: a date (`2024-05-01`, from midnight UTC), an RFC 3339
+/// timestamp, or a duration before `now` in hours, days or weeks (`36h`, `14d`, `2w`)
+fn parse_comments_since(spec: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let spec = spec.trim();
+    
+    if let Ok(date) = NaiveDate::parse_from_str(spec, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(spec) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    
+    // The unit is the last character, which may not be ASCII in a mistyped value
+    let (amount, unit) = spec
+        .char_indices()
+        .last()
+        .map_or((spec, ' '), |(index, unit)| (&spec[..index], unit));
+    let duration = amount.parse::<u32>().ok().and_then(|amount| match unit {
+        'h' => Some(chrono::Duration::hours(amount.into())),
+        'd' => Some(chrono::Duration::days(amount.into())),
+        'w' => Some(chrono::Duration::weeks(amount.into())),
+        _ => None,
+    });
+    match duration {
+        Some(duration) => Ok(now - duration),
+        None => anyhow::bail!(
+            "'{}' is not a date (2024-05-01) or a duration in hours, days or weeks (36h, 14d, 2w)",
+            spec
+        ),
+    }
+}
+
+/// Parse a comma-separated list of workflow state types, rejecting unknown ones
+fn parse_state_types(spec: &str) -> Result<Vec<String>> {
+    spec.split(',')
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            if STATE_TYPES.contains(&t.as_str()) {
+                Ok(t)
+            } else {
+                anyhow::bail!("Unknown state type '{}', expected one of: {}", t, STATE_TYPES.join(", "))
+            }
+        })
+        .collect()
 }
 
 impl Default for AppConfig {
@@ -26,38 +395,141 @@ impl Default for AppConfig {
         Self {
             linear_api_key: String::new(),
             anthropic_api_key: None,
-            linear_team_name: "Engineering".to_string(),
+            linear_team_name: String::new(),
             linear_agent_user: String::new(),
             linear_agent_states: vec!["Open".to_string(), "In Progress".to_string()],
+            linear_agent_state_types: Vec::new(),
+            linear_agent_exclude_states: Vec::new(),
             anthropic_model: "claude-3-7-sonnet-20250219".to_string(),
+            context_budget: DEFAULT_CONTEXT_BUDGET,
+            priority_colors: PriorityColors::default(),
+            model_map: ModelMap::default(),
+            slack_webhook_url: None,
+            linear_api_url: crate::linear::DEFAULT_LINEAR_API_URL.to_string(),
+            linear_user_agent: crate::linear::DEFAULT_USER_AGENT.to_string(),
+            proxy: None,
+            proxy_auth: None,
+            github_token: None,
+            github_api_url: crate::github::DEFAULT_GITHUB_API_URL.to_string(),
+            anthropic_api_base: crate::anthropic::DEFAULT_ANTHROPIC_API_BASE.to_string(),
+            anthropic_max_retries: crate::anthropic::DEFAULT_MAX_RETRIES,
+            anthropic_rpm: None,
+            anthropic_tpm: None,
+            page_size: crate::linear::DEFAULT_PAGE_SIZE,
+            review_rubric: crate::anthropic::DEFAULT_REVIEW_RUBRIC.to_string(),
+            plan_sections: crate::anthropic::parse_plan_sections(crate::anthropic::DEFAULT_PLAN_SECTIONS)
+                .unwrap_or_default(),
+            project_context: None,
+            repo_path: None,
+            repo_summary_chars: crate::repo::DEFAULT_REPO_SUMMARY_CHARS,
+            max_description_length: None,
+            limit_comments_chars: None,
+            comments_since: None,
+            plan_from_comments: None,
+            anthropic_version: crate::anthropic::DEFAULT_ANTHROPIC_VERSION.to_string(),
+            anthropic_beta: Vec::new(),
+            blocker_keywords: DEFAULT_BLOCKER_KEYWORDS.iter().map(|k| k.to_string()).collect(),
+            checklist_output: false,
+            gfm_tables: false,
+            concise_plan_below: crate::anthropic::DEFAULT_CONCISE_PLAN_BELOW,
+            detailed_plan_from: crate::anthropic::DEFAULT_DETAILED_PLAN_FROM,
+            confirm_above: None,
+            max_tickets_per_run: DEFAULT_MAX_TICKETS_PER_RUN,
+            duplicate_threshold: crate::duplicates::DEFAULT_DUPLICATE_THRESHOLD,
+            record_dir: None,
+            replay_dir: None,
         }
     }
 }
 
+/// Settings whose values --dump-config masks
+const SECRET_SETTINGS: [&str; 5] = ["linear_api_key", "anthropic_api_key", "proxy_auth", "github_token", "slack_webhook_url"];
+
+/// Where the effective value of a setting came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    /// An environment variable, set in the shell or by a .env file
+    Env(String),
+    /// The `[teams.<name>]` section of config.toml for the selected team
+    Team(String),
+    /// A command line option
+    Flag(&'static str),
+}
+
+/// The source of each setting that doesn't have its default value, keyed by field name
+#[derive(Debug, Default)]
+pub struct Sources(HashMap<&'static str, Source>);
+
+impl Sources {
+    /// Where the setting came from; settings that were never set are defaults
+    pub fn get(&self, setting: &str) -> &Source {
+        self.0.get(setting).unwrap_or(&Source::Default)
+    }
+    
+    fn set(&mut self, setting: &'static str, source: Source) {
+        self.0.insert(setting, source);
+    }
+    
+    fn env(&mut self, setting: &'static str, var: &str) {
+        self.set(setting, Source::Env(var.to_string()));
+    }
+    
+    fn flag(&mut self, setting: &'static str, flag: &'static str) {
+        self.set(setting, Source::Flag(flag));
+    }
+    
+    /// A secret read by `read_secret`, from `<name>_FILE` if that is set
+    fn secret(&mut self, setting: &'static str, name: &str) {
+        let file_var = format!("{}_FILE", name);
+        let var = if env::var(&file_var).is_ok() { file_var } else { name.to_string() };
+        self.set(setting, Source::Env(var));
+    }
+}
+
 impl AppConfig {
     /// Loads configuration from environment variables
     pub async fn load(_unused: Option<&Path>, args: &Args) -> Result<Self> {
+        Ok(Self::load_with_sources(args).await?.0)
+    }
+    
+    /// Loads configuration like `load`, also recording where each setting came from:
+    /// the defaults, overridden by environment variables, then the team's section of
+    /// config.toml, then command line options
+    pub async fn load_with_sources(args: &Args) -> Result<(Self, Sources)> {
+        let config_file = env::var("LINEAR_AGENT_CONFIG").ok().map(PathBuf::from);
+        Self::load_with_config_file(args, config_file.as_deref()).await
+    }
+    
+    /// `load_with_sources` with the team sections read from `config_file`, or from the
+    /// first config.toml found in the .env locations when there is none
+    async fn load_with_config_file(args: &Args, config_file: Option<&Path>) -> Result<(Self, Sources)> {
         // Start with default config
         let mut config = Self::default();
+        let mut sources = Sources::default();
         
         // Load environment variables (they should already be loaded in main.rs)
         
         // Get config from environment variables
-        if let Ok(key) = env::var("LINEAR_API_KEY") {
+        if let Some(key) = read_secret("LINEAR_API_KEY")? {
             config.linear_api_key = key;
+            sources.secret("linear_api_key", "LINEAR_API_KEY");
         }
         
         // Make Anthropic API key optional
-        if let Ok(key) = env::var("ANTHROPIC_API_KEY") {
+        if let Some(key) = read_secret("ANTHROPIC_API_KEY")? {
             config.anthropic_api_key = Some(key);
+            sources.secret("anthropic_api_key", "ANTHROPIC_API_KEY");
         }
         
         if let Ok(team) = env::var("LINEAR_TEAM_NAME") {
             config.linear_team_name = team;
+            sources.env("linear_team_name", "LINEAR_TEAM_NAME");
         }
         
         if let Ok(user) = env::var("LINEAR_AGENT_USER") {
             config.linear_agent_user = user;
+            sources.env("linear_agent_user", "LINEAR_AGENT_USER");
         }
         
         if let Ok(states) = env::var("LINEAR_AGENT_STATES") {
@@ -65,19 +537,263 @@ impl AppConfig {
                 .split(',')
                 .map(|s| s.trim().to_string())
                 .collect();
+            sources.env("linear_agent_states", "LINEAR_AGENT_STATES");
+        }
+        
+        let mut explicit_states = env::var("LINEAR_AGENT_STATES").is_ok();
+        if let Ok(states) = env::var("LINEAR_AGENT_EXCLUDE_STATES") {
+            config.linear_agent_exclude_states = split_list(&states);
+            sources.env("linear_agent_exclude_states", "LINEAR_AGENT_EXCLUDE_STATES");
+        }
+        
+        if let Ok(types) = env::var("LINEAR_AGENT_STATE_TYPES") {
+            config.linear_agent_state_types = parse_state_types(&types)
+                .context("Invalid LINEAR_AGENT_STATE_TYPES")?;
+            sources.env("linear_agent_state_types", "LINEAR_AGENT_STATE_TYPES");
         }
         
         if let Ok(model) = env::var("ANTHROPIC_MODEL") {
             config.anthropic_model = model;
+            sources.env("anthropic_model", "ANTHROPIC_MODEL");
+        }
+        
+        if let Ok(budget) = env::var("ANTHROPIC_CONTEXT_BUDGET") {
+            config.context_budget = budget
+                .trim()
+                .parse()
+                .context("ANTHROPIC_CONTEXT_BUDGET must be a positive number of tokens")?;
+            sources.env("context_budget", "ANTHROPIC_CONTEXT_BUDGET");
+        }
+        
+        if let Ok(length) = env::var("ANTHROPIC_MAX_DESCRIPTION_LENGTH") {
+            config.max_description_length = Some(
+                length
+                    .trim()
+                    .parse()
+                    .context("ANTHROPIC_MAX_DESCRIPTION_LENGTH must be a number of characters")?,
+            );
+            sources.env("max_description_length", "ANTHROPIC_MAX_DESCRIPTION_LENGTH");
+        }
+        
+        if let Ok(length) = env::var("LINEAR_AGENT_LIMIT_COMMENTS_CHARS") {
+            config.limit_comments_chars = Some(
+                length
+                    .trim()
+                    .parse()
+                    .context("LINEAR_AGENT_LIMIT_COMMENTS_CHARS must be a number of characters")?,
+            );
+            sources.env("limit_comments_chars", "LINEAR_AGENT_LIMIT_COMMENTS_CHARS");
+        }
+        
+        if let Ok(since) = env::var("ANTHROPIC_COMMENTS_SINCE") {
+            config.comments_since = Some(
+                parse_comments_since(&since, Utc::now()).context("Invalid ANTHROPIC_COMMENTS_SINCE")?,
+            );
+            sources.env("comments_since", "ANTHROPIC_COMMENTS_SINCE");
+        }
+        
+        if let Ok(count) = env::var("ANTHROPIC_PLAN_FROM_COMMENTS") {
+            config.plan_from_comments = Some(
+                count
+                    .trim()
+                    .parse()
+                    .context("ANTHROPIC_PLAN_FROM_COMMENTS must be a number of comments")?,
+            );
+            sources.env("plan_from_comments", "ANTHROPIC_PLAN_FROM_COMMENTS");
+        }
+        
+        if let Ok(points) = env::var("ANTHROPIC_CONCISE_PLAN_BELOW") {
+            config.concise_plan_below = points
+                .trim()
+                .parse()
+                .context("ANTHROPIC_CONCISE_PLAN_BELOW must be an estimate in points")?;
+            sources.env("concise_plan_below", "ANTHROPIC_CONCISE_PLAN_BELOW");
+        }
+        
+        if let Ok(points) = env::var("ANTHROPIC_DETAILED_PLAN_FROM") {
+            config.detailed_plan_from = points
+                .trim()
+                .parse()
+                .context("ANTHROPIC_DETAILED_PLAN_FROM must be an estimate in points")?;
+            sources.env("detailed_plan_from", "ANTHROPIC_DETAILED_PLAN_FROM");
+        }
+        
+        if let Ok(dollars) = env::var("ANTHROPIC_CONFIRM_ABOVE") {
+            config.confirm_above = Some(
+                dollars
+                    .trim()
+                    .trim_start_matches('$')
+                    .parse()
+                    .context("ANTHROPIC_CONFIRM_ABOVE must be an amount in dollars")?,
+            );
+            sources.env("confirm_above", "ANTHROPIC_CONFIRM_ABOVE");
+        }
+        
+        if let Ok(max) = env::var("LINEAR_AGENT_MAX_TICKETS_PER_RUN") {
+            config.max_tickets_per_run = max
+                .trim()
+                .parse()
+                .context("LINEAR_AGENT_MAX_TICKETS_PER_RUN must be a number of tickets")?;
+            sources.env("max_tickets_per_run", "LINEAR_AGENT_MAX_TICKETS_PER_RUN");
+        }
+        
+        if let Ok(threshold) = env::var("LINEAR_AGENT_DUPLICATE_THRESHOLD") {
+            config.duplicate_threshold = threshold
+                .trim()
+                .parse()
+                .context("LINEAR_AGENT_DUPLICATE_THRESHOLD must be a number between 0 and 1")?;
+            sources.env("duplicate_threshold", "LINEAR_AGENT_DUPLICATE_THRESHOLD");
+        }
+        
+        if let Ok(keywords) = env::var("LINEAR_AGENT_BLOCKER_KEYWORDS") {
+            config.blocker_keywords = keywords
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            sources.env("blocker_keywords", "LINEAR_AGENT_BLOCKER_KEYWORDS");
+        }
+        
+        if let Ok(version) = env::var("ANTHROPIC_VERSION") {
+            config.anthropic_version = version.trim().to_string();
+            sources.env("anthropic_version", "ANTHROPIC_VERSION");
+        }
+        
+        if let Ok(beta) = env::var("ANTHROPIC_BETA") {
+            config.anthropic_beta = beta
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            sources.env("anthropic_beta", "ANTHROPIC_BETA");
+        }
+        
+        if let Ok(rubric) = env::var("ANTHROPIC_REVIEW_RUBRIC") {
+            config.review_rubric = rubric;
+            sources.env("review_rubric", "ANTHROPIC_REVIEW_RUBRIC");
+        }
+        
+        if let Ok(sections) = env::var("ANTHROPIC_PLAN_SECTIONS") {
+            config.plan_sections = crate::anthropic::parse_plan_sections(&sections)
+                .context("Invalid ANTHROPIC_PLAN_SECTIONS")?;
+            sources.env("plan_sections", "ANTHROPIC_PLAN_SECTIONS");
+        }
+        
+        if let Ok(path) = env::var("ANTHROPIC_CONTEXT_FILE") {
+            config.project_context = read_project_context(Path::new(&path))
+                .context("Invalid ANTHROPIC_CONTEXT_FILE")?;
+            sources.env("project_context", "ANTHROPIC_CONTEXT_FILE");
+        }
+        
+        if let Ok(path) = env::var("LINEAR_AGENT_REPO_PATH") {
+            config.repo_path = Some(PathBuf::from(path));
+            sources.env("repo_path", "LINEAR_AGENT_REPO_PATH");
+        }
+        
+        if let Ok(chars) = env::var("LINEAR_AGENT_REPO_SUMMARY_CHARS") {
+            config.repo_summary_chars = chars
+                .trim()
+                .parse()
+                .context("LINEAR_AGENT_REPO_SUMMARY_CHARS must be a number of characters")?;
+            sources.env("repo_summary_chars", "LINEAR_AGENT_REPO_SUMMARY_CHARS");
+        }
+        
+        if let Ok(page_size) = env::var("LINEAR_PAGE_SIZE") {
+            config.page_size = page_size
+                .trim()
+                .parse()
+                .context("LINEAR_PAGE_SIZE must be a number between 1 and 250")?;
+            sources.env("page_size", "LINEAR_PAGE_SIZE");
+        }
+        
+        if let Ok(map) = env::var("ANTHROPIC_MODEL_MAP") {
+            config.model_map = ModelMap::parse(&map).context("Invalid ANTHROPIC_MODEL_MAP")?;
+            sources.env("model_map", "ANTHROPIC_MODEL_MAP");
+        }
+        
+        if let Ok(colors) = env::var("LINEAR_AGENT_PRIORITY_COLORS") {
+            config.priority_colors = PriorityColors::parse(&colors)
+                .context("Invalid LINEAR_AGENT_PRIORITY_COLORS")?;
+            sources.env("priority_colors", "LINEAR_AGENT_PRIORITY_COLORS");
+        }
+        
+        if let Ok(url) = env::var("SLACK_WEBHOOK_URL") {
+            config.slack_webhook_url = Some(url);
+            sources.env("slack_webhook_url", "SLACK_WEBHOOK_URL");
+        }
+        
+        if let Ok(url) = env::var("LINEAR_API_URL") {
+            config.linear_api_url = url;
+            sources.env("linear_api_url", "LINEAR_API_URL");
+        }
+        
+        if let Ok(user_agent) = env::var("LINEAR_USER_AGENT") {
+            config.linear_user_agent = user_agent;
+            sources.env("linear_user_agent", "LINEAR_USER_AGENT");
+        }
+        
+        if let Ok(proxy) = env::var("LINEAR_AGENT_PROXY") {
+            config.proxy = Some(proxy);
+            sources.env("proxy", "LINEAR_AGENT_PROXY");
+        }
+        
+        config.proxy_auth = read_secret("LINEAR_AGENT_PROXY_AUTH")?;
+        if config.proxy_auth.is_some() {
+            sources.secret("proxy_auth", "LINEAR_AGENT_PROXY_AUTH");
+        }
+        config.github_token = read_secret("GITHUB_TOKEN")?;
+        if config.github_token.is_some() {
+            sources.secret("github_token", "GITHUB_TOKEN");
+        }
+        if let Ok(url) = env::var("GITHUB_API_URL") {
+            config.github_api_url = url;
+            sources.env("github_api_url", "GITHUB_API_URL");
+        }
+        
+        if let Ok(url) = env::var("ANTHROPIC_API_BASE") {
+            config.anthropic_api_base = url;
+            sources.env("anthropic_api_base", "ANTHROPIC_API_BASE");
+        }
+        
+        if let Ok(retries) = env::var("ANTHROPIC_MAX_RETRIES") {
+            config.anthropic_max_retries = retries
+                .trim()
+                .parse()
+                .context("ANTHROPIC_MAX_RETRIES must be a non-negative number")?;
+            sources.env("anthropic_max_retries", "ANTHROPIC_MAX_RETRIES");
+        }
+        
+        if let Ok(rpm) = env::var("ANTHROPIC_RPM") {
+            config.anthropic_rpm = Some(rpm.trim().parse().context("ANTHROPIC_RPM must be a number of requests")?);
+            sources.env("anthropic_rpm", "ANTHROPIC_RPM");
+        }
+        
+        if let Ok(tpm) = env::var("ANTHROPIC_TPM") {
+            config.anthropic_tpm = Some(tpm.trim().parse().context("ANTHROPIC_TPM must be a number of tokens")?);
+            sources.env("anthropic_tpm", "ANTHROPIC_TPM");
+        }
+        
+        // The selected team's section in config.toml overrides the global settings
+        let team = args.team.clone().unwrap_or_else(|| config.linear_team_name.clone());
+        if let Some(settings) = TeamSettings::load(&team, config_file)? {
+            explicit_states |= settings.states.is_some();
+            let applied = config
+                .apply_team_settings(&settings)
+                .context(format!("Invalid settings for team '{}'", team))?;
+            for setting in applied {
+                sources.set(setting, Source::Team(team.clone()));
+            }
         }
         
         // Override with command line arguments
         if let Some(user) = &args.user {
             config.linear_agent_user = user.clone();
+            sources.flag("linear_agent_user", "--user");
         }
         
         if let Some(team) = &args.team {
             config.linear_team_name = team.clone();
+            sources.flag("linear_team_name", "--team");
         }
         
         if let Some(states) = &args.states {
@@ -85,29 +801,357 @@ impl AppConfig {
                 .split(',')
                 .map(|s| s.trim().to_string())
                 .collect();
+            sources.flag("linear_agent_states", "--states");
+        }
+        
+        // --states on the command line overrides state types from the environment,
+        // while --state-type overrides any state names
+        if args.states.is_some() {
+            config.linear_agent_state_types.clear();
+            sources.flag("linear_agent_state_types", "--states");
+        }
+        
+        if let Some(types) = &args.state_type {
+            config.linear_agent_state_types = parse_state_types(types)
+                .context("Invalid --state-type")?;
+            sources.flag("linear_agent_state_types", "--state-type");
+        }
+        
+        if let Some(states) = &args.exclude_states {
+            config.linear_agent_exclude_states = split_list(states);
+            sources.flag("linear_agent_exclude_states", "--exclude-states");
+        }
+        
+        // Excluded states replace the default state names unless states were given explicitly
+        if !config.linear_agent_exclude_states.is_empty() && !explicit_states && args.states.is_none() {
+            config.linear_agent_states.clear();
+            let source = sources.get("linear_agent_exclude_states").clone();
+            sources.set("linear_agent_states", source);
         }
         
         if let Some(model) = &args.model {
             config.anthropic_model = model.clone();
+            sources.flag("anthropic_model", "--model");
+        }
+        
+        if let Some(budget) = args.context_budget {
+            config.context_budget = budget;
+            sources.flag("context_budget", "--context-budget");
+        }
+        
+        if let Some(version) = &args.anthropic_version {
+            config.anthropic_version = version.clone();
+            sources.flag("anthropic_version", "--anthropic-version");
+        }
+        
+        if !args.anthropic_beta.is_empty() {
+            config.anthropic_beta = args.anthropic_beta.clone();
+            sources.flag("anthropic_beta", "--anthropic-beta");
+        }
+        
+        config.record_dir = args.record.clone();
+        config.replay_dir = args.replay.clone();
+        if args.record.is_some() {
+            sources.flag("record_dir", "--record");
+        }
+        if args.replay.is_some() {
+            sources.flag("replay_dir", "--replay");
+        }
+        
+        config.checklist_output = args.checklist_output;
+        if args.checklist_output {
+            sources.flag("checklist_output", "--checklist-output");
+        }
+        
+        config.gfm_tables = args.gfm_tables;
+        if args.gfm_tables {
+            sources.flag("gfm_tables", "--gfm-tables");
+        }
+        
+        if let Some(length) = args.max_description_length {
+            config.max_description_length = Some(length);
+            sources.flag("max_description_length", "--max-description-length");
+        }
+        
+        if let Some(sections) = &args.plan_sections {
+            config.plan_sections = crate::anthropic::parse_plan_sections(sections)
+                .context("Invalid --plan-sections")?;
+            sources.flag("plan_sections", "--plan-sections");
+        }
+        
+        if let Some(path) = &args.context_file {
+            config.project_context = read_project_context(path)?;
+            sources.flag("project_context", "--context-file");
+        }
+        
+        if let Some(context) = &args.context {
+            config.project_context = Some(context.trim().to_string()).filter(|context| !context.is_empty());
+            sources.flag("project_context", "--context");
+        }
+        
+        if let Some(path) = &args.repo_path {
+            config.repo_path = Some(path.clone());
+            sources.flag("repo_path", "--repo-path");
+        }
+        
+        if let Some(chars) = args.repo_summary_chars {
+            config.repo_summary_chars = chars;
+            sources.flag("repo_summary_chars", "--repo-summary-chars");
+        }
+        
+        if let Some(length) = args.limit_comments_chars {
+            config.limit_comments_chars = Some(length);
+            sources.flag("limit_comments_chars", "--limit-comments-chars");
+        }
+        
+        if let Some(since) = &args.comments_since {
+            config.comments_since = Some(
+                parse_comments_since(since, Utc::now()).context("Invalid --comments-since")?,
+            );
+            sources.flag("comments_since", "--comments-since");
+        }
+        
+        if let Some(count) = args.plan_from_comments_only {
+            config.plan_from_comments = Some(count);
+            sources.flag("plan_from_comments", "--plan-from-comments-only");
+        }
+        
+        if let Some(dollars) = args.confirm_above {
+            config.confirm_above = Some(dollars);
+            sources.flag("confirm_above", "--confirm-above");
+        }
+        
+        if let Some(max) = args.max_tickets_per_run {
+            config.max_tickets_per_run = max;
+            sources.flag("max_tickets_per_run", "--max-tickets-per-run");
+        }
+        
+        if let Some(threshold) = args.duplicate_threshold {
+            config.duplicate_threshold = threshold;
+            sources.flag("duplicate_threshold", "--duplicate-threshold");
+        }
+        
+        if let Some(points) = args.concise_plan_below {
+            config.concise_plan_below = points;
+            sources.flag("concise_plan_below", "--concise-plan-below");
+        }
+        
+        if let Some(points) = args.detailed_plan_from {
+            config.detailed_plan_from = points;
+            sources.flag("detailed_plan_from", "--detailed-plan-from");
+        }
+        
+        if config.concise_plan_below > config.detailed_plan_from {
+            anyhow::bail!(
+                "The concise plan threshold ({}) must not be above the detailed plan threshold ({})",
+                config.concise_plan_below,
+                config.detailed_plan_from
+            );
+        }
+        
+        if let Some(url) = &args.notify_slack {
+            config.slack_webhook_url = Some(url.clone());
+            sources.flag("slack_webhook_url", "--notify-slack");
+        }
+        
+        if let Some(url) = &args.linear_api_url {
+            config.linear_api_url = url.clone();
+            sources.flag("linear_api_url", "--linear-api-url");
+        }
+        
+        if let Some(url) = &args.anthropic_api_base {
+            config.anthropic_api_base = url.clone();
+            sources.flag("anthropic_api_base", "--anthropic-api-base");
+        }
+        
+        if let Some(retries) = args.anthropic_max_retries {
+            config.anthropic_max_retries = retries;
+            sources.flag("anthropic_max_retries", "--anthropic-max-retries");
+        }
+        
+        if let Some(rpm) = args.anthropic_rpm {
+            config.anthropic_rpm = Some(rpm);
+            sources.flag("anthropic_rpm", "--anthropic-rpm");
+        }
+        
+        if let Some(tpm) = args.anthropic_tpm {
+            config.anthropic_tpm = Some(tpm);
+            sources.flag("anthropic_tpm", "--anthropic-tpm");
+        }
+        
+        if let Some(map) = &args.model_map {
+            config.model_map = ModelMap::parse(map).context("Invalid --model-map")?;
+            sources.flag("model_map", "--model-map");
+        }
+        
+        if let Some(colors) = &args.priority_colors {
+            config.priority_colors = PriorityColors::parse(colors)
+                .context("Invalid --priority-colors")?;
+            sources.flag("priority_colors", "--priority-colors");
         }
         
-        Ok(config)
+        if let Some(page_size) = args.page_size {
+            config.page_size = page_size;
+            sources.flag("page_size", "--page-size");
+        }
+        
+        if let Some(proxy) = &args.proxy {
+            config.proxy = Some(proxy.clone());
+            sources.flag("proxy", "--proxy");
+        }
+        
+        // Fail early on an invalid proxy rather than when the first request is made
+        config.http_client().context("Invalid proxy settings")?;
+        
+        if !(1..=crate::linear::MAX_PAGE_SIZE).contains(&config.page_size) {
+            anyhow::bail!(
+                "Page size must be between 1 and {}, got {}",
+                crate::linear::MAX_PAGE_SIZE,
+                config.page_size
+            );
+        }
+        
+        if config.anthropic_rpm == Some(0) || config.anthropic_tpm == Some(0) {
+            anyhow::bail!("Anthropic rate limits must be at least 1 per minute");
+        }
+        
+        if config.plan_from_comments == Some(0) {
+            anyhow::bail!("--plan-from-comments-only needs at least 1 comment");
+        }
+        
+        if let Some(path) = config.repo_path.as_ref().filter(|path| !path.is_dir()) {
+            anyhow::bail!("Repository path {} is not a directory", path.display());
+        }
+        
+        if !(0.0..=1.0).contains(&config.duplicate_threshold) {
+            anyhow::bail!("Duplicate threshold must be between 0 and 1, got {}", config.duplicate_threshold);
+        }
+        
+        Ok((config, sources))
     }
     
-    /// Save configuration to a .env file
-    pub fn save(&self, path: Option<&Path>) -> Result<PathBuf> {
-        let env_path = if let Some(p) = path {
-            p.to_path_buf()
-        } else {
-            // Create the default config directory in home
-            let home = home_dir().context("Failed to find home directory")?;
-            let config_dir = home.join(DEFAULT_CONFIG_DIR);
-            if !config_dir.exists() {
-                fs::create_dir_all(&config_dir).context("Failed to create config directory")?;
+    /// Apply the settings of a team section over the global ones, returning the names of
+    /// the settings it changed. Like --states, state names given for the team replace any
+    /// global state types.
+    fn apply_team_settings(&mut self, team: &TeamSettings) -> Result<Vec<&'static str>> {
+        let mut applied = Vec::new();
+        if let Some(states) = &team.states {
+            self.linear_agent_states = states.clone();
+            self.linear_agent_state_types.clear();
+            applied.extend(["linear_agent_states", "linear_agent_state_types"]);
+        }
+        if let Some(types) = &team.state_types {
+            self.linear_agent_state_types = parse_state_types(&types.join(","))?;
+            applied.push("linear_agent_state_types");
+        }
+        if let Some(states) = &team.exclude_states {
+            self.linear_agent_exclude_states = states.clone();
+            applied.push("linear_agent_exclude_states");
+        }
+        if let Some(model) = &team.model {
+            self.anthropic_model = model.clone();
+            applied.push("anthropic_model");
+        }
+        if let Some(map) = &team.model_map {
+            self.model_map = ModelMap::parse(map).context("Invalid model_map")?;
+            applied.push("model_map");
+        }
+        if let Some(rubric) = &team.review_rubric {
+            self.review_rubric = rubric.clone();
+            applied.push("review_rubric");
+        }
+        if let Some(keywords) = &team.blocker_keywords {
+            self.blocker_keywords = keywords.clone();
+            applied.push("blocker_keywords");
+        }
+        
+        Ok(applied)
+    }
+    
+    /// Every setting by field name, in alphabetical order, with its value as one line of
+    /// text and secrets masked (e.g. `****cdef`). Unset settings are "(not set)".
+    pub fn masked_settings(&self) -> Result<Vec<(String, String)>> {
+        let value = serde_json::to_value(self).context("Failed to serialize the configuration")?;
+        let serde_json::Value::Object(settings) = value else {
+            anyhow::bail!("The configuration did not serialize to an object");
+        };
+        
+        Ok(settings
+            .into_iter()
+            .map(|(name, value)| {
+                let text = match value {
+                    serde_json::Value::Null => "(not set)".to_string(),
+                    serde_json::Value::String(text) if text.is_empty() => "(not set)".to_string(),
+                    serde_json::Value::String(text) if SECRET_SETTINGS.contains(&name.as_str()) => mask_secret(&text),
+                    // Keep multi-line values such as the review rubric on one line
+                    serde_json::Value::String(text) => text.replace('\n', "\\n"),
+                    other => other.to_string(),
+                };
+                (name, text)
+            })
+            .collect())
+    }
+    
+    /// HTTP client for the Linear, Anthropic and Slack requests, going through the
+    /// configured proxy if any
+    pub fn http_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        
+        if let Some(url) = &self.proxy {
+            let mut proxy = reqwest::Proxy::all(url)
+                .context(format!("Invalid proxy URL '{}'", url))?
+                .no_proxy(reqwest::NoProxy::from_env());
+            if let Some(auth) = &self.proxy_auth {
+                let (user, password) = auth
+                    .split_once(':')
+                    .context("Proxy credentials must be given as user:password")?;
+                proxy = proxy.basic_auth(user, password);
             }
-            config_dir.join(DEFAULT_ENV_FILENAME)
+            builder = builder.proxy(proxy);
+        }
+        
+        builder.build().context("Failed to create the HTTP client")
+    }
+    
+    /// Anthropic model for a ticket: the one mapped to its priority, or the configured model
+    pub fn model_for(&self, ticket: &Ticket) -> &str {
+        self.model_map.model_for(ticket.priority).unwrap_or(&self.anthropic_model)
+    }
+    
+    /// The users to fetch tickets for; LINEAR_AGENT_USER and --user take a comma-separated list
+    pub fn agent_users(&self) -> Vec<String> {
+        split_list(&self.linear_agent_user)
+    }
+    
+    /// The state filter for ticket queries: state types take precedence over state names,
+    /// and excluded states are left out of either
+    pub fn state_filter(&self) -> StateFilter<'_> {
+        let selection = if !self.linear_agent_state_types.is_empty() {
+            StateSelection::Types(&self.linear_agent_state_types)
+        } else if self.linear_agent_states.is_empty() {
+            StateSelection::All
+        } else {
+            StateSelection::Names(&self.linear_agent_states)
+        };
+        
+        StateFilter {
+            selection,
+            exclude: &self.linear_agent_exclude_states,
+        }
+    }
+    
+    /// Save configuration to a .env file, by default at `default_env_path`
+    pub fn save(&self, path: Option<&Path>) -> Result<PathBuf> {
+        let env_path = match path {
+            Some(p) => p.to_path_buf(),
+            None => Self::default_env_path().context("Failed to find home directory")?,
         };
         
+        // Create the config directory if needed
+        if let Some(config_dir) = env_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(config_dir).context("Failed to create config directory")?;
+        }
+        
         // Create the .env file content
         let mut content = String::new();
         content.push_str(&format!("LINEAR_API_KEY={}\n", self.linear_api_key));
@@ -131,17 +1175,193 @@ impl AppConfig {
     
     /// Get standard locations for .env file
     pub fn get_env_locations() -> Vec<PathBuf> {
-        let mut locations = Vec::new();
-        
-        // Current directory
-        locations.push(PathBuf::from(DEFAULT_ENV_FILENAME));
-        
-        // Home directory
-        if let Some(home) = home_dir() {
-            // ~/.linear-agent/.env
-            locations.push(home.join(DEFAULT_CONFIG_DIR).join(DEFAULT_ENV_FILENAME));
+        env_locations(env::var_os("XDG_CONFIG_HOME").map(PathBuf::from), home_dir())
+    }
+    
+    /// Where a new configuration is saved by default: the XDG config directory on Linux,
+    /// ~/.linear-agent/.env elsewhere
+    pub fn default_env_path() -> Option<PathBuf> {
+        let home = home_dir();
+        if cfg!(target_os = "linux") {
+            xdg_env_path(env::var_os("XDG_CONFIG_HOME").map(PathBuf::from), home.as_deref())
+        } else {
+            home.map(|home| home.join(DEFAULT_CONFIG_DIR).join(DEFAULT_ENV_FILENAME))
         }
-        
-        locations
     }
-}
\ No newline at end of file
+}
+
+/// The .env locations in order of precedence: the current directory, the XDG config
+/// directory, then ~/.linear-agent/ (kept for existing setups)
+fn env_locations(xdg_config_home: Option<PathBuf>, home: Option<PathBuf>) -> Vec<PathBuf> {
+    let mut locations = vec![PathBuf::from(DEFAULT_ENV_FILENAME)];
+    
+    if let Some(xdg_path) = xdg_env_path(xdg_config_home, home.as_deref()) {
+        locations.push(xdg_path);
+    }
+    if let Some(home) = home {
+        locations.push(home.join(DEFAULT_CONFIG_DIR).join(DEFAULT_ENV_FILENAME));
+    }
+    
+    locations
+}
+
+/// $XDG_CONFIG_HOME/linear-agent/.env, defaulting to ~/.config/linear-agent/.env. Relative
+/// XDG_CONFIG_HOME values are invalid per the spec and ignored.
+fn xdg_env_path(xdg_config_home: Option<PathBuf>, home: Option<&Path>) -> Option<PathBuf> {
+    let config_home = xdg_config_home
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home.map(|home| home.join(".config")))?;
+    Some(config_home.join(XDG_CONFIG_DIR).join(DEFAULT_ENV_FILENAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-06-15T12:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn secrets_are_read_from_the_variable_or_the_file_it_names() {
+        let dir = env::temp_dir().join(format!("linear-agent-secrets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let key_file = dir.join("linear_api_key");
+        fs::write(&key_file, "lin_from_file\n").unwrap();
+        let missing_file = dir.join("missing");
+        let vars = |pairs: Vec<(&'static str, String)>| {
+            let vars: HashMap<&str, String> = pairs.into_iter().collect();
+            move |name: &str| vars.get(name).cloned()
+        };
+
+        let direct = read_secret_from("LINEAR_API_KEY", vars(vec![("LINEAR_API_KEY", "lin_direct".to_string())]));
+        assert_eq!(direct.unwrap().as_deref(), Some("lin_direct"));
+
+        // The file wins over the variable, and is trimmed
+        let from_file = read_secret_from("LINEAR_API_KEY", vars(vec![
+            ("LINEAR_API_KEY", "lin_direct".to_string()),
+            ("LINEAR_API_KEY_FILE", key_file.to_string_lossy().into_owned()),
+        ]));
+        assert_eq!(from_file.unwrap().as_deref(), Some("lin_from_file"));
+
+        let error = read_secret_from("LINEAR_API_KEY", vars(vec![
+            ("LINEAR_API_KEY_FILE", missing_file.to_string_lossy().into_owned()),
+        ])).unwrap_err();
+        assert!(format!("{:#}", error).contains("set by LINEAR_API_KEY_FILE"), "{:#}", error);
+
+        assert_eq!(read_secret_from("LINEAR_API_KEY", vars(Vec::new())).unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    const TEAMS_TOML: &str = r#"
+[teams.Engineering]
+states = ["Todo", "In Progress"]
+model = "claude-team"
+
+[teams.Design]
+model = "claude-design"
+"#;
+
+    #[test]
+    fn team_sections_override_only_the_settings_they_set() {
+        let mut config = AppConfig {
+            anthropic_model: "claude-global".to_string(),
+            linear_agent_state_types: vec!["started".to_string()],
+            ..AppConfig::default()
+        };
+        let settings = team_settings_from_toml(TEAMS_TOML, "engineering").unwrap().unwrap();
+        config.apply_team_settings(&settings).unwrap();
+
+        assert_eq!(config.anthropic_model, "claude-team");
+        assert_eq!(config.linear_agent_states, vec!["Todo", "In Progress"]);
+        assert!(config.linear_agent_state_types.is_empty());
+        assert_eq!(config.blocker_keywords, AppConfig::default().blocker_keywords);
+        assert!(team_settings_from_toml(TEAMS_TOML, "Mobile").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn command_line_options_override_team_sections() {
+        use clap::Parser;
+
+        let path = env::temp_dir().join(format!("linear-agent-teams-{}.toml", std::process::id()));
+        fs::write(&path, TEAMS_TOML).unwrap();
+        let args = Args::parse_from(["linear-agent", "--team", "Engineering", "--model", "claude-cli"]);
+        let loaded = AppConfig::load_with_config_file(&args, Some(&path)).await;
+        fs::remove_file(&path).unwrap();
+
+        let (config, sources) = loaded.unwrap();
+        assert_eq!(config.anthropic_model, "claude-cli");
+        assert_eq!(config.linear_agent_states, vec!["Todo", "In Progress"]);
+        assert_eq!(sources.get("anthropic_model"), &Source::Flag("--model"));
+        assert_eq!(sources.get("linear_agent_states"), &Source::Team("Engineering".to_string()));
+        assert_eq!(sources.get("concise_plan_below"), &Source::Default);
+    }
+
+    #[test]
+    fn masked_settings_hide_secrets() {
+        let config = AppConfig {
+            linear_api_key: "lin_api_0123456789abcdef".to_string(),
+            anthropic_api_key: Some("short".to_string()),
+            ..AppConfig::default()
+        };
+        let settings: HashMap<String, String> = config.masked_settings().unwrap().into_iter().collect();
+
+        assert_eq!(settings["linear_api_key"], "****cdef");
+        assert_eq!(settings["anthropic_api_key"], "****");
+        assert_eq!(settings["github_token"], "(not set)");
+        assert_eq!(settings["page_size"], crate::linear::DEFAULT_PAGE_SIZE.to_string());
+    }
+
+    #[test]
+    fn env_locations_check_xdg_before_the_home_directory() {
+        let locations = env_locations(Some(PathBuf::from("/xdg")), Some(PathBuf::from("/home/jane")));
+        assert_eq!(
+            locations,
+            vec![
+                PathBuf::from(".env"),
+                PathBuf::from("/xdg/linear-agent/.env"),
+                PathBuf::from("/home/jane/.linear-agent/.env"),
+            ]
+        );
+    }
+
+    #[test]
+    fn xdg_config_home_defaults_to_dot_config() {
+        let home = Path::new("/home/jane");
+        let expected = Some(PathBuf::from("/home/jane/.config/linear-agent/.env"));
+        assert_eq!(xdg_env_path(None, Some(home)), expected);
+        assert_eq!(xdg_env_path(Some(PathBuf::from("relative")), Some(home)), expected);
+        assert_eq!(xdg_env_path(None, None), None);
+    }
+
+    #[test]
+    fn comments_since_accepts_dates_and_timestamps() {
+        let date = parse_comments_since("2024-05-01", now()).unwrap();
+        assert_eq!(date.to_rfc3339(), "2024-05-01T00:00:00+00:00");
+
+        let timestamp = parse_comments_since("2024-05-01T09:30:00+02:00", now()).unwrap();
+        assert_eq!(timestamp.to_rfc3339(), "2024-05-01T07:30:00+00:00");
+    }
+
+    #[test]
+    fn comments_since_accepts_durations_before_now() {
+        assert_eq!(parse_comments_since("36h", now()).unwrap().to_rfc3339(), "2024-06-14T00:00:00+00:00");
+        assert_eq!(parse_comments_since("14d", now()).unwrap().to_rfc3339(), "2024-06-01T12:00:00+00:00");
+        assert_eq!(parse_comments_since(" 2w ", now()).unwrap().to_rfc3339(), "2024-06-01T12:00:00+00:00");
+    }
+
+    #[test]
+    fn comments_since_rejects_anything_else() {
+        for spec in ["", "d", "14", "14m", "-3d", "yesterday", "2024-13-01"] {
+            assert!(parse_comments_since(spec, now()).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn comments_since_rejects_non_ascii_units() {
+        for spec in ["3é", "14日", "é", "2w✓"] {
+            assert!(parse_comments_since(spec, now()).is_err(), "{}", spec);
+        }
+    }
+}
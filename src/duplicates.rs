@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+
+use crate::models::Ticket;
+
+/// Default similarity from which two selected tickets are flagged as likely duplicates
+pub const DEFAULT_DUPLICATE_THRESHOLD: f64 = 0.6;
+
+/// Two selected tickets that look alike, by their index in the selection
+#[derive(Debug, Clone, PartialEq)]
+pub struct LikelyDuplicate {
+    pub first: usize,
+    pub second: usize,
+    /// Between 0 (nothing in common) and 1 (the same words)
+    pub similarity: f64,
+}
+
+/// Pairs of tickets at least `threshold` similar, most similar first
+pub fn find_duplicates(tickets: &[Ticket], threshold: f64) -> Vec<LikelyDuplicate> {
+    let words: Vec<(HashSet<String>, HashSet<String>)> = tickets
+        .iter()
+        .map(|ticket| {
+            let title = normalized_words(&ticket.title);
+            let mut all = title.clone();
+            all.extend(normalized_words(ticket.description_text()));
+            (title, all)
+        })
+        .collect();
+
+    let mut duplicates = Vec::new();
+    for first in 0..tickets.len() {
+        for second in first + 1..tickets.len() {
+            // Titles alone catch a copy whose description was never filled in
+            let similarity = overlap(&words[first].0, &words[second].0).max(overlap(&words[first].1, &words[second].1));
+            if similarity >= threshold {
+                duplicates.push(LikelyDuplicate { first, second, similarity });
+            }
+        }
+    }
+
+    duplicates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    duplicates
+}
+
+/// Lowercase words of text, without punctuation and one- or two-letter words such as "a" or "to"
+fn normalized_words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 2)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Share of the words two texts have in common (Jaccard index); 0 when both are empty
+fn overlap(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket(title: &str, description: &str) -> Ticket {
+        Ticket {
+            description: (!description.is_empty()).then(|| description.to_string()),
+            ..crate::models::test_ticket("T-1", title)
+        }
+    }
+
+    #[test]
+    fn similar_tickets_are_flagged_most_similar_first() {
+        let tickets = [
+            ticket("Export to CSV fails for large teams", "The export times out after 30 seconds."),
+            ticket("Dark mode for the settings page", ""),
+            ticket("CSV export fails for large teams!", ""),
+            ticket("Exporting large teams to CSV times out", "The export times out after 30 seconds."),
+        ];
+        let pairs = |threshold: f64| -> Vec<(usize, usize)> {
+            find_duplicates(&tickets, threshold)
+                .iter()
+                .map(|duplicate| (duplicate.first, duplicate.second))
+                .collect()
+        };
+
+        assert_eq!(pairs(DEFAULT_DUPLICATE_THRESHOLD), [(0, 2), (0, 3)]);
+        assert_eq!(pairs(0.9), [(0, 2)]);
+        assert!(find_duplicates(&[ticket("", ""), ticket("", "")], 0.1).is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use anyhow::{Context, Result};
+use pulldown_cmark::{html, Event, Options, Parser};
+use std::path::Path;
+
+use crate::models::{format_estimate, Ticket};
+
+/// Write the ticket list as a flat CSV file, one row per ticket
+pub fn write_tickets_csv(tickets: &[Ticket], path: &Path) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)
+        .context(format!("Failed to create CSV file at {}", path.display()))?;
+
+    writer.write_record([
+        "id", "title", "state", "priority", "estimate", "assignee", "url", "labels",
+    ])?;
+
+    for ticket in tickets {
+        writer.write_record([
+            ticket.id.as_str(),
+            ticket.title.as_str(),
+            ticket.state.as_str(),
+            &ticket.priority.to_string(),
+            &ticket.estimate.map(|e| format_estimate(Some(e))).unwrap_or_default(),
+            ticket.assignee.as_deref().unwrap_or_default(),
+            ticket.url.as_str(),
+            &ticket.labels.join(", "),
+        ])?;
+    }
+
+    writer.flush().context("Failed to write CSV file")?;
+    Ok(())
+}
+
+/// Render the ticket list as a GitHub-flavored markdown table for pasting into PRs or wikis
+pub fn format_tickets_markdown_table(tickets: &[Ticket]) -> String {
+    let mut table = String::from("| ID | Title | State | Priority | Estimate | Labels | Link |\n");
+    table.push_str("| --- | --- | --- | --- | --- | --- | --- |\n");
+
+    for ticket in tickets {
+        table.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | [Open]({}) |\n",
+            escape_table_cell(&ticket.id),
+            escape_table_cell(&ticket.title),
+            escape_table_cell(&ticket.state),
+            crate::ui::priority_label(ticket.priority),
+            ticket.estimate.map(|e| format_estimate(Some(e))).unwrap_or_default(),
+            escape_table_cell(&ticket.labels.join(", ")),
+            ticket.url,
+        ));
+    }
+
+    table
+}
+
+/// Escape characters that would break a markdown table cell
+fn escape_table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+/// Render an implementation plan as a standalone HTML page, with the ticket metadata as
+/// a header, for publishing to wikis that take HTML
+pub fn format_plan_html(ticket: &Ticket, implementation_plan: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    // Raw HTML in the generated plan is shown as text rather than passed through
+    let events = Parser::new_ext(implementation_plan, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        event => event,
+    });
+    let mut body = String::new();
+    html::push_html(&mut body, events);
+
+    format!(
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{id}: {title}</title>\n\
+         <style>\n\
+         body {{ font-family: -apple-system, \"Segoe UI\", Helvetica, Arial, sans-serif; max-width: 50rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.5; color: #24292f; }}\n\
+         .meta {{ color: #57606a; border-bottom: 1px solid #d0d7de; padding-bottom: 1rem; }}\n\
+         pre {{ background: #f6f8fa; padding: 1rem; overflow-x: auto; border-radius: 6px; }}\n\
+         code {{ font-family: ui-monospace, SFMono-Regular, Menlo, monospace; font-size: 0.9em; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         th, td {{ border: 1px solid #d0d7de; padding: 0.4rem 0.8rem; }}\n\
+         </style>\n\
+         </head>\n\
+         <body>\n\
+         <h1>Implementation Plan: {title}</h1>\n\
+         <p class=\"meta\">\n\
+         <strong>Ticket ID:</strong> <a href=\"{url}\">{id}</a><br>\n\
+         <strong>State:</strong> {state}<br>\n\
+         <strong>Priority:</strong> {priority}<br>\n\
+         <strong>Estimate:</strong> {estimate}\n\
+         </p>\n\
+         {body}\
+         </body>\n\
+         </html>\n",
+        id = escape_html(&ticket.id),
+        title = escape_html(&ticket.title),
+        url = escape_html(&ticket.url),
+        state = escape_html(&ticket.state),
+        priority = crate::ui::priority_label(ticket.priority),
+        estimate = format_estimate(ticket.estimate),
+        body = body,
+    )
+}
+
+/// Render an implementation plan as an Emacs Org-mode document: the ticket is the top
+/// heading with its metadata in a property drawer, and the plan's markdown is converted
+/// for the common subset (headings one level below it, emphasis, inline code, links,
+/// tables and fenced code blocks as source blocks)
+pub fn format_plan_org(ticket: &Ticket, implementation_plan: &str) -> String {
+    let labels = if ticket.labels.is_empty() { "None".to_string() } else { ticket.labels.join(", ") };
+    let mut org = format!(
+        "#+TITLE: Implementation Plan: {title}\n\n\
+         * {id}: {title}\n\
+         :PROPERTIES:\n\
+         :TICKET_ID: {id}\n\
+         :URL:       {url}\n\
+         :STATE:     {state}\n\
+         :PRIORITY:  {priority}\n\
+         :ESTIMATE:  {estimate}\n\
+         :LABELS:    {labels}\n\
+         :END:\n\n",
+        id = ticket.id,
+        title = ticket.title,
+        url = ticket.url,
+        state = ticket.state,
+        priority = crate::ui::priority_label(ticket.priority),
+        estimate = format_estimate(ticket.estimate),
+        labels = labels,
+    );
+
+    let mut in_code_block = false;
+    for line in implementation_plan.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            if in_code_block {
+                org.push_str("#+END_SRC\n");
+            } else {
+                match trimmed.trim_start_matches(['`', '~']).trim() {
+                    "" => org.push_str("#+BEGIN_SRC\n"),
+                    language => org.push_str(&format!("#+BEGIN_SRC {}\n", language)),
+                }
+            }
+            in_code_block = !in_code_block;
+        } else if in_code_block {
+            // Lines starting with * or #+ would be read as headings or keywords; Org
+            // escapes them with a comma
+            if line.starts_with('*') || line.starts_with("#+") {
+                org.push(',');
+            }
+            org.push_str(line);
+            org.push('\n');
+        } else {
+            org.push_str(&org_line(line));
+            org.push('\n');
+        }
+    }
+    // A block the model left open would swallow nothing more, but Org needs the end marker
+    if in_code_block {
+        org.push_str("#+END_SRC\n");
+    }
+    org
+}
+
+/// Convert a markdown line outside code blocks to Org
+fn org_line(line: &str) -> String {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+        // One level below the ticket's heading
+        return format!("{} {}", "*".repeat(level + 1), org_inline(line[level..].trim()));
+    }
+
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    if let Some(item) = trimmed.strip_prefix("* ").or_else(|| trimmed.strip_prefix("+ ")) {
+        // A bullet starting with * at the start of a line would be a heading in Org
+        return format!("{}- {}", indent, org_inline(item));
+    }
+    if trimmed.starts_with('|') && trimmed.trim_end().trim_matches('|').chars().all(|c| matches!(c, '-' | ':' | '|' | ' ')) {
+        let columns = trimmed.trim_end().trim_matches('|').split('|').count();
+        return format!("{}|{}|", indent, vec!["---"; columns].join("+"));
+    }
+    if matches!(trimmed.trim_end(), "---" | "***" | "___") {
+        return "-----".to_string();
+    }
+    org_inline(line)
+}
+
+/// Convert inline markdown to Org: `code` to ~code~, **bold** to *bold* and
+/// [text](url) to [[url][text]]. Code spans are left as they are inside.
+fn org_inline(text: &str) -> String {
+    let mut org = String::new();
+    for (i, segment) in text.split('`').enumerate() {
+        if i % 2 == 1 {
+            org.push_str(&format!("~{}~", segment));
+        } else {
+            org.push_str(&org_links(&segment.replace("**", "*")));
+        }
+    }
+    org
+}
+
+/// Convert markdown links ([text](url)) to Org links ([[url][text]])
+fn org_links(text: &str) -> String {
+    let mut org = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        let link = rest[start..]
+            .split_once("](")
+            .and_then(|(label, after)| Some((label, after.split_once(')')?)))
+            .filter(|(label, _)| !label[1..].contains(['[', ']']));
+        match link {
+            Some((label, (url, after))) => {
+                org.push_str(&rest[..start]);
+                org.push_str(&format!("[[{}][{}]]", url, &label[1..]));
+                rest = after;
+            }
+            None => {
+                org.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    org.push_str(rest);
+    org
+}
+
+/// Escape text for use in HTML content or attribute values
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_are_converted_to_org() {
+        let ticket = Ticket {
+            priority: 2,
+            estimate: Some(3.0),
+            labels: vec!["Backend".to_string(), "Export".to_string()],
+            url: "https://linear.app/acme/issue/ABC-1".to_string(),
+            ..crate::models::test_ticket("ABC-1", "Export to CSV")
+        };
+        let plan = "# Overview\n\
+                    Add **streaming** to `export.rs`, see [the docs](https://example.com/docs).\n\n\
+                    ## Steps\n\
+                    * Write rows\n  \
+                    * Flush\n\n\
+                    | Step | Owner |\n\
+                    | --- | :---: |\n\n\
+                    ```rust\n\
+                    *writer = Writer::new();\n\
+                    ```\n";
+
+        let org = format_plan_org(&ticket, plan);
+        assert!(org.starts_with("#+TITLE: Implementation Plan: Export to CSV\n\n* ABC-1: Export to CSV\n:PROPERTIES:\n"));
+        assert!(org.contains(":ESTIMATE:  3\n:LABELS:    Backend, Export\n:END:\n"));
+        let body: Vec<&str> = org.lines().skip_while(|line| *line != ":END:").skip(2).collect();
+        assert_eq!(
+            body,
+            [
+                "** Overview",
+                "Add *streaming* to ~export.rs~, see [[https://example.com/docs][the docs]].",
+                "",
+                "*** Steps",
+                "- Write rows",
+                "  - Flush",
+                "",
+                "| Step | Owner |",
+                "|---+---|",
+                "",
+                "#+BEGIN_SRC rust",
+                ",*writer = Writer::new();",
+                "#+END_SRC",
+            ]
+        );
+    }
+}
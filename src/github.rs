@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+
+/// Default GitHub REST API base URL, overridable with GITHUB_API_URL (e.g. for GitHub Enterprise)
+pub const DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+
+/// Files requested per page of a pull request's file list (the API maximum)
+const FILES_PAGE_SIZE: usize = 100;
+
+/// The API lists at most this many files of a pull request
+const MAX_FILES: usize = 3000;
+
+/// Characters of each file's patch included in the summary
+const MAX_PATCH_CHARS: usize = 2000;
+
+/// Characters of patches included in the summary in total; later files are listed without one
+const MAX_TOTAL_PATCH_CHARS: usize = 40_000;
+
+/// A pull request with its changed files, as needed to compare it with a plan (--verify)
+#[derive(Debug, Deserialize)]
+pub struct PullRequest {
+    pub title: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    pub html_url: String,
+    #[serde(skip)]
+    pub files: Vec<PullRequestFile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PullRequestFile {
+    pub filename: String,
+    pub status: String,
+    pub additions: u64,
+    pub deletions: u64,
+    /// Missing for binary files and very large diffs
+    #[serde(default)]
+    pub patch: Option<String>,
+}
+
+impl PullRequest {
+    /// Describe the pull request for a prompt: title, description and each changed file
+    /// with its line counts and (shortened) patch
+    pub fn summary(&self) -> String {
+        let mut summary = format!("Title: {}\nURL: {}\n", self.title, self.html_url);
+        let body = self.body.as_deref().map(str::trim).unwrap_or_default();
+        summary.push_str(&format!(
+            "Description: {}\n\nChanged files ({}):\n",
+            if body.is_empty() { "None" } else { body },
+            self.files.len()
+        ));
+
+        let mut patch_chars = 0;
+        for file in &self.files {
+            summary.push_str(&format!(
+                "\n- {} ({}, +{} -{})\n",
+                file.filename, file.status, file.additions, file.deletions
+            ));
+            if let Some(patch) = &file.patch {
+                if patch_chars < MAX_TOTAL_PATCH_CHARS {
+                    let shown: String = patch.chars().take(MAX_PATCH_CHARS).collect();
+                    patch_chars += shown.chars().count();
+                    summary.push_str(&format!("```diff\n{}\n```\n", shown));
+                    if shown.len() < patch.len() {
+                        summary.push_str("(patch truncated)\n");
+                    }
+                }
+            }
+        }
+
+        summary
+    }
+}
+
+/// Fetch a pull request and its changed files from a URL such as
+/// `https://github.com/owner/repo/pull/123`. The token is needed for private repositories.
+pub async fn fetch_pull_request(
+    client: &Client,
+    api_base: &str,
+    pr_url: &str,
+    token: Option<&str>,
+) -> Result<PullRequest> {
+    let (owner, repo, number) = parse_pull_request_url(pr_url)?;
+    let api_url = format!("{}/repos/{}/{}/pulls/{}", api_base.trim_end_matches('/'), owner, repo, number);
+
+    let mut pull_request: PullRequest = get_json(client, &api_url, token).await?;
+
+    for page in 1..=MAX_FILES / FILES_PAGE_SIZE {
+        let files_url = format!("{}/files?per_page={}&page={}", api_url, FILES_PAGE_SIZE, page);
+        let files: Vec<PullRequestFile> = get_json(client, &files_url, token).await?;
+        let last_page = files.len() < FILES_PAGE_SIZE;
+        pull_request.files.extend(files);
+        if last_page {
+            break;
+        }
+    }
+
+    Ok(pull_request)
+}
+
+/// Split a pull request URL into owner, repository and number
+fn parse_pull_request_url(pr_url: &str) -> Result<(String, String, u64)> {
+    let invalid = || {
+        anyhow::anyhow!(
+            "'{}' is not a GitHub pull request URL (https://github.com/owner/repo/pull/123)",
+            pr_url
+        )
+    };
+
+    // Any host is accepted, for GitHub Enterprise
+    let (_, path) = pr_url
+        .trim()
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .split_once('/')
+        .ok_or_else(invalid)?;
+    let parts: Vec<&str> = path.split(['/', '#', '?']).collect();
+    match parts.as_slice() {
+        [owner, repo, "pull", number, ..] if !owner.is_empty() && !repo.is_empty() => {
+            let number = number.parse().map_err(|_| invalid())?;
+            Ok((owner.to_string(), repo.to_string(), number))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(client: &Client, url: &str, token: Option<&str>) -> Result<T> {
+    // The GitHub API rejects requests without a User-Agent
+    let mut request = client
+        .get(url)
+        .header("User-Agent", crate::linear::DEFAULT_USER_AGENT)
+        .header("Accept", "application/vnd.github+json");
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+
+    let response = request.send().await.context("Failed to send request to GitHub API")?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        anyhow::bail!("GitHub API request failed with status {}: {}", status, error_text);
+    }
+
+    response.json().await.context("Failed to deserialize GitHub API response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(pr_url: &str) -> (String, String, u64) {
+        parse_pull_request_url(pr_url).unwrap()
+    }
+
+    #[test]
+    fn pull_request_urls_are_parsed() {
+        let expected = ("acme".to_string(), "api".to_string(), 123);
+        assert_eq!(parsed("https://github.com/acme/api/pull/123"), expected);
+        assert_eq!(parsed(" https://github.com/acme/api/pull/123/files "), expected);
+        assert_eq!(parsed("https://github.com/acme/api/pull/123#discussion_r1"), expected);
+        assert_eq!(parsed("https://github.com/acme/api/pull/123?w=1"), expected);
+        assert_eq!(parsed("https://github.acme.internal/acme/api/pull/123"), expected);
+    }
+
+    #[test]
+    fn other_urls_are_rejected() {
+        for pr_url in [
+            "https://github.com/acme/api/issues/123",
+            "https://github.com/acme/api",
+            "https://github.com/acme/api/pull/latest",
+            "https://github.com//api/pull/123",
+            "github.com",
+        ] {
+            assert!(parse_pull_request_url(pr_url).is_err(), "{}", pr_url);
+        }
+    }
+
+    #[test]
+    fn patch_budget_counts_characters() {
+        let files = (0..21)
+            .map(|i| PullRequestFile {
+                filename: format!("src/file_{}.rs", i),
+                status: "modified".to_string(),
+                additions: 1,
+                deletions: 1,
+                patch: Some("é".repeat(MAX_PATCH_CHARS)),
+            })
+            .collect();
+        let pull_request = PullRequest {
+            title: "Accents".to_string(),
+            body: None,
+            html_url: "https://github.com/acme/api/pull/1".to_string(),
+            files,
+        };
+
+        // Twenty full patches fit the budget in characters, though not in bytes
+        let summary = pull_request.summary();
+        assert_eq!(summary.matches("```diff").count(), MAX_TOTAL_PATCH_CHARS / MAX_PATCH_CHARS);
+        assert!(!summary.contains("(patch truncated)"));
+    }
+}
@@ -1,18 +1,495 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use reqwest::Client;
+use colored::Colorize;
+use reqwest::{Client, StatusCode};
 use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use crate::models::{
-    Comment, LinearResponse, LinearState, LinearUsersResponse, LinearViewerResponse, RelatedTicket,
-    Ticket,
+    Comment, LinearAssignee, LinearIssue, LinearNodesContainer, LinearPageInfo, LinearProject, LinearResponse,
+    LinearState, LinearUsersResponse, LinearViewer, LinearViewerResponse, PullRequestLink, RelatedTicket, Ticket,
 };
+use crate::ui::Reporter;
 
-const LINEAR_API_URL: &str = "https://api.linear.app/graphql";
+/// Default Linear GraphQL endpoint, overridable with LINEAR_API_URL or --linear-api-url
+pub const DEFAULT_LINEAR_API_URL: &str = "https://api.linear.app/graphql";
 
+/// Default User-Agent for Linear requests, overridable with LINEAR_USER_AGENT
+pub const DEFAULT_USER_AGENT: &str = concat!("linear-agent/", env!("CARGO_PKG_VERSION"));
+
+/// Default number of nodes requested per page of a paginated connection
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Largest page size the Linear API accepts
+pub const MAX_PAGE_SIZE: usize = 250;
+
+/// Maximum number of user-name suggestions offered when a user is not found
+const MAX_USER_SUGGESTIONS: usize = 3;
+
+/// Minimum normalized similarity for a name to be suggested
+const USER_SUGGESTION_THRESHOLD: f64 = 0.6;
+
+/// Linear's workflow state types, shared by every team regardless of state names
+pub const STATE_TYPES: [&str; 6] = ["triage", "backlog", "unstarted", "started", "completed", "canceled"];
+
+/// Which workflow states to fetch tickets in: a selection of states, minus any excluded
+/// state names. Exclusion wins when a state is both selected and excluded.
+#[derive(Debug, Clone, Copy)]
+pub struct StateFilter<'a> {
+    pub selection: StateSelection<'a>,
+    pub exclude: &'a [String],
+}
+
+/// The states selected by a `StateFilter`: all of them, exact state names, or state types,
+/// which stay the same when a team renames its workflow states
+#[derive(Debug, Clone, Copy)]
+pub enum StateSelection<'a> {
+    All,
+    Names(&'a [String]),
+    Types(&'a [String]),
+}
+
+impl StateFilter<'_> {
+    /// The filter as a GraphQL `WorkflowStateFilter` variable
+    fn to_variable(self) -> serde_json::Value {
+        let mut filter = match self.selection {
+            StateSelection::All => json!({}),
+            StateSelection::Names(names) => json!({ "name": { "in": names } }),
+            StateSelection::Types(types) => json!({ "type": { "in": types } }),
+        };
+        if !self.exclude.is_empty() {
+            filter["name"]["nin"] = json!(self.exclude);
+        }
+        filter
+    }
+
+    /// Whether a state name is excluded, checked again on the results in case the API
+    /// ignored the filter
+    fn excludes(&self, state: &str) -> bool {
+        self.exclude.iter().any(|excluded| excluded.eq_ignore_ascii_case(state))
+    }
+}
+
+impl std::fmt::Display for StateFilter<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.selection {
+            StateSelection::All => write!(f, "all states")?,
+            StateSelection::Names(names) => write!(f, "the states: {}", names.join(", "))?,
+            StateSelection::Types(types) => write!(f, "the state types: {}", types.join(", "))?,
+        }
+        if !self.exclude.is_empty() {
+            write!(f, " (excluding {})", self.exclude.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returned when no Linear user matches the requested name exactly
+#[derive(Debug, thiserror::Error)]
+#[error("User '{name}' not found{}", format_suggestions(.suggestions))]
+pub struct UserNotFound {
+    pub name: String,
+    pub suggestions: Vec<String>,
+}
+
+fn format_suggestions(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        let quoted: Vec<String> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+        format!(". Did you mean {}?", quoted.join(" or "))
+    }
+}
+
+/// Failures of a Linear API request. They reach callers wrapped in `anyhow::Error`,
+/// where the kind can be told apart with `downcast_ref::<LinearError>()`.
+#[derive(Debug, thiserror::Error)]
+pub enum LinearError {
+    /// The API key is missing, invalid or lacks access
+    #[error("Linear API rejected the API key: {body}")]
+    Unauthorized { body: String },
+    #[error("Linear API rate limit exceeded: {body}")]
+    RateLimited { body: String },
+    /// The API key works but may not read this data, e.g. a restricted key and labels
+    #[error("Linear API key lacks permission for this request: {body}")]
+    Forbidden { body: String },
+    /// The issue has disappeared (deleted, or no longer visible to the API key)
+    /// between listing and enrichment
+    #[error("Issue '{id}' not found")]
+    NotFound { id: String },
+    /// Any other unsuccessful HTTP status
+    #[error("Linear API request failed with status {status}: {body}")]
+    Http { status: StatusCode, body: String },
+    #[error("Linear API returned GraphQL errors: {0}")]
+    Graphql(String),
+    #[error("Failed to send request to Linear API: {0}")]
+    Network(reqwest::Error),
+    #[error("Failed to deserialize Linear API response: {0}")]
+    Deserialize(serde_json::Error),
+    #[error(transparent)]
+    Recording(#[from] crate::recording::RecordingError),
+}
+
+impl LinearError {
+    /// Classify a failed response from its HTTP status and the `extensions.code` of its
+    /// GraphQL errors, which is how Linear reports rate limiting, authentication and
+    /// permission errors
+    fn from_response(status: Option<StatusCode>, body: String) -> Self {
+        let code = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|json| {
+                json.get("errors")?
+                    .as_array()?
+                    .iter()
+                    .find_map(|error| error.pointer("/extensions/code")?.as_str().map(String::from))
+            });
+        
+        match (status, code.as_deref()) {
+            (_, Some("FORBIDDEN")) => LinearError::Forbidden { body },
+            (Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN), _) | (_, Some("AUTHENTICATION_ERROR")) => {
+                LinearError::Unauthorized { body }
+            }
+            (Some(StatusCode::TOO_MANY_REQUESTS), _) | (_, Some("RATELIMITED")) => LinearError::RateLimited { body },
+            (Some(status), _) => LinearError::Http { status, body },
+            (None, _) => LinearError::Graphql(body),
+        }
+    }
+    
+    /// What to do about this error when it happens while testing the connection
+    pub fn connection_advice(&self) -> &'static str {
+        match self {
+            LinearError::Unauthorized { .. } => {
+                "Your Linear API key looks invalid or revoked. Create a new one in Linear's API \
+                 settings and set it as LINEAR_API_KEY."
+            }
+            LinearError::RateLimited { .. } => "Linear is rate limiting this API key. Wait a minute and try again.",
+            LinearError::Forbidden { .. } => {
+                "Your Linear API key lacks permission for this data. Use a key with read access to the team."
+            }
+            LinearError::Http { status, .. } if status.is_server_error() => {
+                "Linear's API is having problems. Try again later."
+            }
+            LinearError::Network(error) => network_advice(error),
+            _ => "Check LINEAR_API_KEY and, if you changed it, LINEAR_API_URL.",
+        }
+    }
+}
+
+/// What to do about a request that never got a response, told apart by the kind of
+/// failure: DNS, TLS, timeout or refused connection
+pub fn network_advice(error: &reqwest::Error) -> &'static str {
+    // reqwest doesn't expose DNS or TLS failures as kinds; they show in the error chain
+    let mut chain = String::new();
+    let mut source: Option<&dyn std::error::Error> = Some(error);
+    while let Some(error) = source {
+        chain.push_str(&error.to_string().to_lowercase());
+        chain.push('\n');
+        source = error.source();
+    }
+    
+    if error.is_timeout() {
+        "The request timed out. Check your network connection, proxy and firewall."
+    } else if chain.contains("dns error") || chain.contains("failed to lookup address") {
+        "The API host name could not be resolved. Check your network connection and DNS, \
+         and the API URL if you changed it."
+    } else if chain.contains("certificate") || chain.contains("tls") || chain.contains("ssl") {
+        "The secure connection could not be set up. A proxy or firewall may be intercepting \
+         HTTPS; check LINEAR_AGENT_PROXY (or HTTPS_PROXY) and that its certificate is trusted."
+    } else if error.is_connect() {
+        "Could not connect to the API. Check your network connection, the proxy settings \
+         (LINEAR_AGENT_PROXY or HTTPS_PROXY) and the API URL if you changed it."
+    } else {
+        "Check your network connection and proxy settings."
+    }
+}
+
+/// Relation type seen from the ticket whose relations were fetched. An inverse relation
+/// was created from the other issue, so "blocks" there means this ticket is blocked.
+fn relation_type(linear_type: &str, inverse: bool) -> String {
+    match (linear_type, inverse) {
+        ("blocks", true) => "blocked_by".to_string(),
+        ("duplicate", true) => "duplicated_by".to_string(),
+        (linear_type, _) => linear_type.to_string(),
+    }
+}
+
+/// Build a ticket from an issue node, using the identifier (e.g. ABC-123) as its ID
+fn ticket_from_issue(issue: &LinearIssue, assignee: Option<String>) -> Ticket {
+    let labels = issue
+        .labels
+        .as_ref()
+        .map(|labels| labels.nodes.iter().map(|label| label.name.clone()).collect())
+        .unwrap_or_default();
+
+    let mut ticket = Ticket::new(
+        issue.identifier.clone(),
+        issue.title.clone(),
+        issue.description.clone(),
+        issue.priority.unwrap_or(0),
+        issue.estimate,
+        labels,
+        issue.url.clone(),
+        issue.state.name.clone(),
+        issue.created_at,
+        issue.updated_at,
+        assignee,
+    );
+    ticket.project = issue.project.as_ref().map(|project| project.name.clone());
+    if let Some(children) = &issue.children {
+        ticket.has_parent = Some(issue.parent.is_some());
+        ticket.has_children = Some(!children.nodes.is_empty());
+    }
+    ticket
+}
+
+/// Whether the error is Linear reporting that the issue doesn't exist
+fn is_not_found(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref::<LinearError>(), Some(LinearError::NotFound { .. }))
+}
+
+/// Treat a missing issue as an empty result, warning that the section was skipped.
+/// Any other error is passed through.
+fn or_empty<T: Default>(result: Result<T>, section: &str, ticket_id: &str, reporter: &Reporter) -> Result<T> {
+    match result {
+        Err(e) if is_not_found(&e) => {
+            reporter.info(
+                format!("⚠️ Issue {} not found while fetching its {}; leaving it out", ticket_id, section)
+                    .yellow()
+            );
+            Ok(T::default())
+        }
+        other => other,
+    }
+}
+
+/// Whether an attachment URL is a GitHub pull request or a GitLab merge request, on any
+/// host so that GitHub Enterprise and self-hosted GitLab count too
+fn is_pull_request_url(url: &str) -> bool {
+    url.contains("/pull/") || url.contains("/merge_requests/")
+}
+
+/// Find a mutation or subscription among the top-level operations of a GraphQL document
+fn write_operation(query: &str) -> Option<&'static str> {
+    let mut depth = 0usize;
+    let mut word = String::new();
+    let mut chars = query.chars().chain(std::iter::once(' ')).peekable();
+
+    // Operation keywords only appear outside braces, comments and strings
+    while let Some(c) = chars.next() {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+
+        if depth == 0 {
+            match word.as_str() {
+                "mutation" => return Some("mutation"),
+                "subscription" => return Some("subscription"),
+                _ => {}
+            }
+        }
+        word.clear();
+
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            '#' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '"' => skip_string(&mut chars),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Skip the rest of a GraphQL string whose opening quote was just read, including
+/// """block strings"""
+fn skip_string(chars: &mut std::iter::Peekable<impl Iterator<Item = char>>) {
+    if chars.next_if_eq(&'"').is_some() {
+        if chars.next_if_eq(&'"').is_none() {
+            return; // ""
+        }
+        let mut quotes = 0;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    quotes += 1;
+                    if quotes == 3 {
+                        return;
+                    }
+                }
+                '\\' => {
+                    quotes = 0;
+                    chars.next();
+                }
+                _ => quotes = 0,
+            }
+        }
+        return;
+    }
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' | '\n' => return,
+            _ => {}
+        }
+    }
+}
+
+/// Whether every GraphQL error is Linear's "Entity not found" error
+fn is_entity_not_found(errors: &serde_json::Value) -> bool {
+    errors.as_array().is_some_and(|errors| {
+        !errors.is_empty()
+            && errors.iter().all(|error| {
+                error
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .is_some_and(|m| m.starts_with("Entity not found"))
+            })
+    })
+}
+
+/// The ticket identifier in a Linear issue URL such as
+/// `https://linear.app/acme/issue/ABC-123/fix-the-login-bug`, with or without the title
+/// slug, query or fragment. Anything that isn't a URL is returned as given, trimmed.
+pub fn parse_ticket_reference(reference: &str) -> Result<String> {
+    let reference = reference.trim();
+    let without_scheme = reference
+        .strip_prefix("https://")
+        .or_else(|| reference.strip_prefix("http://"))
+        .unwrap_or(reference);
+    if !without_scheme.contains('/') {
+        return Ok(reference.to_string());
+    }
+
+    let invalid = || anyhow::anyhow!("not a Linear issue URL (https://linear.app/<workspace>/issue/ABC-123)");
+    let path = without_scheme.split(['?', '#']).next().unwrap_or_default();
+    let mut segments = path.split('/').skip(1);
+    segments.by_ref().find(|segment| *segment == "issue").ok_or_else(invalid)?;
+    let identifier = segments.next().ok_or_else(invalid)?;
+
+    // Identifiers are a team key and a number, e.g. ABC-123
+    match identifier.rsplit_once('-') {
+        Some((team, number))
+            if !team.is_empty()
+                && team.chars().all(|c| c.is_ascii_alphanumeric())
+                && !number.is_empty()
+                && number.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            Ok(identifier.to_uppercase())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Rank candidate names by how closely they match `name`, case-insensitively.
+/// Substring matches come first, followed by names within edit distance.
+pub fn suggest_user_names(name: &str, candidates: &[String]) -> Vec<String> {
+    let needle = name.trim().to_lowercase();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<(f64, &String)> = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let hay = candidate.to_lowercase();
+            let score = if hay.contains(&needle) || needle.contains(&hay) {
+                // Substring matches always rank above pure edit-distance matches
+                1.0 + strsim::normalized_levenshtein(&needle, &hay)
+            } else {
+                strsim::normalized_levenshtein(&needle, &hay)
+            };
+            (score >= USER_SUGGESTION_THRESHOLD).then_some((score, candidate))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored
+        .into_iter()
+        .map(|(_, candidate)| candidate.clone())
+        .take(MAX_USER_SUGGESTIONS)
+        .collect()
+}
+
+/// A person's share of a team's tickets in the selected states (--team-members)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberWorkload {
+    pub name: String,
+    pub open_tickets: usize,
+}
+
+/// The issue that subtasks are created under, resolved once per ticket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtaskParent {
+    pub id: String,
+    pub team_id: String,
+}
+
+/// Sections of a ticket fetched by `enrich_ticket`; each one costs a request or more
+#[derive(Debug, Clone, Copy)]
+pub struct EnrichOptions {
+    pub labels: bool,
+    pub comments: bool,
+    pub parent: bool,
+    pub children: bool,
+    pub relations: bool,
+    /// Descriptions of the related tickets, one request each; off by default
+    pub relation_descriptions: bool,
+    /// Subscribers and the previous assignee
+    pub history: bool,
+    /// Git branch name and attached pull requests
+    pub development: bool,
+}
+
+impl Default for EnrichOptions {
+    /// Every section except the related tickets' descriptions
+    fn default() -> Self {
+        Self {
+            labels: true,
+            comments: true,
+            parent: true,
+            children: true,
+            relations: true,
+            relation_descriptions: false,
+            history: true,
+            development: true,
+        }
+    }
+}
+
+impl EnrichOptions {
+    /// No section, leaving the ticket as listed; turn on just the sections needed
+    pub fn minimal() -> Self {
+        Self {
+            labels: false,
+            comments: false,
+            parent: false,
+            children: false,
+            relations: false,
+            relation_descriptions: false,
+            history: false,
+            development: false,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct LinearClient {
     client: Client,
     api_key: String,
+    api_url: String,
+    user_agent: String,
+    page_size: usize,
+    /// Records or replays requests (--record, --replay)
+    recorder: Option<Arc<crate::recording::Recorder>>,
+    /// Set once the missing labels permission has been warned about, across clones
+    labels_forbidden_warned: Arc<AtomicBool>,
 }
 
 impl LinearClient {
@@ -20,6 +497,24 @@ impl LinearClient {
         Self {
             client: Client::new(),
             api_key: api_key.to_string(),
+            api_url: DEFAULT_LINEAR_API_URL.to_string(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            page_size: DEFAULT_PAGE_SIZE,
+            recorder: None,
+            labels_forbidden_warned: Arc::new(AtomicBool::new(false)),
+        }
+    }
+    
+    /// Create a new client from AppConfig
+    pub fn from_config(app_config: &crate::config::AppConfig) -> Self {
+        Self {
+            // The proxy settings were validated when the config was loaded
+            client: app_config.http_client().unwrap_or_default(),
+            api_url: app_config.linear_api_url.clone(),
+            user_agent: app_config.linear_user_agent.clone(),
+            page_size: app_config.page_size,
+            recorder: crate::recording::Recorder::from_config(app_config),
+            ..Self::new(&app_config.linear_api_key)
         }
     }
 
@@ -28,6 +523,7 @@ impl LinearClient {
         let query = r#"
         query {
           viewer {
+            id
             name
           }
         }
@@ -55,9 +551,20 @@ impl LinearClient {
             }
             createdAt
             updatedAt
+            project {
+              name
+            }
             assignee {
               name
             }
+            parent {
+              id
+            }
+            children(first: 1) {
+              nodes {
+                id
+              }
+            }
           }
         }
         "#;
@@ -84,6 +591,12 @@ impl LinearClient {
             created_at: DateTime<Utc>,
             updated_at: DateTime<Utc>,
             assignee: Option<LinearAssignee>,
+            #[serde(default)]
+            project: Option<LinearProject>,
+            parent: Option<serde::de::IgnoredAny>,
+            // Absent from recordings made before the hints were selected
+            #[serde(default)]
+            children: Option<LinearNodesContainer<serde::de::IgnoredAny>>,
         }
 
         #[derive(serde::Deserialize)]
@@ -96,10 +609,10 @@ impl LinearClient {
 
         let issue = &response.data.issue;
 
-        let ticket = Ticket::new(
+        let mut ticket = Ticket::new(
             issue.identifier.clone(), // Use the identifier field for the ticket ID
             issue.title.clone(),
-            issue.description.clone().unwrap_or_default(),
+            issue.description.clone(),
             issue.priority.unwrap_or(0),
             issue.estimate,
             Vec::new(), // Will be populated in enrich_ticket
@@ -109,6 +622,11 @@ impl LinearClient {
             issue.updated_at,
             issue.assignee.as_ref().map(|a| a.name.clone()),
         );
+        ticket.project = issue.project.as_ref().map(|project| project.name.clone());
+        if let Some(children) = &issue.children {
+            ticket.has_parent = Some(issue.parent.is_some());
+            ticket.has_children = Some(!children.nodes.is_empty());
+        }
 
         Ok(ticket)
     }
@@ -118,27 +636,25 @@ impl LinearClient {
         &self,
         team_name: &str,
         user_name: &str,
-        states: &[String],
+        states: StateFilter<'_>,
         verbose: bool,
     ) -> Result<Vec<Ticket>> {
         let query = r#"
-        query UserTickets($teamName: String!, $assigneeName: String!, $states: [String!]!) {
+        query UserTickets($teamName: String!, $assigneeName: String!, $stateFilter: WorkflowStateFilter!, $pageSize: Int!, $after: String) {
           users(filter: { name: { eq: $assigneeName } }) {
             nodes {
               id
               name
               assignedIssues(
+                first: $pageSize
+                after: $after
                 filter: {
                   team: {
                     name: {
                       eq: $teamName
                     }
                   }
-                  state: { 
-                    name: { 
-                      in: $states 
-                    } 
-                  }
+                  state: $stateFilter
                   assignee: {
                     name: {
                       eq: $assigneeName
@@ -159,6 +675,23 @@ impl LinearClient {
                   }
                   createdAt
                   updatedAt
+                  labels {
+                    nodes {
+                      name
+                    }
+                  }
+                  parent {
+                    id
+                  }
+                  children(first: 1) {
+                    nodes {
+                      id
+                    }
+                  }
+                }
+                pageInfo {
+                  hasNextPage
+                  endCursor
                 }
               }
             }
@@ -169,81 +702,811 @@ impl LinearClient {
         let variables = json!({
             "teamName": team_name,
             "assigneeName": user_name,
-            "states": states
+            "stateFilter": states.to_variable()
         });
 
-        let response: LinearResponse<LinearUsersResponse> =
-            self.execute_query(query, variables, verbose).await?;
-
-        // Check if user exists
-        let users = &response.data.users.nodes;
-        if users.is_empty() {
-            anyhow::bail!("User '{}' not found", user_name);
-        }
-
-        // Get assigned issues
-        let user = &users[0];
-        let issues = &user.assigned_issues.nodes;
+        // Get assigned issues of the (first) matching user
+        let issues = self
+            .fetch_all_pages(query, variables, verbose, |data: LinearUsersResponse| {
+                data.users
+                    .nodes
+                    .into_iter()
+                    .next()
+                    .map(|user| user.assigned_issues)
+                    .ok_or_else(|| {
+                        UserNotFound {
+                            name: user_name.to_string(),
+                            suggestions: Vec::new(),
+                        }
+                        .into()
+                    })
+            })
+            .await;
+
+        // Check if user exists, suggesting close matches from the team if not
+        let issues = match issues {
+            Err(e) if e.downcast_ref::<UserNotFound>().is_some() => {
+                let members = self
+                    .fetch_users(team_name, verbose)
+                    .await
+                    .unwrap_or_default();
+                return Err(UserNotFound {
+                    name: user_name.to_string(),
+                    suggestions: suggest_user_names(user_name, &members),
+                }
+                .into());
+            }
+            issues => issues?,
+        };
 
         let tickets = issues
             .iter()
-            .map(|issue| {
-                Ticket::new(
-                    issue.identifier.clone(), // Use the identifier field instead of id
-                    issue.title.clone(),
-                    issue.description.clone().unwrap_or_default(),
-                    issue.priority.unwrap_or(0),
-                    issue.estimate,
-                    Vec::new(), // Will be populated in enrich_ticket
-                    issue.url.clone(),
-                    issue.state.name.clone(),
-                    issue.created_at,
-                    issue.updated_at,
-                    Some(user_name.to_string()),
-                )
-            })
+            .map(|issue| ticket_from_issue(issue, Some(user_name.to_string())))
+            .filter(|ticket| !states.excludes(&ticket.state))
             .collect();
 
         Ok(tickets)
     }
 
-    /// Enrich a ticket with additional information
-    pub async fn enrich_ticket(
+    /// Fetch the authenticated user (the owner of the API key)
+    pub async fn fetch_viewer(&self, verbose: bool) -> Result<LinearViewer> {
+        let query = r#"
+        query {
+          viewer {
+            id
+            name
+          }
+        }
+        "#;
+
+        let response: LinearResponse<LinearViewerResponse> =
+            self.execute_query(query, json!({}), verbose).await?;
+        Ok(response.data.viewer)
+    }
+
+    /// Fetch the names of the teams the authenticated user belongs to, sorted by name
+    pub async fn fetch_viewer_teams(&self, verbose: bool) -> Result<Vec<String>> {
+        let query = r#"
+        query ViewerTeams($pageSize: Int!, $after: String) {
+          viewer {
+            teams(first: $pageSize, after: $after) {
+              nodes {
+                name
+              }
+              pageInfo {
+                hasNextPage
+                endCursor
+              }
+            }
+          }
+        }
+        "#;
+
+        #[derive(serde::Deserialize)]
+        struct ViewerResponse {
+            viewer: ViewerTeams,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct ViewerTeams {
+            teams: LinearNodesContainer<Team>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Team {
+            name: String,
+        }
+
+        let teams = self
+            .fetch_all_pages(query, json!({}), verbose, |data: ViewerResponse| Ok(data.viewer.teams))
+            .await?;
+
+        let mut names: Vec<String> = teams.into_iter().map(|team| team.name).collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        Ok(names)
+    }
+
+    /// Fetch tickets assigned to the authenticated user, filtered by their id
+    pub async fn fetch_viewer_tickets(
         &self,
-        ticket: &Ticket,
+        viewer: &LinearViewer,
+        team_name: &str,
+        states: StateFilter<'_>,
         verbose: bool,
-        skip_labels: bool,
-    ) -> Result<Ticket> {
-        let mut enriched = ticket.clone();
-
-        // Fetch labels only if not skipped
-        if !skip_labels {
-            enriched.labels = self.fetch_ticket_labels(&ticket.id, verbose).await?;
+    ) -> Result<Vec<Ticket>> {
+        let query = r#"
+        query ViewerTickets($teamName: String!, $assigneeId: ID!, $stateFilter: WorkflowStateFilter!, $pageSize: Int!, $after: String) {
+          issues(
+            first: $pageSize
+            after: $after
+            filter: {
+              team: { name: { eq: $teamName } }
+              state: $stateFilter
+              assignee: { id: { eq: $assigneeId } }
+            }
+          ) {
+            nodes {
+              id
+              identifier
+              title
+              description
+              priority
+              estimate
+              url
+              state {
+                name
+              }
+              createdAt
+              updatedAt
+              project {
+                name
+              }
+              labels {
+                nodes {
+                  name
+                }
+              }
+              parent {
+                id
+              }
+              children(first: 1) {
+                nodes {
+                  id
+                }
+              }
+            }
+            pageInfo {
+              hasNextPage
+              endCursor
+            }
+          }
         }
+        "#;
 
-        // Fetch comments
-        enriched.comments = self.fetch_ticket_comments(&ticket.id, verbose).await?;
+        let variables = json!({
+            "teamName": team_name,
+            "assigneeId": viewer.id,
+            "stateFilter": states.to_variable()
+        });
 
-        // Fetch parent ticket
-        enriched.parent = self.fetch_ticket_parent(&ticket.id, verbose).await?;
+        #[derive(serde::Deserialize)]
+        struct IssuesResponse {
+            issues: LinearNodesContainer<LinearIssue>,
+        }
 
-        // Fetch children tickets
-        enriched.children = self.fetch_ticket_children(&ticket.id, verbose).await?;
+        let issues = self
+            .fetch_all_pages(query, variables, verbose, |data: IssuesResponse| Ok(data.issues))
+            .await?;
 
-        // Fetch related tickets
-        enriched.related_tickets = self.fetch_related_tickets(&ticket.id, verbose).await?;
+        let tickets = issues
+            .iter()
+            .map(|issue| ticket_from_issue(issue, Some(viewer.name.clone())))
+            .filter(|ticket| !states.excludes(&ticket.state))
+            .collect();
 
-        Ok(enriched)
+        Ok(tickets)
     }
 
-    /// Fetch labels for a ticket
-    async fn fetch_ticket_labels(&self, ticket_id: &str, verbose: bool) -> Result<Vec<String>> {
+    /// Fetch unassigned tickets of a team in the given states, for backlog grooming
+    pub async fn fetch_unassigned_tickets(
+        &self,
+        team_name: &str,
+        states: StateFilter<'_>,
+        verbose: bool,
+    ) -> Result<Vec<Ticket>> {
         let query = r#"
-        query TicketLabels($issueId: String!) {
-          issue(id: $issueId) {
-            labels {
+        query UnassignedTickets($teamName: String!, $stateFilter: WorkflowStateFilter!, $pageSize: Int!, $after: String) {
+          issues(
+            first: $pageSize
+            after: $after
+            filter: {
+              team: { name: { eq: $teamName } }
+              state: $stateFilter
+              assignee: { null: true }
+            }
+          ) {
+            nodes {
+              id
+              identifier
+              title
+              description
+              priority
+              estimate
+              url
+              state {
+                name
+              }
+              createdAt
+              updatedAt
+              project {
+                name
+              }
+              labels {
+                nodes {
+                  name
+                }
+              }
+              parent {
+                id
+              }
+              children(first: 1) {
+                nodes {
+                  id
+                }
+              }
+            }
+            pageInfo {
+              hasNextPage
+              endCursor
+            }
+          }
+        }
+        "#;
+
+        let variables = json!({
+            "teamName": team_name,
+            "stateFilter": states.to_variable()
+        });
+
+        #[derive(serde::Deserialize)]
+        struct IssuesResponse {
+            issues: LinearNodesContainer<LinearIssue>,
+        }
+
+        let issues = self
+            .fetch_all_pages(query, variables, verbose, |data: IssuesResponse| Ok(data.issues))
+            .await?;
+
+        let tickets = issues
+            .iter()
+            .map(|issue| ticket_from_issue(issue, None))
+            .filter(|ticket| !states.excludes(&ticket.state))
+            .collect();
+
+        Ok(tickets)
+    }
+
+    /// Fetch the issues of a saved Linear view (custom view), using the view's own filters
+    pub async fn fetch_view_tickets(&self, view_id: &str, verbose: bool) -> Result<Vec<Ticket>> {
+        let query = r#"
+        query ViewTickets($viewId: String!, $pageSize: Int!, $after: String) {
+          customView(id: $viewId) {
+            name
+            issues(first: $pageSize, after: $after) {
+              nodes {
+                id
+                identifier
+                title
+                description
+                priority
+                estimate
+                url
+                state {
+                  name
+                }
+                createdAt
+                updatedAt
+                project {
+                  name
+                }
+                labels {
+                  nodes {
+                    name
+                  }
+                }
+                parent {
+                  id
+                }
+                children(first: 1) {
+                  nodes {
+                    id
+                  }
+                }
+                assignee {
+                  name
+                }
+              }
+              pageInfo {
+                hasNextPage
+                endCursor
+              }
+            }
+          }
+        }
+        "#;
+
+        let variables = json!({
+            "viewId": view_id
+        });
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ViewResponse {
+            custom_view: Option<CustomView>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct CustomView {
+            issues: LinearNodesContainer<LinearIssue>,
+        }
+
+        // Unknown views and views the API key can't access both come back as GraphQL errors
+        let issues = self
+            .fetch_all_pages(query, variables, verbose, |data: ViewResponse| {
+                data.custom_view.map(|view| view.issues).context(format!(
+                    "Linear view '{}' was not found or you don't have permission to see it",
+                    view_id
+                ))
+            })
+            .await
+            .context(format!(
+                "Could not load Linear view '{}'. Check the view ID and that your API key has access to it",
+                view_id
+            ))?;
+
+        let tickets = issues
+            .iter()
+            .map(|issue| {
+                ticket_from_issue(issue, issue.assignee.as_ref().map(|a| a.name.clone()))
+            })
+            .collect();
+
+        Ok(tickets)
+    }
+
+    /// Fetch the names of all members of a team, a page at a time
+    pub async fn fetch_users(&self, team_name: &str, verbose: bool) -> Result<Vec<String>> {
+        let query = r#"
+        query TeamMembers($teamName: String!, $pageSize: Int!, $after: String) {
+          teams(filter: { name: { eq: $teamName } }) {
+            nodes {
+              members(first: $pageSize, after: $after) {
+                nodes {
+                  name
+                }
+                pageInfo {
+                  hasNextPage
+                  endCursor
+                }
+              }
+            }
+          }
+        }
+        "#;
+
+        let variables = json!({
+            "teamName": team_name
+        });
+
+        #[derive(serde::Deserialize)]
+        struct TeamsResponse {
+            teams: LinearNodesContainer<TeamMembers>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct TeamMembers {
+            members: LinearNodesContainer<Member>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Member {
+            name: String,
+        }
+
+        let members = self
+            .fetch_all_pages(query, variables, verbose, |data: TeamsResponse| {
+                // An unknown team has no members
+                Ok(data.teams.nodes.into_iter().next().map_or(
+                    LinearNodesContainer { nodes: Vec::new(), page_info: None },
+                    |team| team.members,
+                ))
+            })
+            .await?;
+
+        Ok(members.into_iter().map(|member| member.name).collect())
+    }
+
+    /// Count the team's tickets in the selected states per assignee (--team-members).
+    /// Every member is listed, with 0 if nothing is assigned to them, as is anyone outside
+    /// the team with tickets in it. Busiest first, then by name.
+    pub async fn fetch_team_workload(
+        &self,
+        team_name: &str,
+        states: StateFilter<'_>,
+        verbose: bool,
+    ) -> Result<Vec<MemberWorkload>> {
+        let query = r#"
+        query TeamWorkload($teamName: String!, $stateFilter: WorkflowStateFilter!, $pageSize: Int!, $after: String) {
+          issues(
+            first: $pageSize
+            after: $after
+            filter: {
+              team: { name: { eq: $teamName } }
+              state: $stateFilter
+              assignee: { null: false }
+            }
+          ) {
+            nodes {
+              assignee {
+                name
+              }
+              state {
+                name
+              }
+            }
+            pageInfo {
+              hasNextPage
+              endCursor
+            }
+          }
+        }
+        "#;
+
+        let variables = json!({
+            "teamName": team_name,
+            "stateFilter": states.to_variable()
+        });
+
+        #[derive(serde::Deserialize)]
+        struct IssuesResponse {
+            issues: LinearNodesContainer<AssignedIssue>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct AssignedIssue {
+            assignee: Option<LinearAssignee>,
+            state: LinearState,
+        }
+
+        let issues = self
+            .fetch_all_pages(query, variables, verbose, |data: IssuesResponse| Ok(data.issues))
+            .await?;
+
+        let mut counts: HashMap<String, usize> = self
+            .fetch_users(team_name, verbose)
+            .await?
+            .into_iter()
+            .map(|name| (name, 0))
+            .collect();
+        for issue in issues.iter().filter(|issue| !states.excludes(&issue.state.name)) {
+            if let Some(assignee) = &issue.assignee {
+                *counts.entry(assignee.name.clone()).or_default() += 1;
+            }
+        }
+
+        let mut workload: Vec<MemberWorkload> = counts
+            .into_iter()
+            .map(|(name, open_tickets)| MemberWorkload { name, open_tickets })
+            .collect();
+        workload.sort_by(|a, b| b.open_tickets.cmp(&a.open_tickets).then_with(|| a.name.cmp(&b.name)));
+        Ok(workload)
+    }
+
+    /// Enrich a ticket with additional information. Sections left out of `options` keep
+    /// whatever the ticket already has.
+    pub async fn enrich_ticket(
+        &self,
+        ticket: &Ticket,
+        verbose: bool,
+        options: EnrichOptions,
+        reporter: &Reporter,
+    ) -> Result<Ticket> {
+        match self.enrich_sections(ticket, verbose, options, reporter).await {
+            // A missing issue (deleted since listing) fails every other section the same
+            // way, so enrichment stops at the first one and the ticket keeps its base data
+            Err(e) if is_not_found(&e) => {
+                reporter.info(
+                    format!("⚠️ Issue {} not found while enriching it; keeping what the listing had", ticket.id)
+                        .yellow()
+                );
+                Ok(ticket.clone())
+            }
+            result => result,
+        }
+    }
+
+    /// The ticket with the sections of `options` fetched
+    async fn enrich_sections(
+        &self,
+        ticket: &Ticket,
+        verbose: bool,
+        options: EnrichOptions,
+        reporter: &Reporter,
+    ) -> Result<Ticket> {
+        let mut enriched = ticket.clone();
+        let id = ticket.id.as_str();
+
+        // Restricted API keys can read issues but not labels; carry on without them
+        if options.labels {
+            enriched.labels = match self.fetch_ticket_labels(id, verbose).await {
+                Err(e) if matches!(e.downcast_ref::<LinearError>(), Some(LinearError::Forbidden { .. })) => {
+                    self.warn_labels_forbidden(reporter);
+                    Vec::new()
+                }
+                result => result?,
+            };
+        }
+
+        if options.comments {
+            enriched.comments = self.fetch_ticket_comments(id, verbose).await?;
+        }
+
+        // Tickets listed with hints that they have no parent or children skip those
+        // queries, which saves two requests for most tickets
+        if options.parent && ticket.has_parent != Some(false) {
+            enriched.parent = self.fetch_ticket_parent(id, verbose).await?;
+        }
+
+        if options.children && ticket.has_children != Some(false) {
+            enriched.children = self.fetch_ticket_children(id, verbose).await?;
+        }
+
+        if options.relations {
+            enriched.related_tickets = self.fetch_related_tickets(id, verbose).await?;
+        }
+
+        // One level deep only: the related tickets' own relations are never fetched. A
+        // related ticket that is gone only loses its description.
+        if options.relations && options.relation_descriptions {
+            for related in &mut enriched.related_tickets {
+                related.description =
+                    or_empty(self.fetch_ticket_description(&related.id, verbose).await, "description", &related.id, reporter)?;
+            }
+        }
+
+        // Stakeholders: subscribers and whoever had the ticket before the current assignee
+        if options.history {
+            enriched.subscribers = self.fetch_ticket_subscribers(id, verbose).await?;
+            enriched.previous_assignee = self.fetch_previous_assignee(id, verbose).await?;
+        }
+
+        // Whether work has started: a linked pull request, or only the branch name to use
+        if options.development {
+            (enriched.branch_name, enriched.pull_requests) = self.fetch_ticket_development(id, verbose).await?;
+        }
+
+        Ok(enriched)
+    }
+
+    /// Warn that labels can't be read with this API key, once per run
+    fn warn_labels_forbidden(&self, reporter: &Reporter) {
+        if !self.labels_forbidden_warned.swap(true, Ordering::SeqCst) {
+            reporter.info(
+                "⚠️ The Linear API key lacks permission to read labels; tickets are saved and planned without them"
+                    .yellow()
+            );
+        }
+    }
+
+    /// Fetch labels for a ticket
+    async fn fetch_ticket_labels(&self, ticket_id: &str, verbose: bool) -> Result<Vec<String>> {
+        let query = r#"
+        query TicketLabels($issueId: String!) {
+          issue(id: $issueId) {
+            labels {
+              nodes {
+                name
+              }
+            }
+          }
+        }
+        "#;
+
+        let variables = json!({
+            "issueId": ticket_id
+        });
+
+        #[derive(serde::Deserialize)]
+        struct LabelsResponse {
+            issue: Option<IssueLabels>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct IssueLabels {
+            labels: LabelsContainer,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct LabelsContainer {
+            nodes: Vec<Label>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Label {
+            name: String,
+        }
+
+        let response: LinearResponse<LabelsResponse> =
+            self.execute_query(query, variables, verbose).await?;
+
+        let labels = response
+            .data
+            .issue
+            .ok_or_else(|| LinearError::NotFound { id: ticket_id.to_string() })?
+            .labels
+            .nodes
+            .iter()
+            .map(|label| label.name.clone())
+            .collect();
+
+        Ok(labels)
+    }
+
+    /// Fetch comments for a ticket
+    async fn fetch_ticket_comments(&self, ticket_id: &str, verbose: bool) -> Result<Vec<Comment>> {
+        let query = r#"
+        query TicketComments($issueId: String!, $pageSize: Int!, $after: String) {
+          issue(id: $issueId) {
+            comments(first: $pageSize, after: $after) {
+              nodes {
+                id
+                body
+                createdAt
+                user {
+                  name
+                }
+              }
+              pageInfo {
+                hasNextPage
+                endCursor
+              }
+            }
+          }
+        }
+        "#;
+
+        let variables = json!({
+            "issueId": ticket_id
+        });
+
+        #[derive(serde::Deserialize)]
+        struct CommentsResponse {
+            issue: Option<IssueComments>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct IssueComments {
+            comments: LinearNodesContainer<CommentNode>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct CommentNode {
+            id: String,
+            body: String,
+            #[serde(rename = "createdAt")]
+            created_at: chrono::DateTime<Utc>,
+            user: Option<User>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct User {
+            name: String,
+        }
+
+        let comments = self
+            .fetch_all_pages(query, variables, verbose, |data: CommentsResponse| {
+                data.issue
+                    .map(|issue| issue.comments)
+                    .ok_or_else(|| LinearError::NotFound { id: ticket_id.to_string() }.into())
+            })
+            .await?
+            .iter()
+            .map(|comment| Comment {
+                id: comment.id.clone(),
+                body: comment.body.clone(),
+                created_at: comment.created_at,
+                user: comment.user.as_ref().map(|u| u.name.clone()),
+            })
+            .collect();
+
+        Ok(comments)
+    }
+
+    /// Fetch the description of a ticket, for the tickets related to the one being enriched
+    async fn fetch_ticket_description(&self, ticket_id: &str, verbose: bool) -> Result<Option<String>> {
+        let query = r#"
+        query TicketDescription($issueId: String!) {
+          issue(id: $issueId) {
+            description
+          }
+        }
+        "#;
+
+        let variables = json!({
+            "issueId": ticket_id
+        });
+
+        #[derive(serde::Deserialize)]
+        struct DescriptionResponse {
+            issue: Option<IssueDescription>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct IssueDescription {
+            description: Option<String>,
+        }
+
+        let response: LinearResponse<DescriptionResponse> =
+            self.execute_query(query, variables, verbose).await?;
+
+        let description = response
+            .data
+            .issue
+            .ok_or_else(|| LinearError::NotFound { id: ticket_id.to_string() })?
+            .description
+            .filter(|description| !description.trim().is_empty());
+
+        Ok(description)
+    }
+
+    /// Fetch the names of the users subscribed to a ticket
+    async fn fetch_ticket_subscribers(&self, ticket_id: &str, verbose: bool) -> Result<Vec<String>> {
+        let query = r#"
+        query TicketSubscribers($issueId: String!) {
+          issue(id: $issueId) {
+            subscribers {
+              nodes {
+                name
+              }
+            }
+          }
+        }
+        "#;
+
+        let variables = json!({
+            "issueId": ticket_id
+        });
+
+        #[derive(serde::Deserialize)]
+        struct SubscribersResponse {
+            issue: Option<IssueSubscribers>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct IssueSubscribers {
+            subscribers: Option<SubscribersContainer>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct SubscribersContainer {
+            nodes: Vec<Subscriber>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Subscriber {
+            name: String,
+        }
+
+        let response: LinearResponse<SubscribersResponse> =
+            self.execute_query(query, variables, verbose).await?;
+
+        let subscribers = response
+            .data
+            .issue
+            .ok_or_else(|| LinearError::NotFound { id: ticket_id.to_string() })?
+            .subscribers
+            .map(|container| container.nodes.into_iter().map(|s| s.name).collect())
+            .unwrap_or_default();
+
+        Ok(subscribers)
+    }
+
+    /// Fetch the git branch name of a ticket and the pull requests attached to it. Other
+    /// attachments (Slack threads, Figma files, ...) are left out.
+    async fn fetch_ticket_development(
+        &self,
+        ticket_id: &str,
+        verbose: bool,
+    ) -> Result<(Option<String>, Vec<PullRequestLink>)> {
+        let query = r#"
+        query TicketDevelopment($issueId: String!) {
+          issue(id: $issueId) {
+            branchName
+            attachments(first: 50) {
               nodes {
-                name
+                title
+                url
+                metadata
               }
             }
           }
@@ -255,51 +1518,70 @@ impl LinearClient {
         });
 
         #[derive(serde::Deserialize)]
-        struct LabelsResponse {
-            issue: IssueLabels,
+        struct DevelopmentResponse {
+            issue: Option<IssueDevelopment>,
         }
 
         #[derive(serde::Deserialize)]
-        struct IssueLabels {
-            labels: LabelsContainer,
+        #[serde(rename_all = "camelCase")]
+        struct IssueDevelopment {
+            branch_name: Option<String>,
+            attachments: Option<AttachmentsContainer>,
         }
 
         #[derive(serde::Deserialize)]
-        struct LabelsContainer {
-            nodes: Vec<Label>,
+        struct AttachmentsContainer {
+            nodes: Vec<Attachment>,
         }
 
         #[derive(serde::Deserialize)]
-        struct Label {
-            name: String,
+        struct Attachment {
+            title: String,
+            url: String,
+            #[serde(default)]
+            metadata: serde_json::Value,
         }
 
-        let response: LinearResponse<LabelsResponse> =
+        let response: LinearResponse<DevelopmentResponse> =
             self.execute_query(query, variables, verbose).await?;
 
-        let labels = response
+        let issue = response
             .data
             .issue
-            .labels
-            .nodes
-            .iter()
-            .map(|label| label.name.clone())
+            .ok_or_else(|| LinearError::NotFound { id: ticket_id.to_string() })?;
+        let pull_requests = issue
+            .attachments
+            .map(|container| container.nodes)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|attachment| is_pull_request_url(&attachment.url))
+            .map(|attachment| PullRequestLink {
+                status: attachment.metadata["status"].as_str().map(str::to_string),
+                title: attachment.title,
+                url: attachment.url,
+            })
             .collect();
+        let branch_name = issue.branch_name.filter(|branch| !branch.trim().is_empty());
 
-        Ok(labels)
+        Ok((branch_name, pull_requests))
     }
 
-    /// Fetch comments for a ticket
-    async fn fetch_ticket_comments(&self, ticket_id: &str, verbose: bool) -> Result<Vec<Comment>> {
+    /// Fetch the most recent previous assignee of a ticket from its history
+    async fn fetch_previous_assignee(
+        &self,
+        ticket_id: &str,
+        verbose: bool,
+    ) -> Result<Option<String>> {
         let query = r#"
-        query TicketComments($issueId: String!) {
+        query TicketAssigneeHistory($issueId: String!) {
           issue(id: $issueId) {
-            comments {
+            history(first: 50) {
               nodes {
-                id
-                body
                 createdAt
-                user {
+                fromAssignee {
+                  name
+                }
+                toAssignee {
                   name
                 }
               }
@@ -313,52 +1595,52 @@ impl LinearClient {
         });
 
         #[derive(serde::Deserialize)]
-        struct CommentsResponse {
-            issue: IssueComments,
+        struct HistoryResponse {
+            issue: Option<IssueHistory>,
         }
 
         #[derive(serde::Deserialize)]
-        struct IssueComments {
-            comments: CommentsContainer,
+        struct IssueHistory {
+            history: HistoryContainer,
         }
 
         #[derive(serde::Deserialize)]
-        struct CommentsContainer {
-            nodes: Vec<CommentNode>,
+        struct HistoryContainer {
+            nodes: Vec<HistoryEntry>,
         }
 
         #[derive(serde::Deserialize)]
-        struct CommentNode {
-            id: String,
-            body: String,
+        struct HistoryEntry {
             #[serde(rename = "createdAt")]
             created_at: chrono::DateTime<Utc>,
-            user: Option<User>,
+            #[serde(rename = "fromAssignee")]
+            from_assignee: Option<HistoryUser>,
+            #[serde(rename = "toAssignee")]
+            to_assignee: Option<HistoryUser>,
         }
 
         #[derive(serde::Deserialize)]
-        struct User {
+        struct HistoryUser {
             name: String,
         }
 
-        let response: LinearResponse<CommentsResponse> =
+        let response: LinearResponse<HistoryResponse> =
             self.execute_query(query, variables, verbose).await?;
 
-        let comments = response
+        // Only reassignments count; the first assignment has no previous assignee
+        let previous_assignee = response
             .data
             .issue
-            .comments
+            .ok_or_else(|| LinearError::NotFound { id: ticket_id.to_string() })?
+            .history
             .nodes
-            .iter()
-            .map(|comment| Comment {
-                id: comment.id.clone(),
-                body: comment.body.clone(),
-                created_at: comment.created_at,
-                user: comment.user.as_ref().map(|u| u.name.clone()),
-            })
-            .collect();
+            .into_iter()
+            .filter(|entry| entry.from_assignee.is_some() && entry.to_assignee.is_some())
+            .max_by_key(|entry| entry.created_at)
+            .and_then(|entry| entry.from_assignee)
+            .map(|user| user.name);
 
-        Ok(comments)
+        Ok(previous_assignee)
     }
 
     /// Fetch parent ticket for a ticket
@@ -391,7 +1673,7 @@ impl LinearClient {
 
         #[derive(serde::Deserialize)]
         struct ParentResponse {
-            issue: IssueParent,
+            issue: Option<IssueParent>,
         }
 
         #[derive(serde::Deserialize)]
@@ -420,11 +1702,20 @@ impl LinearClient {
         let response: LinearResponse<ParentResponse> =
             self.execute_query(query, variables, verbose).await?;
 
-        let parent = response.data.issue.parent.map(|parent| RelatedTicket {
+        let parent = response
+            .data
+            .issue
+            .ok_or_else(|| LinearError::NotFound { id: ticket_id.to_string() })?
+            .parent
+            .map(|parent| RelatedTicket {
             id: parent.identifier, // Use identifier instead of id
             title: parent.title,
             state: parent.state.name,
             assignee: parent.assignee.map(|a| a.name),
+            relation_type: "parent".to_string(),
+            resolved: false,
+            estimate: None,
+            description: None,
         });
 
         Ok(parent)
@@ -437,13 +1728,14 @@ impl LinearClient {
         verbose: bool,
     ) -> Result<Vec<RelatedTicket>> {
         let query = r#"
-        query TicketChildren($issueId: String!) {
+        query TicketChildren($issueId: String!, $pageSize: Int!, $after: String) {
           issue(id: $issueId) {
-            children {
+            children(first: $pageSize, after: $after) {
               nodes {
                 id
                 identifier
                 title
+                estimate
                 state {
                   name
                 }
@@ -451,6 +1743,10 @@ impl LinearClient {
                   name
                 }
               }
+              pageInfo {
+                hasNextPage
+                endCursor
+              }
             }
           }
         }
@@ -462,23 +1758,19 @@ impl LinearClient {
 
         #[derive(serde::Deserialize)]
         struct ChildrenResponse {
-            issue: IssueChildren,
+            issue: Option<IssueChildren>,
         }
 
         #[derive(serde::Deserialize)]
         struct IssueChildren {
-            children: ChildrenContainer,
-        }
-
-        #[derive(serde::Deserialize)]
-        struct ChildrenContainer {
-            nodes: Vec<ChildTicket>,
+            children: LinearNodesContainer<ChildTicket>,
         }
 
         #[derive(serde::Deserialize)]
         struct ChildTicket {
             identifier: String,
             title: String,
+            estimate: Option<f64>,
             state: TicketState,
             assignee: Option<TicketAssignee>,
         }
@@ -493,50 +1785,91 @@ impl LinearClient {
             name: String,
         }
 
-        let response: LinearResponse<ChildrenResponse> =
-            self.execute_query(query, variables, verbose).await?;
-
-        let children = response
-            .data
-            .issue
-            .children
-            .nodes
+        let children = self
+            .fetch_all_pages(query, variables, verbose, |data: ChildrenResponse| {
+                data.issue
+                    .map(|issue| issue.children)
+                    .ok_or_else(|| LinearError::NotFound { id: ticket_id.to_string() }.into())
+            })
+            .await?
             .iter()
             .map(|child| RelatedTicket {
                 id: child.identifier.clone(), // Use identifier instead of id
                 title: child.title.clone(),
                 state: child.state.name.clone(),
                 assignee: child.assignee.as_ref().map(|a| a.name.clone()),
+                relation_type: "child".to_string(),
+                resolved: false,
+                estimate: child.estimate,
+                description: None,
             })
             .collect();
 
         Ok(children)
     }
 
-    /// Fetch related tickets for a ticket
+    /// Fetch related tickets for a ticket, in both directions: the relations it was given
+    /// and the inverse ones, such as a ticket that blocks it. `relation_type` is set from
+    /// this ticket's side, e.g. "blocked_by".
     async fn fetch_related_tickets(
         &self,
         ticket_id: &str,
         verbose: bool,
     ) -> Result<Vec<RelatedTicket>> {
         let query = r#"
-        query RelatedIssues($issueId: String!) {
+        query RelatedIssues($issueId: String!, $pageSize: Int!, $after: String) {
           issue(id: $issueId) {
-            relations {
+            relations(first: $pageSize, after: $after) {
               nodes {
                 id
+                type
                 relatedIssue {
                   id
                   identifier
                   title
                   state {
                     name
+                    type
+                  }
+                  assignee {
+                    name
+                  }
+                }
+              }
+              pageInfo {
+                hasNextPage
+                endCursor
+              }
+            }
+          }
+        }
+        "#;
+
+        // Linear stores each relation once, on the issue it was created from
+        let inverse_query = r#"
+        query InverseRelations($issueId: String!, $pageSize: Int!, $after: String) {
+          issue(id: $issueId) {
+            inverseRelations(first: $pageSize, after: $after) {
+              nodes {
+                id
+                type
+                issue {
+                  id
+                  identifier
+                  title
+                  state {
+                    name
+                    type
                   }
                   assignee {
                     name
                   }
                 }
               }
+              pageInfo {
+                hasNextPage
+                endCursor
+              }
             }
           }
         }
@@ -548,25 +1881,21 @@ impl LinearClient {
 
         #[derive(serde::Deserialize)]
         struct RelationsResponse {
-            issue: IssueRelations,
+            issue: Option<IssueRelations>,
         }
 
         #[derive(serde::Deserialize)]
         struct IssueRelations {
-            relations: RelationsContainer,
-        }
-
-        #[derive(serde::Deserialize)]
-        struct RelationsContainer {
-            nodes: Vec<Relation>,
+            #[serde(alias = "inverseRelations")]
+            relations: LinearNodesContainer<Relation>,
         }
 
         #[derive(serde::Deserialize)]
         struct Relation {
-            // Skip this field completely since it's not used
-            #[serde(skip)]
-            id: String,
-            #[serde(rename = "relatedIssue")]
+            #[serde(rename = "type", default)]
+            relation_type: String,
+            /// The other issue: `relatedIssue` for relations, `issue` for inverse ones
+            #[serde(rename = "relatedIssue", alias = "issue")]
             related_issue: RelatedIssue,
         }
 
@@ -581,6 +1910,8 @@ impl LinearClient {
         #[derive(serde::Deserialize)]
         struct TicketState {
             name: String,
+            #[serde(rename = "type", default)]
+            state_type: String,
         }
 
         #[derive(serde::Deserialize)]
@@ -588,28 +1919,178 @@ impl LinearClient {
             name: String,
         }
 
-        let response: LinearResponse<RelationsResponse> =
-            self.execute_query(query, variables, verbose).await?;
+        let mut related = Vec::new();
+        for (query, inverse) in [(query, false), (inverse_query, true)] {
+            let relations = self
+                .fetch_all_pages(query, variables.clone(), verbose, |data: RelationsResponse| {
+                    data.issue
+                        .map(|issue| issue.relations)
+                        .ok_or_else(|| LinearError::NotFound { id: ticket_id.to_string() }.into())
+                })
+                .await?;
+
+            related.extend(relations.into_iter().map(|relation| RelatedTicket {
+                id: relation.related_issue.identifier, // Use identifier instead of id
+                title: relation.related_issue.title,
+                relation_type: relation_type(&relation.relation_type, inverse),
+                resolved: matches!(relation.related_issue.state.state_type.as_str(), "completed" | "canceled"),
+                estimate: None,
+                description: None,
+                state: relation.related_issue.state.name,
+                assignee: relation.related_issue.assignee.map(|a| a.name),
+            }));
+        }
+
+        Ok(related)
+    }
+
+    /// Look up the issue and team that subtasks of the ticket are created under
+    pub async fn fetch_subtask_parent(&self, ticket_id: &str, verbose: bool) -> Result<SubtaskParent> {
+        let query = r#"
+        query SubtaskParent($issueId: String!) {
+          issue(id: $issueId) {
+            id
+            team {
+              id
+            }
+          }
+        }
+        "#;
+
+        #[derive(serde::Deserialize)]
+        struct ParentResponse {
+            issue: Option<ParentIssue>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct ParentIssue {
+            id: String,
+            team: LinearTeam,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct LinearTeam {
+            id: String,
+        }
 
-        let related = response
+        let response: LinearResponse<ParentResponse> = self
+            .execute_query(query, json!({ "issueId": ticket_id }), verbose)
+            .await?;
+        let issue = response
             .data
             .issue
-            .relations
-            .nodes
-            .iter()
-            .map(|relation| RelatedTicket {
-                id: relation.related_issue.identifier.clone(), // Use identifier instead of id
-                title: relation.related_issue.title.clone(),
-                state: relation.related_issue.state.name.clone(),
-                assignee: relation
-                    .related_issue
-                    .assignee
-                    .as_ref()
-                    .map(|a| a.name.clone()),
-            })
-            .collect();
+            .ok_or_else(|| LinearError::NotFound { id: ticket_id.to_string() })?;
 
-        Ok(related)
+        Ok(SubtaskParent {
+            id: issue.id,
+            team_id: issue.team.id,
+        })
+    }
+
+    /// Create an issue titled `title` as a subtask of `parent`, in the parent's team.
+    /// Returns the identifier of the new issue.
+    pub async fn create_subtask(&self, parent: &SubtaskParent, title: &str, verbose: bool) -> Result<String> {
+        let mutation = r#"
+        mutation CreateSubtask($input: IssueCreateInput!) {
+          issueCreate(input: $input) {
+            success
+            issue {
+              identifier
+            }
+          }
+        }
+        "#;
+
+        let variables = json!({
+            "input": {
+                "title": title,
+                "parentId": parent.id,
+                "teamId": parent.team_id
+            }
+        });
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct CreateResponse {
+            issue_create: IssuePayload,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct IssuePayload {
+            success: bool,
+            issue: Option<CreatedIssue>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct CreatedIssue {
+            identifier: String,
+        }
+
+        let response: LinearResponse<CreateResponse> =
+            self.execute_query(mutation, variables, verbose).await?;
+
+        match response.data.issue_create {
+            IssuePayload {
+                success: true,
+                issue: Some(issue),
+            } => Ok(issue.identifier),
+            _ => anyhow::bail!("Linear did not create the subtask '{}'", title),
+        }
+    }
+
+    /// Run an arbitrary read-only GraphQL query and return the `data` of the response.
+    /// Mutations and subscriptions are rejected.
+    pub async fn execute_raw_query(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+        verbose: bool,
+    ) -> Result<serde_json::Value> {
+        if let Some(operation) = write_operation(query) {
+            anyhow::bail!(
+                "Only read queries can be run with --raw-query, found a {} operation",
+                operation
+            );
+        }
+
+        let response: LinearResponse<serde_json::Value> =
+            self.execute_query(query, variables, verbose).await?;
+
+        Ok(response.data)
+    }
+
+    /// Run a paginated query until the connection returned by `connection` has no next page,
+    /// collecting all nodes. The query must accept `$pageSize: Int!` and `$after: String`
+    /// and select `pageInfo { hasNextPage endCursor }` on the connection.
+    async fn fetch_all_pages<T, N>(
+        &self,
+        query: &str,
+        mut variables: serde_json::Value,
+        verbose: bool,
+        connection: impl Fn(T) -> Result<LinearNodesContainer<N>>,
+    ) -> Result<Vec<N>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut nodes = Vec::new();
+        variables["pageSize"] = json!(self.page_size);
+
+        loop {
+            let response: LinearResponse<T> =
+                self.execute_query(query, variables.clone(), verbose).await?;
+            let page = connection(response.data)?;
+            nodes.extend(page.nodes);
+
+            match page.page_info {
+                Some(LinearPageInfo {
+                    has_next_page: true,
+                    end_cursor: Some(cursor),
+                }) => variables["after"] = json!(cursor),
+                _ => break,
+            }
+        }
+
+        Ok(nodes)
     }
 
     /// Execute a GraphQL query against the Linear API
@@ -618,37 +2099,21 @@ impl LinearClient {
         query: &str,
         variables: serde_json::Value,
         verbose: bool,
-    ) -> Result<LinearResponse<T>>
+    ) -> Result<LinearResponse<T>, LinearError>
     where
         T: serde::de::DeserializeOwned,
     {
         let payload = json!({
             "query": query,
-            "variables": variables
+            "variables": &variables
         });
 
-        let response = self
-            .client
-            .post(LINEAR_API_URL)
-            .header("Authorization", &self.api_key)
-            .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
-            .await
-            .context("Failed to send request to Linear API")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            anyhow::bail!(
-                "Linear API request failed with status {}: {}",
-                status,
-                error_text
-            );
+        let (status, response_text) = self.post(&payload).await?;
+        if !status.is_success() {
+            return Err(LinearError::from_response(Some(status), response_text));
         }
 
         // Check for GraphQL errors in the response
-        let response_text = response.text().await?;
 
         // Debug print - only show if verbose mode is enabled
         if verbose {
@@ -658,11 +2123,16 @@ impl LinearClient {
             );
         }
 
-        let json: serde_json::Value = serde_json::from_str(&response_text)
-            .context("Failed to parse Linear API response as JSON")?;
+        let json: serde_json::Value =
+            serde_json::from_str(&response_text).map_err(LinearError::Deserialize)?;
 
-        if json.get("errors").is_some() {
-            anyhow::bail!("Linear API returned GraphQL errors: {}", response_text);
+        if let Some(errors) = json.get("errors") {
+            // Linear reports deleted or inaccessible issues as "Entity not found" errors
+            let issue_id = variables.get("issueId").and_then(|id| id.as_str());
+            if let (Some(id), true) = (issue_id, is_entity_not_found(errors)) {
+                return Err(LinearError::NotFound { id: id.to_string() });
+            }
+            return Err(LinearError::from_response(None, response_text));
         }
 
         // Now deserialize the successful response
@@ -674,8 +2144,556 @@ impl LinearClient {
                     println!("DEBUG Deserialization error: {}", e);
                     println!("DEBUG Linear User struct: {:?}", std::any::type_name::<T>());
                 }
-                anyhow::bail!("Failed to deserialize Linear API response: {}", e)
+                Err(LinearError::Deserialize(e))
+            }
+        }
+    }
+
+    /// Send a GraphQL payload and read the raw response, or take it from the recording
+    /// when replaying. Responses are recorded when recording.
+    async fn post(&self, payload: &serde_json::Value) -> Result<(StatusCode, String), LinearError> {
+        if let Some(recorder) = self.recorder.as_ref().filter(|recorder| recorder.is_replaying()) {
+            return Ok(recorder.replay("linear", payload)?);
+        }
+
+        let response = self
+            .client
+            .post(&self.api_url)
+            .header("Authorization", &self.api_key)
+            .header("Content-Type", "application/json")
+            .header("User-Agent", &self.user_agent)
+            .json(payload)
+            .send()
+            .await
+            .map_err(LinearError::Network)?;
+        let status = response.status();
+        let response_text = response.text().await.map_err(LinearError::Network)?;
+
+        if let Some(recorder) = &self.recorder {
+            recorder.record("linear", &self.api_url, payload, status, &response_text)?;
+        }
+        Ok((status, response_text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_string_contains, header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client_for(server: &MockServer) -> LinearClient {
+        LinearClient {
+            api_url: server.uri(),
+            ..LinearClient::new("lin_test_key")
+        }
+    }
+
+    fn issue_json(identifier: &str, title: &str) -> serde_json::Value {
+        json!({
+            "id": format!("uuid-{}", identifier),
+            "identifier": identifier,
+            "title": title,
+            "description": "Some description",
+            "priority": 2,
+            "estimate": 3.0,
+            "url": format!("https://linear.app/acme/issue/{}", identifier),
+            "state": { "name": "Todo" },
+            "createdAt": "2024-01-01T00:00:00Z",
+            "updatedAt": "2024-01-02T00:00:00Z",
+            "labels": { "nodes": [{ "name": "Bug" }] }
+        })
+    }
+
+    fn user_tickets_page(issues: Vec<serde_json::Value>, end_cursor: Option<&str>) -> serde_json::Value {
+        json!({
+            "data": {
+                "users": {
+                    "nodes": [{
+                        "id": "user-1",
+                        "name": "Jane Smith",
+                        "assignedIssues": {
+                            "nodes": issues,
+                            "pageInfo": {
+                                "hasNextPage": end_cursor.is_some(),
+                                "endCursor": end_cursor
+                            }
+                        }
+                    }]
+                }
             }
+        })
+    }
+
+    fn connection(nodes: serde_json::Value) -> serde_json::Value {
+        json!({ "nodes": nodes, "pageInfo": { "hasNextPage": false, "endCursor": null } })
+    }
+
+    async fn mock_query(server: &MockServer, operation: &str, body: serde_json::Value) {
+        Mock::given(method("POST"))
+            .and(body_string_contains(operation))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_connection_returns_viewer_name() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("Authorization", "lin_test_key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "viewer": { "id": "user-1", "name": "Jane Smith" } }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let name = client_for(&server).test_connection(false).await.unwrap();
+        assert_eq!(name, "Jane Smith");
+    }
+
+    #[tokio::test]
+    async fn http_errors_are_reported_with_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("internal error"))
+            .mount(&server)
+            .await;
+
+        let err = client_for(&server).test_connection(false).await.unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("500"), "{}", message);
+        assert!(message.contains("internal error"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_are_reported_by_kind() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "errors": [{ "message": "Rate limit exceeded", "extensions": { "code": "RATELIMITED" } }]
+            })))
+            .mount(&server)
+            .await;
+
+        let err = client_for(&server).test_connection(false).await.unwrap_err();
+        assert!(
+            matches!(err.downcast_ref::<LinearError>(), Some(LinearError::RateLimited { .. })),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn connection_failures_get_advice_by_cause() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("Authentication required"))
+            .mount(&server)
+            .await;
+        let err = client_for(&server).test_connection(false).await.unwrap_err();
+        let advice = err.downcast_ref::<LinearError>().unwrap().connection_advice();
+        assert!(advice.contains("API key looks invalid"), "{}", advice);
+
+        // Nothing listens on port 1, so the connection is refused
+        let unreachable = LinearClient {
+            api_url: "http://127.0.0.1:1".to_string(),
+            ..LinearClient::new("lin_test_key")
+        };
+        let err = unreachable.test_connection(false).await.unwrap_err();
+        let advice = err.downcast_ref::<LinearError>().unwrap().connection_advice();
+        assert!(advice.starts_with("Could not connect"), "{}", advice);
+    }
+
+    #[tokio::test]
+    async fn fetch_user_tickets_follows_pagination() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("\"after\":\"cursor-1\""))
+            .respond_with(ResponseTemplate::new(200).set_body_json(user_tickets_page(
+                vec![issue_json("ABC-3", "Third")],
+                None,
+            )))
+            .with_priority(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("UserTickets"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(user_tickets_page(
+                vec![issue_json("ABC-1", "First"), issue_json("ABC-2", "Second")],
+                Some("cursor-1"),
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tickets = client_for(&server)
+            .fetch_user_tickets("Engineering", "Jane Smith", StateFilter {
+                selection: StateSelection::Names(&["Todo".to_string()]),
+                exclude: &[],
+            }, false)
+            .await
+            .unwrap();
+
+        let ids: Vec<&str> = tickets.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, ["ABC-1", "ABC-2", "ABC-3"]);
+        assert_eq!(tickets[0].labels, ["Bug"]);
+        assert_eq!(tickets[0].assignee.as_deref(), Some("Jane Smith"));
+    }
+
+    #[tokio::test]
+    async fn viewer_teams_are_sorted_by_name() {
+        let server = MockServer::start().await;
+        mock_query(&server, "ViewerTeams", json!({ "data": { "viewer": { "teams": {
+            "nodes": [{ "name": "mobile" }, { "name": "Design" }, { "name": "Engineering" }],
+            "pageInfo": { "hasNextPage": false, "endCursor": null }
+        } } } }))
+        .await;
+
+        let teams = client_for(&server).fetch_viewer_teams(false).await.unwrap();
+
+        assert_eq!(teams, ["Design", "Engineering", "mobile"]);
+    }
+
+    #[tokio::test]
+    async fn team_workload_counts_open_tickets_per_member() {
+        let server = MockServer::start().await;
+        let page_info = json!({ "hasNextPage": false, "endCursor": null });
+        mock_query(&server, "TeamMembers", json!({ "data": { "teams": { "nodes": [{
+            "members": { "nodes": [{ "name": "Ana" }, { "name": "Bo" }, { "name": "Cy" }], "pageInfo": page_info }
+        }] } } }))
+        .await;
+        let issue = |assignee: &str, state: &str| json!({ "assignee": { "name": assignee }, "state": { "name": state } });
+        mock_query(&server, "TeamWorkload", json!({ "data": { "issues": {
+            "nodes": [
+                issue("Bo", "Todo"),
+                issue("Bo", "In Progress"),
+                issue("Ana", "Todo"),
+                issue("Ana", "Blocked"),
+                issue("Guest", "Todo"),
+            ],
+            "pageInfo": page_info
+        } } }))
+        .await;
+
+        let workload = client_for(&server)
+            .fetch_team_workload("Engineering", StateFilter {
+                selection: StateSelection::All,
+                exclude: &["Blocked".to_string()],
+            }, false)
+            .await
+            .unwrap();
+
+        let counts: Vec<(&str, usize)> = workload.iter().map(|m| (m.name.as_str(), m.open_tickets)).collect();
+        assert_eq!(counts, [("Bo", 2), ("Ana", 1), ("Guest", 1), ("Cy", 0)]);
+    }
+
+    #[tokio::test]
+    async fn enrich_ticket_fills_in_every_section() {
+        let server = MockServer::start().await;
+        mock_query(&server, "TicketLabels", json!({
+            "data": { "issue": { "labels": { "nodes": [{ "name": "Bug" }, { "name": "Backend" }] } } }
+        })).await;
+        mock_query(&server, "TicketComments", json!({
+            "data": { "issue": { "comments": connection(json!([{
+                "id": "comment-1",
+                "body": "Blocked by the API migration",
+                "createdAt": "2024-01-03T00:00:00Z",
+                "user": { "name": "Sam" }
+            }])) } }
+        })).await;
+        mock_query(&server, "TicketParent", json!({
+            "data": { "issue": { "parent": {
+                "id": "uuid-ABC-0",
+                "identifier": "ABC-0",
+                "title": "Epic",
+                "state": { "name": "In Progress" },
+                "assignee": null
+            } } }
+        })).await;
+        mock_query(&server, "TicketChildren", json!({
+            "data": { "issue": { "children": connection(json!([])) } }
+        })).await;
+        mock_query(&server, "RelatedIssues", json!({
+            "data": { "issue": { "relations": connection(json!([{
+                "id": "relation-1",
+                "type": "related",
+                "relatedIssue": {
+                    "id": "uuid-ABC-9",
+                    "identifier": "ABC-9",
+                    "title": "Related",
+                    "state": { "name": "Done", "type": "completed" },
+                    "assignee": { "name": "Alex" }
+                }
+            }])) } }
+        })).await;
+        mock_query(&server, "InverseRelations", json!({
+            "data": { "issue": { "inverseRelations": connection(json!([{
+                "id": "relation-2",
+                "type": "blocks",
+                "issue": {
+                    "id": "uuid-ABC-7",
+                    "identifier": "ABC-7",
+                    "title": "API migration",
+                    "state": { "name": "In Progress", "type": "started" },
+                    "assignee": null
+                }
+            }])) } }
+        })).await;
+        mock_query(&server, "TicketSubscribers", json!({
+            "data": { "issue": { "subscribers": { "nodes": [{ "name": "Pat" }] } } }
+        })).await;
+        mock_query(&server, "TicketAssigneeHistory", json!({
+            "data": { "issue": { "history": { "nodes": [{
+                "createdAt": "2024-01-02T00:00:00Z",
+                "fromAssignee": { "name": "Alex" },
+                "toAssignee": { "name": "Jane Smith" }
+            }] } } }
+        })).await;
+        mock_query(&server, "TicketDevelopment", json!({
+            "data": { "issue": {
+                "branchName": "jane/abc-1-first",
+                "attachments": { "nodes": [
+                    {
+                        "title": "ABC-1 First",
+                        "url": "https://github.com/acme/app/pull/42",
+                        "metadata": { "status": "merged", "number": 42 }
+                    },
+                    { "title": "Design", "url": "https://www.figma.com/file/abc", "metadata": {} }
+                ] }
+            } }
+        })).await;
+
+        let issue: LinearIssue = serde_json::from_value(issue_json("ABC-1", "First")).unwrap();
+        let ticket = ticket_from_issue(&issue, Some("Jane Smith".to_string()));
+        let enriched = client_for(&server).enrich_ticket(&ticket, false, EnrichOptions::default(), &Reporter::new(true)).await.unwrap();
+
+        assert_eq!(enriched.labels, ["Bug", "Backend"]);
+        assert_eq!(enriched.comments.len(), 1);
+        assert_eq!(enriched.comments[0].user.as_deref(), Some("Sam"));
+        assert_eq!(enriched.parent.as_ref().map(|p| p.id.as_str()), Some("ABC-0"));
+        assert!(enriched.children.is_empty());
+        assert_eq!(enriched.related_tickets[0].id, "ABC-9");
+        assert_eq!(enriched.related_tickets[0].relation_type, "related");
+        let blockers: Vec<&str> = enriched.open_blockers().iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(blockers, ["ABC-7"]);
+        assert_eq!(enriched.subscribers, ["Pat"]);
+        assert_eq!(enriched.previous_assignee.as_deref(), Some("Alex"));
+        assert_eq!(enriched.branch_name.as_deref(), Some("jane/abc-1-first"));
+        assert_eq!(
+            enriched.pull_requests,
+            [PullRequestLink {
+                title: "ABC-1 First".to_string(),
+                url: "https://github.com/acme/app/pull/42".to_string(),
+                status: Some("merged".to_string()),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn enrich_ticket_skips_the_hierarchy_of_a_flat_ticket() {
+        let server = MockServer::start().await;
+        for operation in ["TicketParent", "TicketChildren"] {
+            Mock::given(method("POST"))
+                .and(body_string_contains(operation))
+                .respond_with(ResponseTemplate::new(500))
+                .expect(0)
+                .mount(&server)
+                .await;
+        }
+
+        let mut flat = issue_json("ABC-1", "First");
+        flat["parent"] = json!(null);
+        flat["children"] = json!({ "nodes": [] });
+        let issue: LinearIssue = serde_json::from_value(flat).unwrap();
+        let ticket = ticket_from_issue(&issue, None);
+        let options = EnrichOptions { parent: true, children: true, ..EnrichOptions::minimal() };
+        let enriched = client_for(&server).enrich_ticket(&ticket, false, options, &Reporter::new(true)).await.unwrap();
+
+        assert!(enriched.parent.is_none());
+        assert!(enriched.children.is_empty());
+    }
+
+    #[tokio::test]
+    async fn deep_relations_fetch_the_descriptions_of_related_tickets() {
+        let server = MockServer::start().await;
+        mock_query(&server, "RelatedIssues", json!({
+            "data": { "issue": { "relations": connection(json!([])) } }
+        })).await;
+        mock_query(&server, "InverseRelations", json!({
+            "data": { "issue": { "inverseRelations": connection(json!([{
+                "id": "relation-1",
+                "type": "blocks",
+                "issue": {
+                    "id": "uuid-ABC-7",
+                    "identifier": "ABC-7",
+                    "title": "API migration",
+                    "state": { "name": "In Progress", "type": "started" },
+                    "assignee": null
+                }
+            }])) } }
+        })).await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("TicketDescription"))
+            .and(body_string_contains("ABC-7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "issue": { "description": "Move every endpoint to v2" } }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let issue: LinearIssue = serde_json::from_value(issue_json("ABC-1", "First")).unwrap();
+        let ticket = ticket_from_issue(&issue, None);
+        let client = client_for(&server);
+
+        let shallow = EnrichOptions { relations: true, ..EnrichOptions::minimal() };
+        let enriched = client.enrich_ticket(&ticket, false, shallow, &Reporter::new(true)).await.unwrap();
+        assert_eq!(enriched.related_tickets[0].description, None);
+
+        let deep = EnrichOptions { relation_descriptions: true, ..shallow };
+        let enriched = client.enrich_ticket(&ticket, false, deep, &Reporter::new(true)).await.unwrap();
+        assert_eq!(enriched.related_tickets[0].description.as_deref(), Some("Move every endpoint to v2"));
+    }
+
+    #[tokio::test]
+    async fn enrich_ticket_goes_on_without_labels_it_may_not_read() {
+        let server = MockServer::start().await;
+        mock_query(&server, "TicketLabels", json!({
+            "data": { "issue": null },
+            "errors": [{
+                "message": "Forbidden",
+                "extensions": { "type": "forbidden", "code": "FORBIDDEN", "statusCode": 403 }
+            }]
+        })).await;
+        mock_query(&server, "TicketComments", json!({
+            "data": { "issue": { "comments": connection(json!([{
+                "id": "comment-1",
+                "body": "Looks good",
+                "createdAt": "2024-01-03T00:00:00Z",
+                "user": { "name": "Sam" }
+            }])) } }
+        })).await;
+
+        let issue: LinearIssue = serde_json::from_value(issue_json("ABC-1", "First")).unwrap();
+        let ticket = ticket_from_issue(&issue, None);
+        let options = EnrichOptions { labels: true, comments: true, ..EnrichOptions::minimal() };
+        let enriched = client_for(&server).enrich_ticket(&ticket, false, options, &Reporter::new(true)).await.unwrap();
+
+        assert!(enriched.labels.is_empty());
+        assert_eq!(enriched.comments[0].body, "Looks good");
+    }
+
+    #[tokio::test]
+    async fn enrich_ticket_keeps_base_data_when_issue_is_gone() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": null,
+                "errors": [{ "message": "Entity not found", "extensions": { "type": "invalid input" } }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let issue: LinearIssue = serde_json::from_value(issue_json("ABC-1", "First")).unwrap();
+        let ticket = ticket_from_issue(&issue, None);
+        let enriched = client_for(&server).enrich_ticket(&ticket, false, EnrichOptions::default(), &Reporter::new(true)).await.unwrap();
+
+        assert_eq!(enriched.id, "ABC-1");
+        assert_eq!(enriched.title, "First");
+        assert!(enriched.comments.is_empty());
+        assert!(enriched.parent.is_none());
+    }
+
+    #[tokio::test]
+    async fn subtasks_are_created_under_the_parent_resolved_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("SubtaskParent"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "issue": { "id": "uuid-ABC-1", "team": { "id": "team-1" } } }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("CreateSubtask"))
+            .and(body_string_contains("team-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "issueCreate": { "success": true, "issue": { "identifier": "ABC-2" } } }
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = client_for(&server);
+        let parent = client.fetch_subtask_parent("ABC-1", false).await.unwrap();
+        assert_eq!(parent, SubtaskParent { id: "uuid-ABC-1".to_string(), team_id: "team-1".to_string() });
+        for title in ["Write the migration", "Backfill the data"] {
+            assert_eq!(client.create_subtask(&parent, title, false).await.unwrap(), "ABC-2");
+        }
+    }
+
+    #[tokio::test]
+    async fn subtasks_of_a_deleted_parent_are_not_found() {
+        let server = MockServer::start().await;
+        mock_query(&server, "SubtaskParent", json!({ "data": { "issue": null } })).await;
+
+        let error = client_for(&server).fetch_subtask_parent("ABC-1", false).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<LinearError>(), Some(LinearError::NotFound { id }) if id == "ABC-1"));
+    }
+
+    #[test]
+    fn ticket_urls_are_reduced_to_their_identifier() {
+        for url in [
+            "https://linear.app/acme/issue/ABC-123/fix-the-login-bug",
+            "https://linear.app/acme/issue/ABC-123",
+            "https://linear.app/acme/issue/ABC-123/",
+            "https://linear.app/acme/issue/abc-123/fix-the-login-bug#comment-5e3f2a1b",
+            "https://linear.app/acme/issue/ABC-123?noRedirect=1",
+            "http://linear.app/acme/issue/ABC-123",
+            " linear.app/acme/issue/ABC-123/fix-the-login-bug ",
+        ] {
+            assert_eq!(parse_ticket_reference(url).unwrap(), "ABC-123", "{}", url);
         }
+
+        assert_eq!(parse_ticket_reference(" ABC-123 ").unwrap(), "ABC-123");
+        assert_eq!(parse_ticket_reference("9b2c1e4a-uuid").unwrap(), "9b2c1e4a-uuid");
+        assert!(parse_ticket_reference("https://linear.app/acme/project/roadmap-42").is_err());
+        assert!(parse_ticket_reference("https://linear.app/acme/issue/").is_err());
+        assert!(parse_ticket_reference("https://linear.app/acme/issue/fix-the-login-bug").is_err());
+    }
+
+    #[test]
+    fn write_operations_are_found() {
+        assert_eq!(write_operation("mutation Archive { issueArchive(id: \"1\") { success } }"), Some("mutation"));
+        assert_eq!(write_operation("subscription { issueUpdated { id } }"), Some("subscription"));
+        assert_eq!(
+            write_operation("query Viewer { viewer { id } }\nmutation { issueArchive(id: \"1\") { success } }"),
+            Some("mutation")
+        );
+    }
+
+    #[test]
+    fn read_only_queries_are_allowed() {
+        assert_eq!(write_operation("{ viewer { id } }"), None);
+        assert_eq!(write_operation("query Viewer { viewer { id } }\nquery Teams { teams { nodes { id } } }"), None);
+        // Field and argument names that happen to be keywords are inside braces
+        assert_eq!(write_operation("{ mutation: viewer { subscription: id } }"), None);
+        assert_eq!(write_operation("# mutation { issueArchive }\n{ viewer { id } }"), None);
+        assert_eq!(
+            write_operation("{ issues(filter: { title: { contains: \"} mutation #\" } }) { nodes { id } } }"),
+            None
+        );
+        assert_eq!(write_operation("{ issues(filter: { title: { eq: \"\"\"a } \\\"\"\" b\"\"\" } }) { nodes { id } } }"), None);
+    }
+
+    #[test]
+    fn hashes_in_strings_are_not_comments() {
+        // Cutting the line at the # would hide the mutation that follows the string
+        assert_eq!(write_operation("query { issue(id: \"#1\") { id } } mutation { issueArchive }"), Some("mutation"));
     }
 }
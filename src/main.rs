@@ -1,17 +1,124 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{ArgGroup, Parser, ValueEnum};
 use colored::Colorize;
 use dotenv::dotenv;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::fs;
 use crate::models::Ticket;
 
 mod config;
+mod duplicates;
+mod export;
+mod github;
 mod linear;
 mod anthropic;
 mod models;
+mod notify;
+mod rate_limit;
+mod recording;
+mod repo;
+mod report;
 mod ui;
 
+/// Exit code used when some tickets in a batch failed but the run otherwise completed
+const PARTIAL_FAILURE_EXIT_CODE: i32 = 2;
+
+/// Saved tickets last updated longer ago than this are offered a re-fetch before planning
+const STALE_TICKET_DAYS: i64 = 30;
+
+/// Separates the metadata header of a saved plan from the plan itself
+const PLAN_HEADER_SEPARATOR: &str = "\n\n---\n\n";
+
+/// Attempts at writing an output file before giving up
+const WRITE_ATTEMPTS: u32 = 3;
+
+/// Pause between attempts at writing an output file
+const WRITE_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Comment threads shorter than this are sent as they are with --compact-comments
+const COMPACT_COMMENTS_MIN: usize = 5;
+
+/// Tickets enriched at the same time; each enrichment makes several Linear requests
+const ENRICH_CONCURRENCY: usize = 4;
+
+/// Directory inside the tickets directory where --compact-comments caches its summaries
+const COMMENT_SUMMARY_CACHE_DIR: &str = ".comment-summaries";
+
+/// Output format for the fetched ticket list
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Interactive listing with one Markdown file per ticket
+    Markdown,
+    /// A single tickets.csv of the fetched list, without selection or enrichment
+    Csv,
+    /// A GitHub-flavored markdown table of the fetched list printed to stdout
+    MdTable,
+    /// Like markdown, with each plan also rendered to a standalone .html page
+    Html,
+    /// Like markdown, with each plan also rendered to an Emacs Org-mode .org file
+    Org,
+}
+
+/// Format of the saved ticket files read with --ticket and --validate
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum InputFormat {
+    /// Markdown as saved by this tool
+    Markdown,
+    /// A serialized ticket, as described by --json-schema
+    Json,
+}
+
+impl InputFormat {
+    /// The format of a ticket file: the one given with --input-format, otherwise JSON
+    /// for .json files and markdown for anything else
+    fn of(path: &Path, explicit: Option<InputFormat>) -> Self {
+        explicit.unwrap_or_else(|| match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => InputFormat::Json,
+            _ => InputFormat::Markdown,
+        })
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            InputFormat::Markdown => "md",
+            InputFormat::Json => "json",
+        }
+    }
+}
+
+/// Ticket field used to sort plans into subdirectories of the output directory
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum GroupBy {
+    /// The ticket's first label, or _unlabeled
+    Label,
+    /// The ticket's workflow state
+    State,
+    /// The ticket's assignee, or _unassigned
+    Assignee,
+    /// The ticket's Linear project, or _no_project
+    Project,
+}
+
+/// Order of the tickets in the selection prompt (--sort-selection)
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SelectionSort {
+    /// Urgent first, tickets without a priority last
+    Priority,
+    /// Alphabetically, ignoring case
+    Title,
+    /// By team key, then ticket number (ABC-2 before ABC-10)
+    Id,
+    /// Largest estimate first, unestimated tickets last
+    Estimate,
+    /// Most recently updated first
+    Updated,
+}
+
+/// Where the tickets of a run come from; at most one of these can be given
+const TICKET_SOURCES: [&str; 8] =
+    ["user", "me", "view", "unassigned", "team_members", "ticket", "ticket_id", "retry_failed"];
+
 /// Linear Agent - Implementation Plan Generator
 /// 
 /// A CLI tool that fetches tickets from Linear, enriches them with detailed information,
@@ -21,7 +128,9 @@ mod ui;
     author = "Bold Inc.", 
     version, 
     about,
-    after_help = "Example usage:\n  linear-agent --setup                       # Run initial setup\n  linear-agent --user \"John Doe\"              # Get John's tickets (no plans)\n  linear-agent --user \"John Doe\" --plan      # Generate plans for John's tickets\n  linear-agent -u \"John Doe\" -s \"Open\"        # Only analyze open tickets\n  linear-agent -e ~/.linear-agent/.env       # Use custom .env file\n  linear-agent --ticket path/to/ticket.md --plan # Generate plan from saved ticket file\n  linear-agent --ticket-id ABC-123            # Fetch and save a specific ticket by ID"
+    after_help = "Example usage:\n  linear-agent --setup                       # Run initial setup\n  linear-agent --user \"John Doe\"              # Get John's tickets (no plans)\n  linear-agent --me --plan                    # Generate plans for your own tickets\n  linear-agent --view <VIEW_ID>              # Use the issues of a saved Linear view\n  linear-agent --unassigned --plan            # Plan the team's unassigned backlog\n  linear-agent --me --all --quiet             # Save all your tickets without prompting\n  linear-agent --user \"John Doe\" --plan      # Generate plans for John's tickets\n  linear-agent -u \"John Doe\" -s \"Open\"        # Only analyze open tickets\n  linear-agent --me --plan --comments-since 2w # Only send comments from the last two weeks\n  linear-agent -e ~/.linear-agent/.env       # Use custom .env file\n  linear-agent --ticket path/to/ticket.md --plan # Generate plan from saved ticket file\n  linear-agent --ticket tickets/ --plan      # Generate plans for all saved tickets\n  linear-agent --ticket-id ABC-123            # Fetch and save a specific ticket by ID\n  linear-agent --retry-failed --plan          # Re-plan the tickets that failed last time\n  linear-agent --validate tickets/            # Check saved ticket files can be parsed\n  linear-agent --raw-query milestones.graphql # Run a read-only GraphQL query and print the JSON\n\nOnly one ticket source can be given: --user, --me, --view, --unassigned, --team-members,\n--ticket, --ticket-id or --retry-failed.",
+    group(ArgGroup::new("source").args(TICKET_SOURCES)),
+    group(ArgGroup::new("generate").args(["plan", "describe"]))
 )]
 struct Args {
     /// Path to .env file containing Linear and Anthropic API keys
@@ -33,10 +142,50 @@ struct Args {
     env: Option<PathBuf>,
 
     /// Linear user to analyze tickets for (e.g. "Jane Smith")
+    /// 
+    /// Takes a comma-separated list to fetch the tickets of several people in one run
+    /// (e.g. "Jane Smith, Bob Lee"); tickets are then listed by assignee.
     #[clap(short, long)]
     user: Option<String>,
 
-    /// Linear team name (defaults to "Engineering" if not specified)
+    /// Fetch tickets assigned to you (the owner of the Linear API key)
+    /// 
+    /// Resolves the authenticated user directly instead of looking up a name.
+    #[clap(long)]
+    me: bool,
+
+    /// Fetch the issues of a saved Linear view by its ID
+    /// 
+    /// Uses the filters maintained in the Linear UI instead of the user, team
+    /// and state options.
+    #[clap(long, value_name = "VIEW_ID")]
+    view: Option<String>,
+
+    /// Fetch unassigned tickets in the team and states, for backlog grooming
+    #[clap(long)]
+    unassigned: bool,
+
+    /// List the team's members with how many tickets in the selected states each has, and exit
+    /// 
+    /// Busiest first, for capacity planning. Nothing is saved or planned.
+    #[clap(long, conflicts_with = "plan")]
+    team_members: bool,
+
+    /// Process every fetched ticket without the interactive selection prompt
+    /// 
+    /// Useful for scripted runs, e.g. archiving all tickets with `--all --quiet`.
+    #[clap(long, conflicts_with_all = ["ticket", "ticket_id"])]
+    all: bool,
+
+    /// Re-fetch and process only the tickets that failed in earlier runs, without prompting
+    /// 
+    /// Failed tickets are listed in failures.json next to the run report and cleared from
+    /// it once processed. Give the same --plan/--describe and --output options as the
+    /// failed run so that its list is found.
+    #[clap(long)]
+    retry_failed: bool,
+
+    /// Linear team name (defaults to your only team, or asks which one if you have several)
     #[clap(short, long)]
     team: Option<String>,
 
@@ -46,6 +195,22 @@ struct Args {
     #[clap(short, long)]
     states: Option<String>,
 
+    /// Comma-separated list of ticket states to leave out
+    /// 
+    /// Example: "Canceled,Done". Without --states (or LINEAR_AGENT_STATES), every other
+    /// state is fetched. Combined with --states or --state-type, a state that is both
+    /// included and excluded is left out.
+    #[clap(long, value_name = "STATES")]
+    exclude_states: Option<String>,
+
+    /// Comma-separated list of workflow state types to analyze instead of state names
+    /// 
+    /// One or more of: triage, backlog, unstarted, started, completed, canceled.
+    /// Unlike state names, types are the same for every team. Takes precedence
+    /// over LINEAR_AGENT_STATES.
+    #[clap(long, value_name = "TYPES", conflicts_with = "states")]
+    state_type: Option<String>,
+
     /// Anthropic model to use for implementation plan generation
     /// 
     /// Supported models: "claude-3-7-sonnet-20250219", "claude-3-5-sonnet-20240620",
@@ -53,6 +218,146 @@ struct Args {
     #[clap(short, long)]
     model: Option<String>,
 
+    /// Anthropic model per ticket priority, e.g. "urgent=claude-3-opus-20240229,default=claude-3-haiku-20240307"
+    /// 
+    /// Levels are urgent, high, medium, low and none, plus "default" for any level not
+    /// listed. Tickets without a matching entry use --model.
+    #[clap(long, value_name = "LEVEL=MODEL,...")]
+    model_map: Option<String>,
+
+    /// Maximum prompt size in tokens (estimated) sent to Anthropic
+    /// 
+    /// When a ticket's prompt exceeds this budget, the oldest comments are dropped first,
+    /// then related and child tickets. Title and description are always kept. [default: 150000]
+    #[clap(long, value_name = "TOKENS")]
+    context_budget: Option<usize>,
+
+    /// Maximum description length in characters sent to Anthropic
+    /// 
+    /// Longer descriptions keep their beginning and end, with a "[description truncated]"
+    /// marker in between. Saved ticket files always keep the full text. [default: no limit]
+    #[clap(long, value_name = "CHARS")]
+    max_description_length: Option<usize>,
+
+    /// Maximum length in characters of each comment in prompts and saved ticket files
+    /// 
+    /// Longer comments (pasted logs) keep their beginning, followed by a
+    /// "[comment truncated]" marker. Every comment is still listed. [default: no limit]
+    #[clap(long, value_name = "CHARS")]
+    limit_comments_chars: Option<usize>,
+
+    /// Comma-separated sections to ask for in each plan, in order
+    /// 
+    /// Known sections: overview, requirements, approach, challenges, testing, effort,
+    /// risks, rollback, security. Entries of several words are requested as written,
+    /// e.g. "overview,approach,Data migration steps". Can also be set with
+    /// ANTHROPIC_PLAN_SECTIONS. [default: overview,requirements,approach,challenges,testing,effort]
+    #[clap(long, value_name = "SECTIONS")]
+    plan_sections: Option<String>,
+
+    /// File with shared background, such as a project brief, sent before every ticket
+    /// 
+    /// Useful when planning several tickets of the same initiative: the brief is sent
+    /// once per prompt, set apart from the ticket, instead of being restated in each
+    /// ticket. Can also be set with ANTHROPIC_CONTEXT_FILE.
+    #[clap(long, value_name = "FILE", conflicts_with = "context")]
+    context_file: Option<PathBuf>,
+
+    /// Shared background sent before every ticket, given inline instead of in a file
+    #[clap(long, value_name = "TEXT")]
+    context: Option<String>,
+
+    /// Local repository to summarize in each prompt, so plans refer to its real files
+    /// 
+    /// Adds the top-level structure and the files whose names or contents mention the
+    /// ticket's keywords, never file contents. Uses the files git tracks when the path
+    /// is in a git repository. Can also be set with LINEAR_AGENT_REPO_PATH.
+    #[clap(long, value_name = "DIR")]
+    repo_path: Option<PathBuf>,
+
+    /// Maximum size in characters of the repository summary [default: 6000]
+    #[clap(long, value_name = "CHARS", requires = "repo_path")]
+    repo_summary_chars: Option<usize>,
+
+    /// Only send comments made since this date or within this duration
+    /// 
+    /// Takes a date (2024-05-01), an RFC 3339 timestamp or a duration such as 36h, 14d
+    /// or 2w. Older comments are left out of plan and --describe prompts (and of
+    /// --compact-comments summaries); saved ticket files keep every comment. There is no
+    /// separate comment count limit: the filter applies first, then --plan-from-comments-only
+    /// takes the latest N of the remaining comments, and a prompt over the context budget
+    /// still drops its oldest remaining comments.
+    #[clap(long, value_name = "DATE|DURATION", requires = "generate")]
+    comments_since: Option<String>,
+
+    /// Plan from the latest N comments and the title, with the description as background
+    /// 
+    /// For tickets whose discussion has moved on from the original description. The
+    /// comments lead the prompt and the description is shortened and marked as possibly
+    /// outdated. Applies after --comments-since, so the latest N of the remaining comments
+    /// are used; tickets left without comments are planned as usual. [default: 10]
+    #[clap(long, value_name = "N", num_args = 0..=1, default_missing_value = "10", requires = "plan", conflicts_with = "compact_comments")]
+    plan_from_comments_only: Option<usize>,
+
+    /// Ask for a concise plan for tickets estimated below this many points
+    /// 
+    /// Concise plans use a lower token limit. Unestimated tickets get a standard plan.
+    /// [default: 2]
+    #[clap(long, value_name = "POINTS")]
+    concise_plan_below: Option<f64>,
+
+    /// Ask for a detailed, milestone-based plan for tickets estimated at this many points or more
+    /// 
+    /// Detailed plans use a higher token limit. [default: 8]
+    #[clap(long, value_name = "POINTS")]
+    detailed_plan_from: Option<f64>,
+
+    /// Show a preview of each ticket's description in the listing
+    /// 
+    /// Optionally takes the number of characters to show (defaults to 200).
+    #[clap(long, value_name = "CHARS", num_args = 0..=1, default_missing_value = "200")]
+    show_description: Option<usize>,
+
+    /// Don't fetch the comments of tickets, leaving them out of files and prompts
+    #[clap(long)]
+    skip_comments: bool,
+
+    /// Don't fetch the child tickets of tickets
+    #[clap(long)]
+    skip_children: bool,
+
+    /// Don't fetch the related tickets (blockers, duplicates, ...) of tickets
+    #[clap(long)]
+    skip_relations: bool,
+
+    /// Also fetch the descriptions of related tickets and include them, shortened, in prompts
+    /// 
+    /// Tells the model what a blocker or duplicate is about rather than only its title.
+    /// Costs one extra Linear request per related ticket. Relations of related tickets
+    /// are never fetched.
+    #[clap(long, conflicts_with = "skip_relations")]
+    deep_relations: bool,
+
+    /// List tickets with open blockers first, marked with a red badge
+    /// 
+    /// Blockers are tickets with a "blocks" relation to the ticket that aren't completed
+    /// or canceled. Checking them takes one extra Linear request per listed ticket.
+    #[clap(long)]
+    sort_by_blocked: bool,
+
+    /// Order of the tickets in the selection prompt
+    /// 
+    /// Only the prompt is sorted, to find a ticket in a long list; the listing above it
+    /// and the order tickets are processed in stay as fetched. Ties keep that order too.
+    #[clap(long, value_enum, value_name = "KEY")]
+    sort_selection: Option<SelectionSort>,
+
+    /// Override the colors used for each priority in the ticket listing
+    /// 
+    /// Example: "urgent=magenta,high=red,medium=yellow,low=green,none=white"
+    #[clap(long, value_name = "LEVEL=COLOR,...")]
+    priority_colors: Option<String>,
+
     /// Run setup wizard to configure credentials and preferences
     /// 
     /// This will guide you through setting up Linear and Anthropic API keys,
@@ -66,26 +371,123 @@ struct Args {
     #[clap(short, long, default_value = "implementation_plans")]
     output: PathBuf,
 
+    /// Write plans into subdirectories of the output directory named after a ticket field
+    /// 
+    /// "label" uses the ticket's primary (first) label, with unlabeled tickets in
+    /// _unlabeled; "state" uses the workflow state; "assignee" the assignee, with
+    /// unassigned tickets in _unassigned; and "project" the Linear project, with tickets
+    /// outside any project in _no_project.
+    #[clap(long, value_enum, value_name = "FIELD")]
+    group_output_by: Option<GroupBy>,
+
+    /// Output format for the fetched tickets
+    /// 
+    /// "csv" writes the fetched ticket list to tickets.csv in the tickets directory
+    /// and exits without prompting for a selection. "md-table" prints the list as a
+    /// markdown table for pasting into PR descriptions or wikis, and exits. "html" works
+    /// like "markdown" and also saves each plan as an .html page next to its .md file, and
+    /// "org" likewise saves each plan as an Org-mode .org file.
+    #[clap(long, value_enum, default_value = "markdown")]
+    output_format: OutputFormat,
+
     /// Directory for saving ticket information
     /// 
     /// Ticket information will be saved as Markdown files in this directory.
     #[clap(long, default_value = "tickets")]
     tickets_dir: PathBuf,
 
-    /// Path to a previously saved ticket markdown file to process
+    /// Write the metadata of saved ticket files as a markdown table
+    /// 
+    /// Renders better on GitHub and in Obsidian than the default bold-prefixed lines.
+    /// Both forms are read back by --ticket and --validate.
+    #[clap(long)]
+    gfm_tables: bool,
+
+    /// Don't save ticket files to the tickets directory
     /// 
-    /// Use this to generate an implementation plan from a ticket file
-    /// that was previously saved using this tool.
+    /// Tickets are still fetched and enriched for plans and descriptions; only those
+    /// are written.
     #[clap(long)]
-    ticket: Option<PathBuf>,
+    no_save_tickets: bool,
+
+    /// File name of the JSON report written at the end of each run
+    /// 
+    /// Records the selection, processed and failed tickets, models, token usage and
+    /// estimated cost. Relative paths are placed in the output directory, or in the
+    /// tickets directory when no plans are generated.
+    #[clap(long, value_name = "FILE", default_value = "run-report.json")]
+    run_report: PathBuf,
+
+    /// Path to previously saved ticket markdown files (or directories of them) to process
+    /// 
+    /// Use this to generate implementation plans from ticket files
+    /// that were previously saved using this tool. Accepts several paths,
+    /// so shell globs like tickets/*.md work too. Tickets serialized as JSON
+    /// (.json files) are read as well; see --input-format.
+    #[clap(long, num_args = 1..)]
+    ticket: Vec<PathBuf>,
+
+    /// Format of the files read with --ticket and --validate
+    /// 
+    /// Without this flag, .json files are parsed as JSON tickets and other files as
+    /// markdown, and directories are searched for both. With it, every file is parsed
+    /// in the given format and directories are searched for that extension only.
+    #[clap(long, value_enum, value_name = "FORMAT")]
+    input_format: Option<InputFormat>,
 
-    /// Linear ticket ID to fetch and save
+    /// Linear ticket ID or URL to fetch and save
     /// 
     /// Fetches a specific ticket from Linear by ID and saves it as Markdown.
     /// The file will be saved in the tickets directory with the format: ticketId-title.md
-    #[clap(long)]
+    /// Takes the ticket's URL as copied from the browser too (also as --url), e.g.
+    /// https://linear.app/acme/issue/ABC-123/fix-the-login-bug.
+    #[clap(long, visible_alias = "url", value_name = "ID|URL", value_parser = linear::parse_ticket_reference)]
     ticket_id: Option<String>,
 
+    /// Validate previously saved ticket files without calling any API
+    /// 
+    /// Parses every .md file in the given directory (or a single file) and reports
+    /// which ones can be loaded with --ticket, along with any warnings.
+    #[clap(long, value_name = "DIR")]
+    validate: Option<PathBuf>,
+
+    /// Run a read-only GraphQL query from a file against Linear and print the JSON response
+    /// 
+    /// A power-user escape hatch for data the tool doesn't model (e.g. project milestones).
+    /// Mutations are rejected. Nothing else is fetched, saved or planned.
+    #[clap(long, value_name = "FILE")]
+    raw_query: Option<PathBuf>,
+
+    /// JSON file with variables for --raw-query
+    #[clap(long, value_name = "FILE", requires = "raw_query")]
+    raw_vars: Option<PathBuf>,
+
+    /// Print the JSON Schema of a ticket and exit
+    /// 
+    /// Describes the ticket records in JSON output, so downstream tools can validate them.
+    #[clap(long)]
+    json_schema: bool,
+
+    /// Print the effective configuration and where each setting came from, and exit
+    /// 
+    /// Shows every setting after defaults, environment variables (and .env), config.toml
+    /// and command line options were applied, with API keys and other secrets masked.
+    #[clap(long)]
+    dump_config: bool,
+
+    /// Check whether a pull request implements a ticket's saved plan
+    /// 
+    /// Needs --github-pr and a single saved ticket file given with --ticket. The pull
+    /// request's description and changed files are compared with the ticket's plan in
+    /// the output directory, and a short gap report is saved next to it as .verify.md.
+    /// Set GITHUB_TOKEN to read pull requests of private repositories.
+    #[clap(long, requires_all = ["github_pr", "ticket"], conflicts_with = "plan")]
+    verify: bool,
+
+    /// URL of the pull request to compare with the plan (e.g. https://github.com/org/repo/pull/42)
+    #[clap(long, value_name = "URL", requires = "verify")]
+    github_pr: Option<String>,
+
     // We've removed the non-interactive mode to avoid accidental high costs
     
     /// Generate implementation plans for tickets
@@ -95,6 +497,228 @@ struct Args {
     #[clap(long)]
     plan: bool,
     
+    /// Show a diff against the existing plan before overwriting it
+    /// 
+    /// When a plan file for the ticket already exists, the new plan is compared against it
+    /// and you are asked whether to overwrite the saved version.
+    #[clap(long)]
+    diff: bool,
+    
+    /// Generate a brief approach and risk note per ticket instead of full plans
+    /// 
+    /// Uses a terser prompt and a low token limit. All summaries are written to a
+    /// single summaries.md in the output directory.
+    #[clap(long, requires = "plan", conflicts_with = "append_to_existing_plan")]
+    summary: bool,
+    
+    /// Explain each ticket in plain English instead of generating a plan
+    /// 
+    /// Claude describes what the ticket asks for, why, and who is affected, without an
+    /// implementation plan, which is cheaper and faster. Each explanation is written to the
+    /// output directory as a .summary.md file, e.g. ABC-123-Title.summary.md.
+    #[clap(long, conflicts_with = "plan")]
+    describe: bool,
+    
+    /// Run a second self-review pass over each generated plan
+    /// 
+    /// The draft is sent back with a quality rubric (test strategy, clear steps, rollback
+    /// plan, ...) and the improved version is saved. This doubles the Anthropic cost per
+    /// ticket. The rubric can be changed with ANTHROPIC_REVIEW_RUBRIC.
+    #[clap(long, requires = "plan", conflicts_with = "summary")]
+    review: bool,
+    
+    /// Ask for the plan's steps as a checkbox list and also save them to a .tasks.md file
+    /// 
+    /// The checklist is written next to the plan, e.g. ABC-123-Title.tasks.md.
+    #[clap(long, requires = "plan", conflicts_with = "summary")]
+    checklist_output: bool,
+    
+    /// Create each step of the plan's checklist as a Linear subtask of the ticket
+    /// 
+    /// The subtasks are listed and only created after confirmation. This writes to Linear.
+    #[clap(long, requires = "checklist_output")]
+    create_subtasks: bool,
+    
+    /// Condense long comment threads with a small model before planning
+    /// 
+    /// Tickets with at least 5 comments get their thread summarized into a few bullet points
+    /// by a cheap model, and the summary is sent instead of the comments. Summaries are
+    /// cached in the tickets directory until a new comment is added.
+    #[clap(long, requires = "plan")]
+    compact_comments: bool,
+    
+    /// Show each generated plan and offer to accept, regenerate or skip it
+    /// 
+    /// A plan can be regenerated as is, or with an extra instruction typed in (e.g. "focus
+    /// on the migration"), which is kept for every later attempt on that ticket. Skipped
+    /// tickets get no plan file. Every regeneration is a new request to Anthropic.
+    #[clap(long, requires = "plan", conflicts_with_all = ["summary", "append_to_existing_plan"])]
+    interactive_refine: bool,
+    
+    /// Ask for confirmation before generating plans estimated to cost more than this many dollars
+    /// 
+    /// The estimate is an upper bound from the prompt sizes and output token limits,
+    /// doubled with --review. Runs below the threshold start without a prompt.
+    #[clap(long, value_name = "DOLLARS", requires = "plan")]
+    confirm_above: Option<f64>,
+    
+    /// Ask for confirmation before planning more than this many tickets in one run
+    /// 
+    /// A safety net against selecting far more tickets than intended, whatever the model
+    /// costs. 0 turns the check off. [default: 20]
+    #[clap(long, value_name = "COUNT", requires = "plan")]
+    max_tickets_per_run: Option<usize>,
+    
+    /// Look for likely duplicates among the selected tickets before planning
+    /// 
+    /// Compares the words of titles and descriptions, and for each pair that looks
+    /// alike asks whether to plan both or only one of them.
+    #[clap(long, requires = "plan")]
+    detect_duplicates: bool,
+    
+    /// Similarity from 0 to 1 from which --detect-duplicates flags two tickets [default: 0.6]
+    #[clap(long, value_name = "RATIO", requires = "detect_duplicates")]
+    duplicate_threshold: Option<f64>,
+    
+    /// Never ask for confirmation of the estimated cost or ticket count, even above
+    /// --confirm-above or --max-tickets-per-run. Ticket files with an outdated snapshot
+    /// are re-fetched from Linear without asking.
+    #[clap(long)]
+    yes: bool,
+    
+    /// Revise the existing plan instead of generating a new one
+    /// 
+    /// When a plan file for the ticket already exists, its contents are sent along with
+    /// the ticket and Claude is asked to revise and extend it.
+    #[clap(long, requires = "plan")]
+    append_to_existing_plan: bool,
+    
+    /// Save the prompt that produced each plan next to it, as a .prompt.md file
+    /// 
+    /// For example ABC-123-Title.prompt.md next to ABC-123-Title.md, with the system
+    /// instructions and the ticket message exactly as sent. Plans are still generated.
+    #[clap(long, requires = "plan")]
+    save_prompts: bool,
+    
+    /// Keep the previous plan as a timestamped copy when overwriting it
+    /// 
+    /// For example ABC-123-Title.20240101-120000.md next to ABC-123-Title.md.
+    #[clap(long)]
+    keep_plan_versions: bool,
+    
+    /// Write every plan of the run to this one markdown file instead of a file per ticket
+    /// 
+    /// Plans are added in the order they are generated, each with its ticket header and
+    /// separated by ---. The file is started afresh for each run unless --append is given.
+    /// Relative paths are placed in the output directory. Only markdown is written, so
+    /// --output-format can't be given with it.
+    #[clap(
+        long,
+        value_name = "FILE",
+        requires = "plan",
+        conflicts_with_all = [
+            "summary", "diff", "keep_plan_versions", "append_to_existing_plan", "group_output_by", "output_format"
+        ]
+    )]
+    single_file: Option<PathBuf>,
+    
+    /// Add this run's plans to the end of the existing --single-file instead of replacing it
+    #[clap(long, requires = "single_file")]
+    append: bool,
+    
+    /// Slack incoming webhook URL to notify when the run finishes
+    /// 
+    /// Posts a summary of processed tickets and failures. Can also be set
+    /// with the SLACK_WEBHOOK_URL environment variable.
+    #[clap(long, value_name = "WEBHOOK_URL")]
+    notify_slack: Option<String>,
+    
+    /// Number of items requested per page from Linear (1 to 250)
+    /// 
+    /// Applies to issue lists, comments, child tickets and relations, which are fetched
+    /// page by page. Smaller pages help on flaky networks; larger pages mean fewer
+    /// requests. [default: 50]
+    #[clap(long, value_name = "N")]
+    page_size: Option<usize>,
+    
+    /// Proxy for all requests to Linear, Anthropic and Slack, e.g. http://proxy.corp:3128
+    /// 
+    /// Hosts listed in NO_PROXY are reached directly. Without this option, the HTTP_PROXY
+    /// and HTTPS_PROXY environment variables are used. Credentials can be put in the URL
+    /// or in LINEAR_AGENT_PROXY_AUTH as user:password.
+    #[clap(long, value_name = "URL")]
+    proxy: Option<String>,
+    
+    /// Linear GraphQL endpoint to use instead of https://api.linear.app/graphql
+    /// 
+    /// Useful for corporate API gateways or local mock servers. Can also be set
+    /// with the LINEAR_API_URL environment variable.
+    #[clap(long, value_name = "URL")]
+    linear_api_url: Option<String>,
+    
+    /// Anthropic API base URL to use instead of https://api.anthropic.com
+    /// 
+    /// Requests are sent to <URL>/v1/messages. Can also be set with the
+    /// ANTHROPIC_API_BASE environment variable.
+    #[clap(long, value_name = "URL")]
+    anthropic_api_base: Option<String>,
+    
+    /// How many times to retry Anthropic requests that fail with 429, 5xx or 529 (overloaded)
+    /// 
+    /// Retries use exponential backoff with jitter and honor the retry-after header.
+    /// Other client errors fail immediately. [default: 3]
+    #[clap(long, value_name = "N")]
+    anthropic_max_retries: Option<u32>,
+    
+    /// Send at most this many Anthropic requests per minute, for your account's tier
+    /// 
+    /// Requests that would go over the limit wait until they fit instead of failing.
+    /// Can also be set with ANTHROPIC_RPM. [default: no limit]
+    #[clap(long, value_name = "N")]
+    anthropic_rpm: Option<u32>,
+    
+    /// Use at most this many Anthropic tokens per minute, for your account's tier
+    /// 
+    /// Each request counts its estimated prompt and its maximum reply length until the
+    /// actual usage is known, and waits until it fits under the limit instead of failing.
+    /// Can also be set with ANTHROPIC_TPM. [default: no limit]
+    #[clap(long, value_name = "N")]
+    anthropic_tpm: Option<u32>,
+    
+    /// Anthropic API version to request, sent as the anthropic-version header
+    /// 
+    /// Newer versions may change the shape of responses, which this tool may not parse.
+    /// Can also be set with ANTHROPIC_VERSION. [default: 2023-06-01]
+    #[clap(long, value_name = "VERSION")]
+    anthropic_version: Option<String>,
+    
+    /// Anthropic beta feature to enable through the anthropic-beta header (repeatable)
+    /// 
+    /// Values are sent verbatim, so new beta features work without an update.
+    /// Can also be set with ANTHROPIC_BETA (comma-separated).
+    #[clap(long, value_name = "FEATURE")]
+    anthropic_beta: Vec<String>,
+
+    /// Save each Linear and Anthropic request and its raw response to this directory
+    /// 
+    /// One timestamped JSON file per request, with API keys redacted. Attach the
+    /// directory to bug reports, or play it back with --replay.
+    #[clap(long, value_name = "DIR")]
+    record: Option<PathBuf>,
+
+    /// Answer Linear and Anthropic requests from a --record directory instead of the network
+    /// 
+    /// Each request gets the first unused recorded response to an identical request;
+    /// a request that wasn't recorded fails.
+    #[clap(long, value_name = "DIR", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+    
+    /// Suppress decorative output
+    /// 
+    /// Only errors and a final one-line summary are printed, both to stderr.
+    #[clap(short, long)]
+    quiet: bool,
+    
     /// Enable verbose output with debug information
     /// 
     /// Shows additional details like API responses and debug messages.
@@ -111,7 +735,9 @@ struct Args {
 #[tokio::main]
 async fn main() -> Result<()> {
     // Parse command line arguments
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let reporter = ui::Reporter::new(args.quiet);
+    let started_at = chrono::Utc::now();
     
     // Check for updates if requested
     if args.check_update {
@@ -119,123 +745,264 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    // Validate saved ticket files if requested (no API access needed)
+    if let Some(path) = &args.validate {
+        let all_valid = validate_ticket_files(path, args.input_format, &reporter)?;
+        if !all_valid {
+            std::process::exit(PARTIAL_FAILURE_EXIT_CODE);
+        }
+        return Ok(());
+    }
+
+    // Print the ticket schema for consumers of JSON output (no API access needed)
+    if args.json_schema {
+        let schema = schemars::schema_for!(Ticket);
+        println!("{}", serde_json::to_string_pretty(&schema)?);
+        return Ok(());
+    }
+
+    // Show the effective settings and their sources (no API access needed)
+    if args.dump_config {
+        return dump_config(&args, &reporter).await;
+    }
+
+    // Run a raw GraphQL query and print the JSON response, bypassing the plan pipeline
+    if let Some(query_path) = &args.raw_query {
+        return run_raw_query(query_path, args.raw_vars.as_deref(), &args).await;
+    }
+
+    // Compare a pull request with a saved plan, bypassing the plan pipeline
+    if args.verify {
+        return verify_pull_request(&args, &reporter).await;
+    }
+
     // Print welcome message
-    println!("{}", "🔍 Linear Agent: Interactive Implementation Plan Generator".bright_green());
+    reporter.info("🔍 Linear Agent: Interactive Implementation Plan Generator".bright_green());
+    
+    for flag in ignored_flags(&args) {
+        reporter.info(format!("⚠️ {} only applies when generating plans and is ignored without --plan", flag).yellow());
+    }
 
-    // If using --ticket option, we'll process a local ticket file
-    if let Some(ticket_path) = &args.ticket {
-        if !args.plan {
-            println!("{}", "Note: Using --ticket without --plan will only display the ticket details".yellow());
+    // If using --ticket option, we'll process local ticket files
+    if !args.ticket.is_empty() {
+        if !args.plan && !args.describe {
+            reporter.info("Note: Using --ticket without --plan will only display the ticket details".yellow());
         }
         
-        if !ticket_path.exists() {
-            println!("{}", format!("❌ Error: Ticket file not found: {}", ticket_path.display()).red());
+        if let Some(missing) = args.ticket.iter().find(|path| !path.exists()) {
+            reporter.error(format!("❌ Error: Ticket file not found: {}", missing.display()).red());
             return Ok(());
         }
         
-        // Load the ticket from the markdown file
-        println!("{}", format!("Loading ticket from {}", ticket_path.display()).blue());
-        let ticket_content = fs::read_to_string(ticket_path)
-            .context(format!("Failed to read ticket file: {}", ticket_path.display()))?;
-        
-        let ticket = models::Ticket::from_markdown(&ticket_content)
-            .context("Failed to parse ticket from markdown file")?;
+        let mut ticket_files = Vec::new();
+        for path in &args.ticket {
+            ticket_files.extend(collect_ticket_files(path, args.input_format)?);
+        }
         
-        println!("{}", "Ticket loaded successfully:".green());
-        println!("Title: {}", ticket.title);
-        println!("ID: {}", ticket.id);
-        println!("State: {}", ticket.state);
+        if ticket_files.is_empty() {
+            reporter.info("⚠️ No ticket files found".yellow());
+            return Ok(());
+        }
         
-        // If --plan flag is provided, generate an implementation plan
-        if args.plan {
+        // With --plan or --describe, we need a working Anthropic client
+        let planner = if args.plan || args.describe {
             // Load environment variables for Anthropic API
-            if let Some(env_path) = &args.env {
-                dotenv::from_path(env_path).context("Failed to load .env file")?;
-            } else {
-                // Try to load from default locations
-                let env_locations = config::AppConfig::get_env_locations();
-                let mut loaded = false;
-                
-                for location in env_locations {
-                    if location.exists() {
-                        dotenv::from_path(&location).context(format!("Failed to load .env from {}", location.display()))?;
-                        println!("Loaded configuration from {}", location.display());
-                        loaded = true;
-                        break;
-                    }
-                }
-                
-                if !loaded {
-                    // If no .env file found, try loading from default location just in case
-                    dotenv().ok();
-                }
-            }
+            load_env(&args, &reporter)?;
             
             // Load configuration
             let app_config = config::AppConfig::load(None, &args).await?;
             
             // Test Anthropic API connection - required for plan generation
-            println!("\n{}", "Testing Anthropic API connection...".blue());
             let anthropic_client = match anthropic::AnthropicClient::from_config(&app_config) {
                 Some(client) => client,
                 None => {
-                    println!("\n{}", "❌ Error: Anthropic API key not found. Please provide ANTHROPIC_API_KEY in your .env file to generate plans.".red());
+                    reporter.error(format!("\n{}", "❌ Error: Anthropic API key not found. Please provide ANTHROPIC_API_KEY in your .env file to generate plans.".red()));
                     return Ok(());
                 }
             };
             
-            let anthropic_test = anthropic_client.test_connection().await;
-            if anthropic_test.is_err() {
-                println!("\n{}", "❌ Error: Anthropic API connection failed. Please check your API key and try again.".red());
+            if let Err(e) = check_connection("Anthropic", anthropic_client.test_connection(), &reporter).await {
+                report_connection_failure("Anthropic", &e, &reporter);
                 return Ok(());
             }
             
-            println!("\n{}", "✅ Anthropic API connection successful".green());
-            
             // Create output directory
             std::fs::create_dir_all(&args.output).context("Failed to create output directory")?;
+            start_single_file(&args)?;
             
-            // Generate implementation plan
-            println!("\n{}", format!("Generating implementation plan for: {}", ticket.title).blue());
+            Some((anthropic_client, app_config))
+        } else {
+            None
+        };
+        
+        let mut tickets: Vec<Ticket> = Vec::new();
+        let mut skipped = 0;
+        
+        for ticket_path in &ticket_files {
+            // Load the ticket from its file, skipping files that can't be parsed
+            reporter.info(format!("\nLoading ticket from {}", ticket_path.display()).blue());
+            let (mut ticket, has_timestamps) = match load_ticket_file(ticket_path, args.input_format) {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    reporter.info(format!("⚠️ Skipping {}: {:#}", ticket_path.display(), e).yellow());
+                    skipped += 1;
+                    continue;
+                }
+            };
             
-            let implementation_plan = anthropic_client
-                .generate_implementation_plan(&ticket, &app_config.anthropic_model)
-                .await?;
+            reporter.info("Ticket loaded successfully:".green());
+            reporter.info(format!("Title: {}", ticket.title));
+            reporter.info(format!("ID: {}", ticket.id));
+            reporter.info(format!("State: {}", ticket.state));
             
-            // Create safe filename with format ticketId-title.md
-            let safe_title = ticket.title.chars()
-                .map(|c| if c.is_alphanumeric() { c } else { '_' })
-                .collect::<String>();
-                
-            let filename = format!("{}-{}.md", ticket.id, &safe_title[..std::cmp::min(50, safe_title.len())]);
-            let file_path = args.output.join(filename);
+            // Before planning, offer to refresh snapshots that may be out of date
+            if let Some((_, app_config)) = &planner {
+                let age_days = (chrono::Utc::now() - ticket.updated_at).num_days();
+                if !has_timestamps {
+                    reporter.info(format!(
+                        "⚠️ {} has no saved timestamps, so it can't be checked for staleness",
+                        ticket_path.display()
+                    ).yellow());
+                } else if age_days > STALE_TICKET_DAYS && refetch_stale_ticket(&ticket, age_days, &args, &reporter) {
+                    match refetch_ticket(&ticket.id, ticket_path, app_config, &args, &reporter).await {
+                        Ok(fresh) => {
+                            reporter.info(format!("✅ Refreshed {} from Linear", ticket.id).green());
+                            ticket = fresh;
+                        }
+                        Err(e) => reporter.info(format!(
+                            "⚠️ Could not re-fetch {}: {:#}. Planning from the saved file", ticket.id, e
+                        ).yellow()),
+                    }
+                }
+            }
             
-            // Create the file content with implementation plan
-            let file_content = format!(
-                "# Implementation Plan: {}\n\n\
-                 **Ticket ID:** {}\n\
-                 **State:** {}\n\
-                 **Priority:** {}\n\
-                 **Estimate:** {}\n\
-                 **URL:** {}\n\n\
-                 ---\n\n\
-                 {}",
-                ticket.title,
-                ticket.id,
-                ticket.state,
-                ticket.priority,
-                ticket.estimate.map_or("Not estimated".to_string(), |e| e.to_string()),
-                ticket.url,
-                implementation_plan
-            );
+            tickets.push(ticket);
+        }
+        let loaded = tickets.len();
+        
+        // A batch of files goes through the same checks as tickets fetched from Linear
+        if let Some((anthropic_client, app_config)) = &planner {
+            if args.detect_duplicates {
+                tickets = skip_duplicates(tickets, app_config, &args, &reporter)?;
+            }
+            if !confirm_ticket_count(tickets.len(), app_config, &args, &reporter)?
+                || !confirm_plan_cost(&tickets, anthropic_client, app_config, &args, &reporter)?
+            {
+                reporter.info(format!("\n{}", "Plan generation cancelled. Exiting.".yellow()));
+                return Ok(());
+            }
+        }
+        
+        // The bar stays hidden when plans are reviewed or diffed, as those prompts would
+        // be drawn over; everything else printed during the batch goes above it
+        let prompts = args.interactive_refine || args.diff;
+        let progress_bar = if planner.is_some() && tickets.len() > 1 && !prompts {
+            reporter.progress_bar(tickets.len())
+        } else {
+            indicatif::ProgressBar::hidden()
+        };
+        let bar_reporter = reporter.above(&progress_bar);
+        let mut failures: Vec<(Ticket, anyhow::Error)> = Vec::new();
+        let mut planned: Vec<Ticket> = Vec::new();
+        let mut summaries: Vec<(Ticket, String)> = Vec::new();
+        let mut filenames = TicketFilenames::default();
+        
+        for mut ticket in tickets {
+            // If --plan flag is provided, generate an implementation plan
+            if let Some((anthropic_client, app_config)) = &planner {
+                ticket = prepare_for_prompt(&ticket, anthropic_client, app_config, &args, &bar_reporter).await;
+                
+                let kind = if args.describe {
+                    "description"
+                } else if args.summary {
+                    "summary"
+                } else {
+                    "implementation plan"
+                };
+                bar_reporter.info(format!("Generating {} for: {}", kind, ticket.title).blue());
+                let filename = filenames.claim(&ticket);
+                
+                let result = async {
+                    if args.summary {
+                        let summary = anthropic_client
+                            .generate_summary(&ticket, app_config.model_for(&ticket))
+                            .await?;
+                        return Ok(Some(summary));
+                    }
+                    
+                    let file_path = plan_output_path(&args, &ticket, &filename)?;
+                    
+                    if args.describe {
+                        describe_ticket(anthropic_client, &ticket, app_config, &file_path, &bar_reporter).await?;
+                        return Ok(None);
+                    }
+                    
+                    let implementation_plan = match generate_plan(
+                        anthropic_client, &ticket, app_config.model_for(&ticket), &file_path, &args, &bar_reporter,
+                    ).await? {
+                        Some(plan) => plan,
+                        None => {
+                            bar_reporter.info(format!("Skipped the plan for {}", ticket.id).yellow());
+                            return Ok(None);
+                        }
+                    };
+                    
+                    // Write to file, showing a diff against any existing plan if requested
+                    save_implementation_plan(&ticket, &implementation_plan, &file_path, &args, &bar_reporter).await?;
+                    if args.create_subtasks {
+                        let tasks = progress_bar.suspend(|| subtasks_to_create(&ticket, &implementation_plan, &reporter))?;
+                        if let Some(tasks) = tasks {
+                            create_subtasks(&ticket, &tasks, app_config, &args, &bar_reporter).await;
+                        }
+                    }
+                    Ok(None)
+                }.await;
+                
+                match result {
+                    Ok(summary) => {
+                        if let Some(summary) = summary {
+                            summaries.push((ticket.clone(), summary));
+                        }
+                        planned.push(ticket);
+                    }
+                    Err(e) => {
+                        bar_reporter.error(format!("❌ Failed to generate {} for {}: {:#}", kind, ticket.id, e).red());
+                        failures.push((ticket, e));
+                    }
+                }
+            }
             
-            // Write to file
-            std::fs::write(&file_path, file_content).context("Failed to write implementation plan to file")?;
+            progress_bar.inc(1);
+        }
+        progress_bar.finish_and_clear();
+        
+        if !summaries.is_empty() {
+            save_summaries(&summaries, &args.output, &reporter).await?;
+        }
+        
+        let mut summary = format!("Processed {} ticket file(s): {} loaded, {} skipped", ticket_files.len(), loaded, skipped);
+        if planner.is_some() {
+            summary.push_str(&format!(", {} {} generated, {} failed", planned.len(), generated_kind(&args), failures.len()));
+        }
+        reporter.info("");
+        
+        if let Some((anthropic_client, app_config)) = &planner {
+            // The usage line is the run's summary; the file counts are detail
+            reporter.info(summary.blue());
+            let planned: Vec<&Ticket> = planned.iter().collect();
+            send_notification(&reporter, app_config, &planned, &failures).await;
             
-            // Get absolute path
-            let abs_path = std::fs::canonicalize(&file_path)
-                .unwrap_or_else(|_| file_path.clone());
-            println!("{}", format!("✅ Implementation plan saved to {}", abs_path.display()).green());
+            let report = report::RunReport::new(started_at, None, &planned, &failures, &anthropic_client.usage());
+            write_run_report(&report, &args.output, &args, &reporter).await;
+            record_failures(&args.output, &planned, &failures, &reporter);
+            reporter.summary(report.usage_summary(generated_kind(&args)).blue());
+        } else {
+            reporter.summary(summary.blue());
+        }
+        
+        if !failures.is_empty() {
+            ui::display_failures(&failures);
+            std::process::exit(PARTIAL_FAILURE_EXIT_CODE);
         }
         
         return Ok(());
@@ -244,261 +1011,180 @@ async fn main() -> Result<()> {
     // If using --ticket-id option, we'll fetch and save that specific ticket
     if let Some(ticket_id) = &args.ticket_id {
         // Load environment variables
-        if let Some(env_path) = &args.env {
-            dotenv::from_path(env_path).context("Failed to load .env file")?;
-        } else {
-            // Try to load from default locations
-            let env_locations = config::AppConfig::get_env_locations();
-            let mut loaded = false;
-            
-            for location in env_locations {
-                if location.exists() {
-                    dotenv::from_path(&location).context(format!("Failed to load .env from {}", location.display()))?;
-                    println!("Loaded configuration from {}", location.display());
-                    loaded = true;
-                    break;
-                }
-            }
-            
-            if !loaded {
-                // If no .env file found, try loading from default location just in case
-                dotenv().ok();
-            }
-        }
+        load_env(&args, &reporter)?;
         
         // Load configuration
         let app_config = config::AppConfig::load(None, &args).await?;
         
         // Create Linear client
-        println!("\n{}", "Testing Linear API connection...".blue());
-        let linear_client = linear::LinearClient::new(&app_config.linear_api_key);
+        let linear_client = linear::LinearClient::from_config(&app_config);
         
-        let linear_test = linear_client.test_connection(args.verbose).await;
-        if linear_test.is_err() {
-            println!("\n{}", "❌ Error: Linear API connection failed. Please check your API key and try again.".red());
+        if let Err(e) = check_connection("Linear", linear_client.test_connection(args.verbose), &reporter).await {
+            report_connection_failure("Linear", &e, &reporter);
             return Ok(());
         }
         
-        println!("\n{}", "✅ Linear API connection successful".green());
-        
         // Fetch ticket by ID
-        println!("\n{}", format!("Fetching ticket with ID: {}...", ticket_id).blue());
+        reporter.info(format!("\n{}", format!("Fetching ticket with ID: {}...", ticket_id).blue()));
         let ticket = linear_client.fetch_ticket_by_id(ticket_id, args.verbose).await
             .context(format!("Failed to fetch ticket with ID: {}", ticket_id))?;
         
         // Enrich ticket with additional information
-        println!("\n{}", "Gathering additional information about the ticket...".blue());
-        let skip_labels = !args.plan;
-        let enriched_ticket = linear_client.enrich_ticket(&ticket, args.verbose, skip_labels).await?;
+        reporter.info(format!("\n{}", "Gathering additional information about the ticket...".blue()));
+        let enriched_ticket = linear_client.enrich_ticket(&ticket, args.verbose, enrich_options(&args), &reporter).await?;
         
-        // Create tickets directory
-        std::fs::create_dir_all(&args.tickets_dir).context("Failed to create tickets directory")?;
+        let filename = safe_filename(&enriched_ticket);
         
-        // Create safe filename with format ticketId-title.md
-        let safe_title = enriched_ticket.title.chars()
-            .map(|c| if c.is_alphanumeric() { c } else { '_' })
-            .collect::<String>();
+        // How the closing summary refers to the ticket
+        let saved = if args.no_save_tickets { "fetched" } else { "saved" };
+        if !args.no_save_tickets {
+            // Create tickets directory
+            std::fs::create_dir_all(&args.tickets_dir).context("Failed to create tickets directory")?;
             
-        let filename = format!("{}-{}.md", enriched_ticket.id, &safe_title[..std::cmp::min(50, safe_title.len())]);
-        
-        // Save ticket to tickets directory
-        let ticket_file_path = args.tickets_dir.join(&filename);
-        
-        // Create labels string
-        let labels_str = if enriched_ticket.labels.is_empty() {
-            "None".to_string()
-        } else {
-            enriched_ticket.labels.join(", ")
-        };
-        
-        // Create related tickets string
-        let related_tickets_str = if enriched_ticket.related_tickets.is_empty() {
-            "None".to_string()
-        } else {
-            enriched_ticket.related_tickets.iter()
-                .map(|rt| format!("- {} (State: {})", rt.title, rt.state))
-                .collect::<Vec<String>>()
-                .join("\n")
-        };
-        
-        // Create children tickets string
-        let children_str = if enriched_ticket.children.is_empty() {
-            "None".to_string()
-        } else {
-            enriched_ticket.children.iter()
-                .map(|child| format!("- {} (State: {})", child.title, child.state))
-                .collect::<Vec<String>>()
-                .join("\n")
-        };
-        
-        // Create comments string
-        let comments_str = if enriched_ticket.comments.is_empty() {
-            "None".to_string()
-        } else {
-            enriched_ticket.comments.iter()
-                .map(|comment| {
-                    let user = comment.user.as_deref().unwrap_or("Unknown");
-                    format!("- {} ({}): {}", user, comment.created_at.format("%Y-%m-%d"), comment.body)
-                })
-                .collect::<Vec<String>>()
-                .join("\n")
-        };
-        
-        // Create the file content with ticket information
-        let ticket_file_content = format!(
-            "# Ticket: {}\n\n\
-             **Ticket ID:** {}\n\
-             **State:** {}\n\
-             **Priority:** {}\n\
-             **Estimate:** {}\n\
-             **URL:** {}\n\
-             **Labels:** {}\n\n\
-             ## Description\n\n{}\n\n\
-             ## Comments\n\n{}\n\n\
-             ## Related Tickets\n\n{}\n\n\
-             ## Child Tickets\n\n{}\n\n",
-            enriched_ticket.title,
-            enriched_ticket.id,
-            enriched_ticket.state,
-            enriched_ticket.priority,
-            enriched_ticket.estimate.map_or("Not estimated".to_string(), |e| e.to_string()),
-            enriched_ticket.url,
-            labels_str,
-            enriched_ticket.description,
-            comments_str,
-            related_tickets_str,
-            children_str
-        );
-        
-        // Write the ticket information to the tickets directory
-        std::fs::write(&ticket_file_path, &ticket_file_content)
-            .context("Failed to write ticket information to file")?;
-        
-        // Get absolute path
-        let abs_path = std::fs::canonicalize(&ticket_file_path)
-            .unwrap_or_else(|_| ticket_file_path.clone());
-        println!("{}", format!("✅ Ticket information saved to {}", abs_path.display()).green());
+            // Save ticket to tickets directory
+            let ticket_file_path = args.tickets_dir.join(&filename);
+            
+            // Create the file content with ticket information
+            let ticket_file_content = enriched_ticket.to_markdown(&app_config.blocker_keywords, app_config.limit_comments_chars, app_config.gfm_tables);
+            
+            // Write the ticket information to the tickets directory
+            write_file(&ticket_file_path, &ticket_file_content).await
+                .context("Failed to write ticket information to file")?;
+            
+            // Get absolute path
+            let abs_path = std::fs::canonicalize(&ticket_file_path)
+                .unwrap_or_else(|_| ticket_file_path.clone());
+            reporter.info(format!("✅ Ticket information saved to {}", abs_path.display()).green());
+        }
         
-        // If --plan flag is provided, also generate an implementation plan
-        if args.plan {
+        // With --plan, also generate an implementation plan (or with --describe, an explanation)
+        if args.plan || args.describe {
             // We need to test the Anthropic API connection first
-            println!("\n{}", "Testing Anthropic API connection...".blue());
             let anthropic_client = match anthropic::AnthropicClient::from_config(&app_config) {
                 Some(client) => client,
                 None => {
-                    println!("\n{}", "❌ Error: Anthropic API key not found. Please provide ANTHROPIC_API_KEY in your .env file to generate plans.".red());
+                    reporter.error(format!("\n{}", "❌ Error: Anthropic API key not found. Please provide ANTHROPIC_API_KEY in your .env file to generate plans.".red()));
                     return Ok(());
                 }
             };
             
-            let anthropic_test = anthropic_client.test_connection().await;
-            if anthropic_test.is_err() {
-                println!("\n{}", "❌ Error: Anthropic API connection failed. Please check your API key and try again.".red());
+            if let Err(e) = check_connection("Anthropic", anthropic_client.test_connection(), &reporter).await {
+                report_connection_failure("Anthropic", &e, &reporter);
                 return Ok(());
             }
             
-            println!("\n{}", "✅ Anthropic API connection successful".green());
-            
             // Create output directory
             std::fs::create_dir_all(&args.output).context("Failed to create implementation plans directory")?;
+            start_single_file(&args)?;
             
-            // Generate implementation plan
-            println!("\n{}", format!("Generating implementation plan for: {}", enriched_ticket.title).blue());
+            let enriched_ticket = prepare_for_prompt(&enriched_ticket, &anthropic_client, &app_config, &args, &reporter).await;
+            
+            if args.summary {
+                reporter.info(format!("\n{}", format!("Generating summary for: {}", enriched_ticket.title).blue()));
+                let summary = anthropic_client
+                    .generate_summary(&enriched_ticket, app_config.model_for(&enriched_ticket))
+                    .await?;
+                save_summaries(&[(enriched_ticket.clone(), summary)], &args.output, &reporter).await?;
+                reporter.summary(format!("Ticket {} {} and summarized", enriched_ticket.id, saved));
+                return Ok(());
+            }
+            
+            if args.describe {
+                reporter.info(format!("\n{}", format!("Generating description for: {}", enriched_ticket.title).blue()));
+                let plan_file_path = plan_output_path(&args, &enriched_ticket, &filename)?;
+                describe_ticket(&anthropic_client, &enriched_ticket, &app_config, &plan_file_path, &reporter).await?;
+                reporter.summary(format!("Ticket {} {} and described", enriched_ticket.id, saved));
+                return Ok(());
+            }
             
-            let implementation_plan = anthropic_client
-                .generate_implementation_plan(&enriched_ticket, &app_config.anthropic_model)
-                .await?;
+            if !confirm_plan_cost(std::slice::from_ref(&enriched_ticket), &anthropic_client, &app_config, &args, &reporter)? {
+                reporter.summary(format!("Ticket {} {}, plan generation cancelled", enriched_ticket.id, saved));
+                return Ok(());
+            }
+            
+            // Generate implementation plan
+            reporter.info(format!("\n{}", format!("Generating implementation plan for: {}", enriched_ticket.title).blue()));
             
             // Path for the implementation plan (using the same filename format for consistency)
-            let plan_file_path = args.output.join(&filename);
+            let plan_file_path = plan_output_path(&args, &enriched_ticket, &filename)?;
             
-            // Create the file content with implementation plan
-            let plan_file_content = format!(
-                "# Implementation Plan: {}\n\n\
-                 **Ticket ID:** {}\n\
-                 **State:** {}\n\
-                 **Priority:** {}\n\
-                 **Estimate:** {}\n\
-                 **URL:** {}\n\n\
-                 ---\n\n\
-                 {}",
-                enriched_ticket.title,
-                enriched_ticket.id,
-                enriched_ticket.state,
-                enriched_ticket.priority,
-                enriched_ticket.estimate.map_or("Not estimated".to_string(), |e| e.to_string()),
-                enriched_ticket.url,
-                implementation_plan
-            );
+            let implementation_plan = match generate_plan(
+                &anthropic_client, &enriched_ticket, app_config.model_for(&enriched_ticket), &plan_file_path, &args, &reporter,
+            ).await? {
+                Some(plan) => plan,
+                None => {
+                    reporter.summary(format!("Ticket {} {}, plan skipped", enriched_ticket.id, saved));
+                    return Ok(());
+                }
+            };
             
             // Write the implementation plan to the output directory
-            std::fs::write(&plan_file_path, plan_file_content)
-                .context("Failed to write implementation plan to file")?;
+            save_implementation_plan(&enriched_ticket, &implementation_plan, &plan_file_path, &args, &reporter).await?;
             
-            // Get absolute path
-            let abs_path = std::fs::canonicalize(&plan_file_path)
-                .unwrap_or_else(|_| plan_file_path.clone());
-            println!("{}", format!("✅ Implementation plan saved to {}", abs_path.display()).green());
+            if args.create_subtasks {
+                if let Some(tasks) = subtasks_to_create(&enriched_ticket, &implementation_plan, &reporter)? {
+                    create_subtasks(&enriched_ticket, &tasks, &app_config, &args, &reporter).await;
+                }
+            }
         }
         
+        let action = if args.plan { format!("{} and planned", saved) } else { saved.to_string() };
+        reporter.summary(format!("Ticket {} {}", enriched_ticket.id, action));
+        
         return Ok(());
     }
 
     // Regular mode: fetch tickets from Linear
     // Load environment variables from .env file if specified
-    if let Some(env_path) = &args.env {
-        dotenv::from_path(env_path).context("Failed to load .env file")?;
-    } else {
-        // Try to load from default locations
-        let env_locations = config::AppConfig::get_env_locations();
-        let mut loaded = false;
-        
-        for location in env_locations {
-            if location.exists() {
-                dotenv::from_path(&location).context(format!("Failed to load .env from {}", location.display()))?;
-                println!("Loaded configuration from {}", location.display());
-                loaded = true;
-                break;
-            }
-        }
-        
-        if !loaded {
-            // If no .env file found, try loading from default location just in case
-            dotenv().ok();
-        }
-    }
+    load_env(&args, &reporter)?;
 
     // Load or create configuration
-    let app_config = if args.setup {
+    let mut app_config = if args.setup {
         ui::setup_wizard().await?
     } else {
         config::AppConfig::load(None, &args).await?
     };
 
     // Test Linear API connection
-    println!("\n{}", "Testing Linear API connection...".blue());
-    let linear_client = linear::LinearClient::new(&app_config.linear_api_key);
-    let linear_test = linear_client.test_connection(args.verbose).await;
+    let linear_client = linear::LinearClient::from_config(&app_config);
+    if let Err(e) = check_connection("Linear", linear_client.test_connection(args.verbose), &reporter).await {
+        report_connection_failure("Linear", &e, &reporter);
+        return Ok(());
+    }
+
+    // Without a configured team, use the viewer's team. The configuration is loaded again
+    // so that the team's config.toml section applies, as if it had been given with --team.
+    if app_config.linear_team_name.trim().is_empty() && args.view.is_none() && !args.retry_failed {
+        args.team = Some(discover_team(&linear_client, &args, &reporter).await?);
+        app_config = config::AppConfig::load(None, &args).await?;
+    }
 
-    if linear_test.is_err() {
-        println!("\n{}", "❌ Error: Linear API connection failed. Please check your API key and try again.".red());
+    // Show the team's workload instead of fetching tickets to plan
+    if args.team_members {
+        reporter.info(format!("\n{}", format!("Counting open tickets in {}...", app_config.linear_team_name).blue()));
+        let workload = linear_client
+            .fetch_team_workload(&app_config.linear_team_name, app_config.state_filter(), args.verbose)
+            .await?;
+        if workload.is_empty() {
+            reporter.info(format!("\n{}", format!("⚠️ No members found in team '{}'", app_config.linear_team_name).yellow()));
+            return Ok(());
+        }
+        ui::display_team_workload(&app_config.linear_team_name, &app_config.state_filter().to_string(), &workload);
         return Ok(());
     }
 
-    // Test Anthropic API connection only if needed for plan generation
-    let anthropic_client = if args.plan {
-        println!("\n{}", "Testing Anthropic API connection...".blue());
+    // Test Anthropic API connection only if needed for plan generation or --describe
+    let anthropic_client = if args.plan || args.describe {
         let client = match anthropic::AnthropicClient::from_config(&app_config) {
             Some(client) => client,
             None => {
-                println!("\n{}", "❌ Error: Anthropic API key not found. Please provide ANTHROPIC_API_KEY in your .env file to generate plans.".red());
+                reporter.error(format!("\n{}", "❌ Error: Anthropic API key not found. Please provide ANTHROPIC_API_KEY in your .env file to generate plans.".red()));
                 return Ok(());
             }
         };
         
-        let anthropic_test = client.test_connection().await;
-        if anthropic_test.is_err() {
-            println!("\n{}", "❌ Error: Anthropic API connection failed. Please check your API key and try again.".red());
+        if let Err(e) = check_connection("Anthropic", client.test_connection(), &reporter).await {
+            report_connection_failure("Anthropic", &e, &reporter);
             return Ok(());
         }
         
@@ -507,282 +1193,2089 @@ async fn main() -> Result<()> {
         None
     };
 
-    println!("\n{}", "✅ API connection(s) successful".green());
-
     // Fetch tickets assigned to the user
-    println!("\n{}", format!("Fetching tickets assigned to {}...", app_config.linear_agent_user).blue());
-    let tickets = linear_client.fetch_user_tickets(
-        &app_config.linear_team_name,
-        &app_config.linear_agent_user,
-        &app_config.linear_agent_states,
-        args.verbose,
-    ).await?;
+    let tickets = fetch_tickets(&linear_client, &args, &mut app_config, &reporter).await?;
+    
+    // Overlapping sources list some tickets more than once; plan each of them only once
+    let (mut tickets, duplicates) = dedup_tickets(tickets);
+    if duplicates > 0 {
+        reporter.info(format!("Skipped {} ticket(s) listed more than once", duplicates).dimmed());
+    }
 
     if tickets.is_empty() {
-        println!("\n{}", format!("⚠️ No tickets found for user '{}'", app_config.linear_agent_user).yellow());
-        println!("{}", format!("Please check if the user exists in Linear and has tickets assigned in the states: {}", 
-                          app_config.linear_agent_states.join(", ")).yellow());
+        // fetch_failed_tickets has said why there is nothing to retry
+        if args.retry_failed {
+            return Ok(());
+        }
+        if let Some(view_id) = &args.view {
+            reporter.info(format!("\n{}", format!("⚠️ View '{}' has no issues", view_id).yellow()));
+            return Ok(());
+        }
+        if args.unassigned {
+            reporter.info(format!("\n{}", format!("⚠️ No unassigned tickets found in {} for {}",
+                              app_config.linear_team_name, app_config.state_filter()).yellow()));
+            return Ok(());
+        }
+        reporter.info(format!("\n{}", format!("⚠️ No tickets found for user '{}'", app_config.linear_agent_user).yellow()));
+        reporter.info(format!("Please check if the user exists in Linear and has tickets assigned in {}", 
+                          app_config.state_filter()).yellow());
+        return Ok(());
+    }
+    
+    if args.sort_by_blocked {
+        sort_by_blocked(&linear_client, &mut tickets, &args, &reporter).await;
+    }
+
+    // Export the list as CSV instead of the interactive flow
+    if args.output_format == OutputFormat::Csv {
+        std::fs::create_dir_all(&args.tickets_dir).context("Failed to create tickets directory")?;
+        let csv_path = args.tickets_dir.join("tickets.csv");
+        export::write_tickets_csv(&tickets, &csv_path)?;
+        
+        let abs_path = std::fs::canonicalize(&csv_path).unwrap_or_else(|_| csv_path.clone());
+        reporter.summary(format!("✅ Exported {} tickets to {}", tickets.len(), abs_path.display()).green());
+        return Ok(());
+    }
+    
+    // Print the list as a markdown table for sharing
+    if args.output_format == OutputFormat::MdTable {
+        print!("{}", export::format_tickets_markdown_table(&tickets));
         return Ok(());
     }
 
-    // Display tickets
-    ui::display_tickets(&tickets);
+    // Display tickets (the selection prompt still lists titles in quiet mode)
+    if !reporter.is_quiet() && !args.all && !args.retry_failed {
+        ui::display_tickets(&tickets, &app_config.priority_colors, args.show_description);
+    }
 
-    // Always interactive - get user's selection of tickets to process
-    let selected_indices = ui::get_user_selection(&tickets, args.plan)?;
+    // Interactive unless --all or --retry-failed - get user's selection of tickets to process
+    let selected_indices = if args.all || args.retry_failed {
+        (0..tickets.len()).collect()
+    } else {
+        ui::get_user_selection(&tickets, &selection_order(&tickets, args.sort_selection), args.plan)?
+    };
     if selected_indices.is_empty() {
-        println!("\n{}", "No tickets selected. Exiting.".yellow());
+        reporter.info(format!("\n{}", "No tickets selected. Exiting.".yellow()));
         return Ok(());
     }
     
-    let selected_tickets: Vec<Ticket> = selected_indices.into_iter()
+    let mut selected_tickets: Vec<Ticket> = selected_indices.into_iter()
         .map(|i| tickets[i].clone())
         .collect();
+    
+    if args.detect_duplicates {
+        selected_tickets = skip_duplicates(selected_tickets, &app_config, &args, &reporter)?;
+    }
 
     let message = if args.plan {
         format!("Selected {} tickets for implementation plan generation:", selected_tickets.len())
     } else {
         format!("Selected {} tickets for analysis:", selected_tickets.len())
     };
-    println!("\n{}", message.blue());
+    reporter.info(format!("\n{}", message.blue()));
     for (i, ticket) in selected_tickets.iter().enumerate() {
-        println!("{}. {}", i + 1, ticket.title);
+        reporter.info(format!("{}. {}", i + 1, ticket.title));
     }
-
-    // Enrich selected tickets with additional information
-    println!("\n{}", "Gathering additional information about selected tickets...".blue());
-    let mut enriched_tickets = Vec::new();
     
-    let progress_bar = indicatif::ProgressBar::new(selected_tickets.len() as u64);
-    for ticket in &selected_tickets {
-        progress_bar.println(format!("Enriching ticket: {}", ticket.title));
-        // Skip fetching labels if not needed unless we're generating plans
-        let skip_labels = !args.plan;
-        let enriched = linear_client.enrich_ticket(ticket, args.verbose, skip_labels).await?;
-        enriched_tickets.push(enriched);
-        progress_bar.inc(1);
+    if !confirm_ticket_count(selected_tickets.len(), &app_config, &args, &reporter)? {
+        reporter.info(format!("\n{}", "Plan generation cancelled. Exiting.".yellow()));
+        return Ok(());
     }
-    progress_bar.finish_with_message("All tickets enriched");
 
-    // Always create the tickets directory to store ticket information
-    std::fs::create_dir_all(&args.tickets_dir).context("Failed to create tickets directory")?;
+    // Enrich selected tickets with additional information. Ticket files hold the whole
+    // ticket, so only a listing that saves none (--no-save-tickets) can make do with the
+    // ticket list, which already has everything the listing shows.
+    let enrich = args.plan || args.describe || !args.no_save_tickets;
+    // Tickets that failed at any stage; the rest of the batch keeps going
+    let (enriched_tickets, mut failures) = if enrich {
+        reporter.info(format!("\n{}", "Gathering additional information about selected tickets...".blue()));
+        enrich_tickets(&linear_client, &selected_tickets, &args, &reporter).await
+    } else {
+        (selected_tickets.clone(), Vec::new())
+    };
+
+    if let Some(anthropic_client) = &anthropic_client {
+        if !confirm_plan_cost(&enriched_tickets, anthropic_client, &app_config, &args, &reporter)? {
+            reporter.info(format!("\n{}", "Plan generation cancelled. Exiting.".yellow()));
+            return Ok(());
+        }
+    }
+    
+    // Create the tickets directory to store ticket information, unless --no-save-tickets
+    if !args.no_save_tickets {
+        std::fs::create_dir_all(&args.tickets_dir).context("Failed to create tickets directory")?;
+    }
     
-    // If generating plans, create the output directory too
-    if args.plan {
+    // If generating plans or descriptions, create the output directory too
+    if args.plan || args.describe {
         std::fs::create_dir_all(&args.output).context("Failed to create implementation plans directory")?;
+        start_single_file(&args)?;
     }
 
-    // Process each enriched ticket
+    // Process each enriched ticket, collecting failures instead of aborting the batch
+    let mut processed: Vec<&Ticket> = Vec::new();
+    let mut summaries: Vec<(Ticket, String)> = Vec::new();
+    let mut filenames = TicketFilenames::default();
     for (i, ticket) in enriched_tickets.iter().enumerate() {
-        let safe_title = ticket.title.chars()
-            .map(|c| if c.is_alphanumeric() { c } else { '_' })
-            .collect::<String>();
-            
-        // Use the new filename format: ticketId-title.md
-        let filename = format!("{}-{}.md", ticket.id, &safe_title[..std::cmp::min(50, safe_title.len())]);
-        
-        // Always save the ticket information to the tickets directory
-        let ticket_file_path = args.tickets_dir.join(&filename);
-        
-        // Create labels string
-        let labels_str = if ticket.labels.is_empty() {
-            "None".to_string()
-        } else {
-            ticket.labels.join(", ")
-        };
-        
-        // Create related tickets string
-        let related_tickets_str = if ticket.related_tickets.is_empty() {
-            "None".to_string()
-        } else {
-            ticket.related_tickets.iter()
-                .map(|rt| format!("- {} (State: {})", rt.title, rt.state))
-                .collect::<Vec<String>>()
-                .join("\n")
-        };
-        
-        // Create children tickets string
-        let children_str = if ticket.children.is_empty() {
-            "None".to_string()
-        } else {
-            ticket.children.iter()
-                .map(|child| format!("- {} (State: {})", child.title, child.state))
-                .collect::<Vec<String>>()
-                .join("\n")
-        };
-        
-        // Create comments string
-        let comments_str = if ticket.comments.is_empty() {
-            "None".to_string()
-        } else {
-            ticket.comments.iter()
-                .map(|comment| {
-                    let user = comment.user.as_deref().unwrap_or("Unknown");
-                    format!("- {} ({}): {}", user, comment.created_at.format("%Y-%m-%d"), comment.body)
-                })
-                .collect::<Vec<String>>()
-                .join("\n")
-        };
-        
-        // Create the file content with ticket information
-        let ticket_file_content = format!(
-            "# Ticket: {}\n\n\
-             **Ticket ID:** {}\n\
-             **State:** {}\n\
-             **Priority:** {}\n\
-             **Estimate:** {}\n\
-             **URL:** {}\n\
-             **Labels:** {}\n\n\
-             ## Description\n\n{}\n\n\
-             ## Comments\n\n{}\n\n\
-             ## Related Tickets\n\n{}\n\n\
-             ## Child Tickets\n\n{}\n\n\
-             ",
-            ticket.title,
-            ticket.id,
-            ticket.state,
-            ticket.priority,
-            ticket.estimate.map_or("Not estimated".to_string(), |e| e.to_string()),
-            ticket.url,
-            labels_str,
-            ticket.description,
-            comments_str,
-            related_tickets_str,
-            children_str
-        );
-        
-        // Always write the ticket information to the tickets directory
-        println!("\n{}", format!("[{}/{}] Saving ticket information: {}", 
-                         i + 1, enriched_tickets.len(), ticket.title).blue());
-        
-        std::fs::write(&ticket_file_path, &ticket_file_content)
-            .context("Failed to write ticket information to file")?;
-        
-        // Get absolute path
-        let abs_path = std::fs::canonicalize(&ticket_file_path)
-            .unwrap_or_else(|_| ticket_file_path.clone());
-        println!("{}", format!("✅ Ticket information saved to {}", abs_path.display()).green());
+        let result = process_ticket(
+            ticket,
+            &filenames.claim(ticket),
+            &args,
+            anthropic_client.as_ref(),
+            &app_config,
+            i + 1,
+            enriched_tickets.len(),
+            &reporter,
+        ).await;
         
-        // If --plan flag is provided, also generate an implementation plan
-        if args.plan {
-            println!("\n{}", format!("[{}/{}] Generating implementation plan for: {}", 
-                              i + 1, enriched_tickets.len(), ticket.title).blue());
-            
-            // Generate implementation plan
-            let implementation_plan = anthropic_client.as_ref().unwrap()
-                .generate_implementation_plan(ticket, &app_config.anthropic_model)
-                .await?;
-            
-            // Path for the implementation plan
-            let plan_file_path = args.output.join(&filename);
-            
-            // Create the file content with implementation plan
-            let plan_file_content = format!(
-                "# Implementation Plan: {}\n\n\
-                 **Ticket ID:** {}\n\
-                 **State:** {}\n\
-                 **Priority:** {}\n\
-                 **Estimate:** {}\n\
-                 **URL:** {}\n\n\
-                 ---\n\n\
-                 {}",
-                ticket.title,
-                ticket.id,
-                ticket.state,
-                ticket.priority,
-                ticket.estimate.map_or("Not estimated".to_string(), |e| e.to_string()),
-                ticket.url,
-                implementation_plan
-            );
-            
-            // Write the implementation plan to the output directory
-            std::fs::write(&plan_file_path, plan_file_content)
-                .context("Failed to write implementation plan to file")?;
-            
-            // Get absolute path
-            let abs_path = std::fs::canonicalize(&plan_file_path)
-                .unwrap_or_else(|_| plan_file_path.clone());
-            println!("{}", format!("✅ Implementation plan saved to {}", abs_path.display()).green());
+        match result {
+            Ok(summary) => {
+                if let Some(summary) = summary {
+                    summaries.push((ticket.clone(), summary));
+                }
+                processed.push(ticket);
+            }
+            Err(e) => {
+                reporter.error(format!("❌ Failed to process ticket {}: {:#}", ticket.id, e).red());
+                failures.push((ticket.clone(), e));
+            }
         }
     }
     
-    if !enriched_tickets.is_empty() {
-        // Always show message about saved tickets
-        println!("\n{}", "✅ All ticket information saved successfully".green());
-        // Get absolute path
-        let tickets_abs_path = std::fs::canonicalize(&args.tickets_dir)
-            .unwrap_or_else(|_| args.tickets_dir.clone());
-        println!("{}", format!("Ticket information saved to the '{}' directory", tickets_abs_path.display()).blue());
+    if !summaries.is_empty() {
+        save_summaries(&summaries, &args.output, &reporter).await?;
+    }
+    
+    if !processed.is_empty() {
+        if !args.no_save_tickets {
+            reporter.info(format!("\n{}", format!("✅ Ticket information saved for {} ticket(s)", processed.len()).green()));
+            // Get absolute path
+            let tickets_abs_path = std::fs::canonicalize(&args.tickets_dir)
+                .unwrap_or_else(|_| args.tickets_dir.clone());
+            reporter.info(format!("Ticket information saved to the '{}' directory", tickets_abs_path.display()).blue());
+        }
         
         // If plans were generated, show message about that too
-        if args.plan {
-            println!("\n{}", "✅ All implementation plans generated successfully".green());
+        if args.plan && !args.summary {
+            reporter.info(format!("\n{}", format!("✅ Implementation plans generated for {} ticket(s)", processed.len()).green()));
             // Get absolute path
             let output_abs_path = std::fs::canonicalize(&args.output)
                 .unwrap_or_else(|_| args.output.clone());
-            println!("{}", format!("Implementation plans saved to the '{}' directory", output_abs_path.display()).blue());
+            reporter.info(format!("Implementation plans saved to the '{}' directory", output_abs_path.display()).blue());
         }
     }
     
+    send_notification(&reporter, &app_config, &processed, &failures).await;
+    
+    let usage = anthropic_client.as_ref().map(|client| client.usage()).unwrap_or_default();
+    let report = report::RunReport::new(started_at, Some(&app_config), &processed, &failures, &usage);
+    write_run_report(&report, report_dir(&args), &args, &reporter).await;
+    record_failures(report_dir(&args), &processed, &failures, &reporter);
+    
+    if args.retry_failed {
+        reporter.summary(format!(
+            "Retried {} ticket(s): {} succeeded, {} still failed",
+            selected_tickets.len(),
+            processed.len(),
+            failures.len()
+        ));
+    }
+    
+    if !failures.is_empty() {
+        ui::display_failures(&failures);
+    }
+    
+    if anthropic_client.is_some() {
+        reporter.summary(report.usage_summary(generated_kind(&args)));
+    } else {
+        reporter.summary(format!(
+            "{} of {} ticket(s) saved, {} failed",
+            processed.len(),
+            selected_tickets.len(),
+            failures.len()
+        ));
+    }
+    
+    if !failures.is_empty() {
+        std::process::exit(PARTIAL_FAILURE_EXIT_CODE);
+    }
+    
     Ok(())
 }
 
-/// Check for updates by comparing the current version with the latest release on GitHub
-async fn check_for_updates() -> Result<()> {
-    println!("{}", "Checking for updates...".blue());
+/// What the Anthropic requests of the run generate, for summaries
+fn generated_kind(args: &Args) -> &'static str {
+    if args.describe {
+        "description(s)"
+    } else if args.summary {
+        "summary(ies)"
+    } else {
+        "plan(s)"
+    }
+}
+
+/// Send the run summary to Slack if a webhook is configured. This is best-effort:
+/// a failed notification is reported but never fails the run.
+async fn send_notification(
+    reporter: &ui::Reporter,
+    app_config: &config::AppConfig,
+    processed: &[&Ticket],
+    failures: &[(Ticket, anyhow::Error)],
+) {
+    if let Some(webhook_url) = &app_config.slack_webhook_url {
+        let client = app_config.http_client().unwrap_or_default();
+        match notify::notify_slack(&client, webhook_url, processed, failures).await {
+            Ok(()) => reporter.info("Slack notification sent".blue()),
+            Err(e) => reporter.info(format!("⚠️ Failed to send Slack notification: {:#}", e).yellow()),
+        }
+    }
+}
+
+/// Write the run report to --run-report, relative to `dir`. Like the notification this
+/// is best-effort and never fails the run.
+async fn write_run_report(report: &report::RunReport, dir: &Path, args: &Args, reporter: &ui::Reporter) {
+    let path = dir.join(&args.run_report);
+    let result = match report.to_json() {
+        Ok(json) => write_file(&path, json).await
+            .with_context(|| format!("Failed to write {}", path.display())),
+        Err(e) => Err(e),
+    };
     
-    // Get current version from Cargo.toml
-    let current_version = env!("CARGO_PKG_VERSION");
-    println!("Current version: {}", current_version);
+    match result {
+        Ok(()) => reporter.info(format!("Run report saved to {}", path.display()).blue()),
+        Err(e) => reporter.info(format!("⚠️ Failed to save the run report: {:#}", e).yellow()),
+    }
+}
+
+/// Directory of the run report and failures.json: the output directory, or the tickets
+/// directory when no plans or descriptions are generated
+fn report_dir(args: &Args) -> &Path {
+    if args.plan || args.describe { &args.output } else { &args.tickets_dir }
+}
+
+/// Update the failures.json in `dir` with the outcome of the run, for --retry-failed.
+/// Best-effort like the run report.
+fn record_failures(dir: &Path, processed: &[&Ticket], failures: &[(Ticket, anyhow::Error)], reporter: &ui::Reporter) {
+    let path = dir.join(report::FAILURES_FILENAME);
+    let result = report::FailedTickets::load(&path).and_then(|mut failed| {
+        failed.update(processed, failures);
+        failed.save(&path)
+    });
     
-    // GitHub API endpoint for the latest release
-    let github_url = "https://api.github.com/repos/oorestisime/linear-agent/releases/latest";
+    match result {
+        Ok(()) if !failures.is_empty() => {
+            reporter.info(format!("Failed tickets listed in {}; rerun with --retry-failed", path.display()).blue())
+        }
+        Ok(()) => {}
+        Err(e) => reporter.info(format!("⚠️ Failed to update the list of failed tickets: {:#}", e).yellow()),
+    }
+}
+
+/// Load environment variables from the --env file, or from the first default location
+/// found, returning the .env file that was loaded, if any
+fn load_env(args: &Args, reporter: &ui::Reporter) -> Result<Option<PathBuf>> {
+    if let Some(env_path) = &args.env {
+        dotenv::from_path(env_path).context("Failed to load .env file")?;
+        return Ok(Some(env_path.clone()));
+    }
     
-    // Create a client with proper user-agent header (required by GitHub API)
-    let client = reqwest::Client::builder()
-        .user_agent("linear-agent-updater")
-        .build()?;
+    // Try to load from default locations
+    for location in config::AppConfig::get_env_locations() {
+        if location.exists() {
+            dotenv::from_path(&location).context(format!("Failed to load .env from {}", location.display()))?;
+            reporter.info(format!("Loaded configuration from {}", location.display()));
+            return Ok(Some(location));
+        }
+    }
     
-    // Make the request to the GitHub API
-    let response = client.get(github_url).send().await;
+    // If no .env file found, try loading from default location just in case
+    Ok(dotenv().ok())
+}
+
+/// Print every effective setting (--dump-config) with its value, secrets masked, and
+/// where it came from. Variables that only exist after loading the .env file are
+/// attributed to it; the others were set in the shell, which takes precedence.
+async fn dump_config(args: &Args, reporter: &ui::Reporter) -> Result<()> {
+    let shell_vars: HashSet<String> = std::env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .collect();
+    let env_file = load_env(args, reporter)?;
+    let (app_config, sources) = config::AppConfig::load_with_sources(args).await?;
     
-    match response {
-        Ok(response) => {
-            if response.status().is_success() {
-                // Parse the JSON response
-                let release: serde_json::Value = response.json().await?;
-                
-                // Extract the latest version (without 'v' prefix)
-                if let Some(tag_name) = release["tag_name"].as_str() {
-                    let latest_version = tag_name.trim_start_matches('v');
-                    println!("Latest version: {}", latest_version);
-                    
-                    // Compare versions
-                    if latest_version != current_version {
-                        println!("{}", "A new version is available!".green());
-                        println!("To update, run the following command:");
-                        
-                        println!("\nDownload and install:");
-                        println!("curl -L https://github.com/oorestisime/linear-agent/releases/latest/download/linear-agent -o linear-agent && chmod +x linear-agent");
-                        
-                        println!("\nMove to your local bin directory:");
-                        println!("mkdir -p ~/.local/bin && mv linear-agent ~/.local/bin/");
-                        
-                        println!("\nOr download directly from: {}", release["html_url"].as_str().unwrap_or("https://github.com/oorestisime/linear-agent/releases"));
-                    } else {
-                        println!("{}", "You are using the latest version!".green());
-                    }
-                } else {
-                    println!("{}", "Failed to extract version from the latest release.".red());
-                }
-            } else {
-                println!("{}", format!("Failed to check for updates: HTTP {}", response.status()).red());
-            }
-        }
-        Err(err) => {
-            println!("{}", format!("Failed to check for updates: {}", err).red());
+    let settings = app_config.masked_settings()?;
+    let name_width = settings.iter().map(|(name, _)| name.len()).max().unwrap_or_default();
+    println!("{}", "Effective configuration (secrets masked):".blue());
+    for (name, value) in &settings {
+        let source = match sources.get(name) {
+            config::Source::Default => "default".to_string(),
+            config::Source::Env(var) => match &env_file {
+                Some(path) if !shell_vars.contains(var) => format!("{} in {}", var, path.display()),
+                _ => format!("${}", var),
+            },
+            config::Source::Team(team) => format!("config.toml [teams.{}]", team),
+            config::Source::Flag(flag) => flag.to_string(),
+        };
+        println!("  {:<width$}  {}  {}", name, value, format!("({})", source).dimmed(), width = name_width);
+    }
+    
+    Ok(())
+}
+
+/// Run the --raw-query file against Linear and print the pretty-printed `data` to stdout
+async fn run_raw_query(query_path: &Path, vars_path: Option<&Path>, args: &Args) -> Result<()> {
+    let query = fs::read_to_string(query_path)
+        .context(format!("Failed to read query file: {}", query_path.display()))?;
+    let variables = match vars_path {
+        Some(path) => {
+            let vars = fs::read_to_string(path)
+                .context(format!("Failed to read variables file: {}", path.display()))?;
+            serde_json::from_str(&vars)
+                .context(format!("Variables file is not valid JSON: {}", path.display()))?
         }
+        None => serde_json::json!({}),
+    };
+    
+    // Keep stdout to the JSON response only
+    load_env(args, &ui::Reporter::new(true))?;
+    let app_config = config::AppConfig::load(None, args).await?;
+    let linear_client = linear::LinearClient::from_config(&app_config);
+    
+    let data = linear_client.execute_raw_query(&query, variables, args.verbose).await?;
+    println!("{}", serde_json::to_string_pretty(&data)?);
+    
+    Ok(())
+}
+
+/// Compare the --github-pr pull request with the saved plan of the --ticket file and save
+/// a gap report next to the plan
+async fn verify_pull_request(args: &Args, reporter: &ui::Reporter) -> Result<()> {
+    let mut ticket_files = Vec::new();
+    for path in &args.ticket {
+        ticket_files.extend(collect_ticket_files(path, args.input_format)?);
     }
+    let ticket_path = match ticket_files.as_slice() {
+        [path] => path,
+        _ => anyhow::bail!("--verify needs exactly one saved ticket file, found {}", ticket_files.len()),
+    };
+    let (ticket, _) = load_ticket_file(ticket_path, args.input_format)?;
+    
+    let plan_path = saved_plan_path(args, &ticket)?;
+    let plan = fs::read_to_string(&plan_path).context(format!(
+        "No saved plan for {} at {}; generate one with --plan first", ticket.id, plan_path.display()
+    ))?;
+    
+    load_env(args, reporter)?;
+    let app_config = config::AppConfig::load(None, args).await?;
+    let anthropic_client = anthropic::AnthropicClient::from_config(&app_config)
+        .context("Anthropic API key not found. Please provide ANTHROPIC_API_KEY in your .env file to verify plans.")?;
+    
+    let pr_url = args.github_pr.as_deref().unwrap_or_default();
+    reporter.info(format!("\n{}", format!("Fetching pull request {}...", pr_url).blue()));
+    let pull_request = github::fetch_pull_request(
+        &app_config.http_client()?,
+        &app_config.github_api_url,
+        pr_url,
+        app_config.github_token.as_deref(),
+    ).await?;
+    
+    reporter.info(format!(
+        "Comparing {} changed file(s) with the plan for {}...", pull_request.files.len(), ticket.id
+    ).blue());
+    let report = anthropic_client
+        .verify_implementation(&ticket, &plan, &pull_request.summary(), app_config.model_for(&ticket))
+        .await?;
+    
+    let report_content = format!(
+        "# Plan Verification: {}\n\n\
+         **Ticket ID:** {}\n\
+         **Pull Request:** {}\n\
+         **Plan:** {}{}{}",
+        ticket.title,
+        ticket.id,
+        pull_request.html_url,
+        plan_path.display(),
+        PLAN_HEADER_SEPARATOR,
+        report
+    );
+    let report_path = plan_path.with_extension("verify.md");
+    write_file(&report_path, report_content).await
+        .context("Failed to write verification report to file")?;
+    reporter.summary(format!("✅ Verification report saved to {}", report_path.display()).green());
     
     Ok(())
 }
+
+/// Load a saved ticket file, also reporting whether it recorded the ticket's timestamps
+/// (markdown files saved by older versions don't, and get the current time instead)
+fn load_ticket_file(path: &Path, format: Option<InputFormat>) -> Result<(Ticket, bool)> {
+    let ticket_content = fs::read_to_string(path)
+        .context(format!("Failed to read ticket file: {}", path.display()))?;
+    
+    match InputFormat::of(path, format) {
+        InputFormat::Markdown => {
+            let ticket = Ticket::from_markdown(&ticket_content)
+                .context("Failed to parse ticket from markdown file")?;
+            let has_timestamps = ticket_content.lines().any(|line| line.starts_with("**Updated:**") || line.starts_with("| Updated |"));
+            Ok((ticket, has_timestamps))
+        }
+        InputFormat::Json => {
+            // The timestamps are required fields of a JSON ticket
+            let ticket = serde_json::from_str(&ticket_content)
+                .context("Failed to parse ticket from JSON file")?;
+            Ok((ticket, true))
+        }
+    }
+}
+
+/// Fetch the live version of a saved ticket and overwrite its file with it, in the
+/// format the file was read in
+async fn refetch_ticket(
+    ticket_id: &str,
+    ticket_path: &Path,
+    app_config: &config::AppConfig,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Result<Ticket> {
+    let linear_client = linear::LinearClient::from_config(app_config);
+    let ticket = linear_client.fetch_ticket_by_id(ticket_id, args.verbose).await?;
+    let enriched = linear_client.enrich_ticket(&ticket, args.verbose, enrich_options(args), reporter).await?;
+    
+    let content = match InputFormat::of(ticket_path, args.input_format) {
+        InputFormat::Markdown => enriched.to_markdown(&app_config.blocker_keywords, app_config.limit_comments_chars, app_config.gfm_tables),
+        InputFormat::Json => serde_json::to_string_pretty(&enriched)?,
+    };
+    write_file(ticket_path, content).await
+        .context(format!("Failed to update ticket file: {}", ticket_path.display()))?;
+    
+    Ok(enriched)
+}
+
+/// List the ticket files at `path`: the file itself, or every file of the given format
+/// in a directory (.md and .json files when no format is given)
+fn collect_ticket_files(path: &Path, format: Option<InputFormat>) -> Result<Vec<PathBuf>> {
+    let extensions = match format {
+        Some(format) => vec![format.extension()],
+        None => vec![InputFormat::Markdown.extension(), InputFormat::Json.extension()],
+    };
+    if path.is_dir() {
+        let mut files = fs::read_dir(path)
+            .context(format!("Failed to read directory: {}", path.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|ext| extensions.iter().any(|e| ext == *e)))
+            .collect::<Vec<PathBuf>>();
+        files.sort();
+        Ok(files)
+    } else if path.is_file() {
+        Ok(vec![path.to_path_buf()])
+    } else {
+        anyhow::bail!("Path not found: {}", path.display());
+    }
+}
+
+/// Flags given on the command line that have no effect in this run, because they only
+/// change how plans (or, for the model options, any Anthropic output) are generated
+fn ignored_flags(args: &Args) -> Vec<&'static str> {
+    let mut ignored = Vec::new();
+    if !args.plan {
+        let plan_only = [
+            ("--plan-sections", args.plan_sections.is_some()),
+            ("--concise-plan-below", args.concise_plan_below.is_some()),
+            ("--detailed-plan-from", args.detailed_plan_from.is_some()),
+            ("--diff", args.diff),
+            ("--keep-plan-versions", args.keep_plan_versions),
+        ];
+        ignored.extend(plan_only.into_iter().filter(|(_, given)| *given).map(|(flag, _)| flag));
+    }
+    if !args.plan && !args.describe {
+        let generation_only = [
+            ("--model", args.model.is_some()),
+            ("--model-map", args.model_map.is_some()),
+            ("--context-budget", args.context_budget.is_some()),
+            ("--max-description-length", args.max_description_length.is_some()),
+            ("--context-file", args.context_file.is_some()),
+            ("--context", args.context.is_some()),
+            ("--repo-path", args.repo_path.is_some()),
+            ("--deep-relations", args.deep_relations),
+            ("--yes", args.yes),
+        ];
+        ignored.extend(generation_only.into_iter().filter(|(_, given)| *given).map(|(flag, _)| flag));
+    }
+    ignored
+}
+
+/// The team to use when none is configured: the viewer's only team, or the one picked
+/// from their teams. Without a terminal to ask on, the teams are listed in the error.
+async fn discover_team(
+    linear_client: &linear::LinearClient,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Result<String> {
+    use std::io::IsTerminal;
+    
+    let teams = linear_client.fetch_viewer_teams(args.verbose).await?;
+    match teams.as_slice() {
+        [] => anyhow::bail!(
+            "No team configured and you are not a member of any Linear team. Use --team or set LINEAR_TEAM_NAME"
+        ),
+        [team] => {
+            reporter.info(format!("Using team '{}', the only team you belong to", team).blue());
+            Ok(team.clone())
+        }
+        _ if std::io::stdin().is_terminal() => ui::select_team(&teams),
+        _ => anyhow::bail!(
+            "No team configured and you belong to {} teams: {}. Use --team or set LINEAR_TEAM_NAME",
+            teams.len(),
+            teams.join(", ")
+        ),
+    }
+}
+
+/// Order in which the tickets are listed in the selection prompt, as indices into `tickets`
+fn selection_order(tickets: &[Ticket], sort: Option<SelectionSort>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..tickets.len()).collect();
+    let Some(sort) = sort else {
+        return order;
+    };
+    
+    // Stable sorts, so tickets that compare equal keep the fetch order
+    match sort {
+        SelectionSort::Priority => order.sort_by_key(|&i| {
+            let priority = tickets[i].priority;
+            (priority == 0, priority)
+        }),
+        SelectionSort::Title => order.sort_by_key(|&i| tickets[i].title.to_lowercase()),
+        SelectionSort::Id => order.sort_by_key(|&i| {
+            let (team, number) = tickets[i].id.rsplit_once('-').unwrap_or((&tickets[i].id, ""));
+            (team.to_string(), number.parse::<u64>().unwrap_or(u64::MAX))
+        }),
+        SelectionSort::Estimate => order.sort_by(|&a, &b| match (tickets[a].estimate, tickets[b].estimate) {
+            (Some(a), Some(b)) => b.total_cmp(&a),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        }),
+        SelectionSort::Updated => order.sort_by_key(|&i| std::cmp::Reverse(tickets[i].updated_at)),
+    }
+    order
+}
+
+/// Fetch the tickets listed in failures.json for --retry-failed. Tickets that can't be
+/// fetched are reported and stay listed.
+async fn fetch_failed_tickets(
+    linear_client: &linear::LinearClient,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Result<Vec<Ticket>> {
+    let path = report_dir(args).join(report::FAILURES_FILENAME);
+    let failed = report::FailedTickets::load(&path)?;
+    if failed.tickets.is_empty() {
+        reporter.info(format!("\n{}", format!("No failed tickets to retry in {}", path.display()).yellow()));
+        return Ok(Vec::new());
+    }
+    
+    let ids: Vec<&str> = failed.tickets.iter().map(|failed| failed.id.as_str()).collect();
+    reporter.info(format!("\n{}", format!("Retrying {} failed ticket(s): {}", ids.len(), ids.join(", ")).blue()));
+    
+    let mut tickets = Vec::new();
+    for id in ids {
+        match linear_client.fetch_ticket_by_id(id, args.verbose).await {
+            Ok(ticket) => tickets.push(ticket),
+            Err(e) => reporter.info(format!("⚠️ Could not fetch {}, leaving it listed: {:#}", id, e).yellow()),
+        }
+    }
+    Ok(tickets)
+}
+
+/// Fetch the tickets to choose from: the failed tickets of earlier runs (--retry-failed), a
+/// saved view (--view), unassigned tickets (--unassigned), the authenticated viewer (--me)
+/// or the configured user, offering close matches if the user is unknown
+async fn fetch_tickets(
+    linear_client: &linear::LinearClient,
+    args: &Args,
+    app_config: &mut config::AppConfig,
+    reporter: &ui::Reporter,
+) -> Result<Vec<Ticket>> {
+    if args.retry_failed {
+        return fetch_failed_tickets(linear_client, args, reporter).await;
+    }
+    
+    if let Some(view_id) = &args.view {
+        reporter.info(format!("\n{}", format!("Fetching tickets from view {}...", view_id).blue()));
+        return linear_client.fetch_view_tickets(view_id, args.verbose).await;
+    }
+    
+    if args.unassigned {
+        reporter.info(format!("\n{}", format!("Fetching unassigned tickets in {}...", app_config.linear_team_name).blue()));
+        return linear_client.fetch_unassigned_tickets(
+            &app_config.linear_team_name,
+            app_config.state_filter(),
+            args.verbose,
+        ).await;
+    }
+    
+    if args.me {
+        let viewer = linear_client.fetch_viewer(args.verbose).await
+            .context("Failed to resolve the authenticated Linear user")?;
+        reporter.info(format!("\n{}", format!("Fetching tickets assigned to {} (you)...", viewer.name).blue()));
+        app_config.linear_agent_user = viewer.name.clone();
+        
+        return linear_client.fetch_viewer_tickets(
+            &viewer,
+            &app_config.linear_team_name,
+            app_config.state_filter(),
+            args.verbose,
+        ).await;
+    }
+    
+    // Fetch each user's tickets in turn, keeping tickets grouped by assignee
+    let mut users = app_config.agent_users();
+    let mut tickets: Vec<Ticket> = Vec::new();
+    for user in users.iter_mut() {
+        reporter.info(format!("\n{}", format!("Fetching tickets assigned to {}...", user).blue()));
+        let user_tickets = match linear_client.fetch_user_tickets(
+            &app_config.linear_team_name,
+            user,
+            app_config.state_filter(),
+            args.verbose,
+        ).await {
+            Ok(user_tickets) => user_tickets,
+            Err(e) => {
+                // On an unknown user, let the user pick from the closest matches instead
+                let suggested = match e.downcast_ref::<linear::UserNotFound>() {
+                    Some(not_found) if !not_found.suggestions.is_empty() => {
+                        ui::select_suggested_user(not_found)?
+                    }
+                    _ => None,
+                };
+                
+                match suggested {
+                    Some(suggested) => {
+                        reporter.info(format!("\n{}", format!("Fetching tickets assigned to {}...", suggested).blue()));
+                        *user = suggested;
+                        linear_client.fetch_user_tickets(
+                            &app_config.linear_team_name,
+                            user,
+                            app_config.state_filter(),
+                            args.verbose,
+                        ).await?
+                    }
+                    None => return Err(e),
+                }
+            }
+        };
+        
+        tickets.extend(user_tickets);
+    }
+    app_config.linear_agent_user = users.join(", ");
+    
+    Ok(tickets)
+}
+
+/// Run an API connection test behind a spinner showing the time elapsed, so that a slow
+/// network is visible, then report how long it took. Errors are returned for
+/// `report_connection_failure`.
+async fn check_connection<T>(
+    service: &str,
+    test: impl std::future::Future<Output = Result<T>>,
+    reporter: &ui::Reporter,
+) -> Result<T> {
+    let spinner = reporter.spinner(format!("Testing {} API connection...", service));
+    let started = std::time::Instant::now();
+    let result = test.await;
+    spinner.finish_and_clear();
+    
+    if result.is_ok() {
+        let message = format!("✅ {} API connection successful ({:.1}s)", service, started.elapsed().as_secs_f64());
+        reporter.info(format!("\n{}", message.green()));
+    }
+    result
+}
+
+/// Report a failed connection test with what went wrong and what to do about it: an
+/// invalid key, a network, DNS, TLS or proxy problem, or an API outage
+fn report_connection_failure(service: &str, error: &anyhow::Error, reporter: &ui::Reporter) {
+    let advice = error
+        .downcast_ref::<linear::LinearError>()
+        .map(linear::LinearError::connection_advice)
+        .or_else(|| error.downcast_ref::<anthropic::AnthropicError>().map(anthropic::AnthropicError::connection_advice))
+        .unwrap_or("Please check your API key and try again.");
+    
+    reporter.error(format!("\n{}", format!("❌ Error: {} API connection failed: {:#}", service, error).red()));
+    reporter.error(format!("   {}", advice).yellow());
+}
+
+/// Drop every ticket whose identifier was already listed, keeping the first occurrence and
+/// the order. Returns the remaining tickets and how many were dropped.
+fn dedup_tickets(tickets: Vec<Ticket>) -> (Vec<Ticket>, usize) {
+    let total = tickets.len();
+    let mut seen = HashSet::new();
+    let unique: Vec<Ticket> = tickets
+        .into_iter()
+        .filter(|ticket| seen.insert(ticket.id.clone()))
+        .collect();
+    let duplicates = total - unique.len();
+    (unique, duplicates)
+}
+
+/// Look up the relations of each listed ticket and move the tickets with open blockers to
+/// the front, keeping the order otherwise (--sort-by-blocked). A ticket whose relations
+/// can't be fetched is listed as unblocked.
+async fn sort_by_blocked(
+    linear_client: &linear::LinearClient,
+    tickets: &mut [Ticket],
+    args: &Args,
+    reporter: &ui::Reporter,
+) {
+    reporter.info(format!("\n{}", "Checking tickets for blockers...".blue()));
+    let progress_bar = reporter.progress_bar(tickets.len());
+    let relations_only = linear::EnrichOptions { relations: true, ..linear::EnrichOptions::minimal() };
+    for ticket in tickets.iter_mut() {
+        match linear_client.enrich_ticket(ticket, args.verbose, relations_only, reporter).await {
+            Ok(enriched) => *ticket = enriched,
+            Err(e) => progress_bar.suspend(|| reporter.info(format!(
+                "⚠️ Could not check {} for blockers: {:#}", ticket.id, e
+            ).yellow())),
+        }
+        progress_bar.inc(1);
+    }
+    progress_bar.finish_and_clear();
+    
+    tickets.sort_by_key(|ticket| ticket.open_blockers().is_empty());
+}
+
+/// Sections to fetch when enriching a ticket: everything except those left out with
+/// --skip-comments, --skip-children or --skip-relations
+fn enrich_options(args: &Args) -> linear::EnrichOptions {
+    linear::EnrichOptions {
+        comments: !args.skip_comments,
+        children: !args.skip_children,
+        relations: !args.skip_relations,
+        relation_descriptions: args.deep_relations,
+        ..Default::default()
+    }
+}
+
+/// Enrich the tickets with up to ENRICH_CONCURRENCY of them in flight. Each ticket being
+/// enriched shows a spinner below the overall bar. Spinners clear themselves when their
+/// task ends, even if it fails or panics, so no bar is left behind. Enriched tickets keep
+/// the selection order; failures are returned with their ticket.
+async fn enrich_tickets(
+    linear_client: &linear::LinearClient,
+    tickets: &[Ticket],
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> (Vec<Ticket>, Vec<(Ticket, anyhow::Error)>) {
+    let multi_progress = reporter.multi_progress();
+    let overall = multi_progress.add(indicatif::ProgressBar::new(tickets.len() as u64));
+    let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(ENRICH_CONCURRENCY));
+    let options = enrich_options(args);
+    let verbose = args.verbose;
+    // Warnings from the tasks are printed above the bars instead of tearing them
+    let task_reporter = reporter.above(&overall);
+    
+    let mut tasks = tokio::task::JoinSet::new();
+    for (index, ticket) in tickets.iter().cloned().enumerate() {
+        let linear_client = linear_client.clone();
+        let semaphore = semaphore.clone();
+        let multi_progress = multi_progress.clone();
+        let overall = overall.clone();
+        let task_reporter = task_reporter.clone();
+        tasks.spawn(async move {
+            // The semaphore is never closed, so acquiring can't fail
+            let _permit = semaphore.acquire_owned().await;
+            let spinner = multi_progress.add(
+                indicatif::ProgressBar::new_spinner().with_finish(indicatif::ProgressFinish::AndClear),
+            );
+            spinner.set_message(format!("Enriching {}: {}", ticket.id, ticket.title));
+            spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+            
+            let result = linear_client.enrich_ticket(&ticket, verbose, options, &task_reporter).await;
+            overall.inc(1);
+            (index, result)
+        });
+    }
+    
+    let mut results: Vec<Option<Result<Ticket>>> = tickets.iter().map(|_| None).collect();
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((index, result)) = joined else {
+            // A panicked task has no index; its ticket is reported as failed below
+            continue;
+        };
+        if let Err(e) = &result {
+            multi_progress.suspend(|| {
+                reporter.error(format!("❌ Failed to enrich ticket {}: {}", tickets[index].id, e).red())
+            });
+        }
+        results[index] = Some(result);
+    }
+    overall.finish_and_clear();
+    reporter.info("All tickets enriched".green());
+    
+    let mut enriched_tickets = Vec::new();
+    let mut failures = Vec::new();
+    for (ticket, result) in tickets.iter().zip(results) {
+        match result.unwrap_or_else(|| Err(anyhow::anyhow!("Enrichment was interrupted"))) {
+            Ok(enriched) => enriched_tickets.push(enriched),
+            Err(e) => failures.push((ticket.clone(), e)),
+        }
+    }
+    
+    (enriched_tickets, failures)
+}
+
+/// Maximum characters of the title in ticket file and plan names
+const FILENAME_TITLE_CHARS: usize = 50;
+
+/// The name of a ticket's file and plan, `ticketId-title.md`, with every character of the
+/// title other than letters and digits replaced by `_` and the title cut to 50 characters
+fn safe_filename(ticket: &Ticket) -> String {
+    let safe_title: String = ticket
+        .title
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .take(FILENAME_TITLE_CHARS)
+        .collect();
+    format!("{}-{}.md", ticket.id, safe_title)
+}
+
+/// The file names given to the tickets of one run, so that two tickets never share one
+/// and a plan can't silently overwrite another
+#[derive(Default)]
+struct TicketFilenames {
+    /// Lowercased, since macOS and Windows file systems ignore case
+    taken: HashSet<String>,
+}
+
+impl TicketFilenames {
+    /// The ticket's `safe_filename`, with a numeric suffix (`-2`, `-3`, ...) when another
+    /// ticket of the run already has that name
+    fn claim(&mut self, ticket: &Ticket) -> String {
+        let filename = safe_filename(ticket);
+        let stem = filename.trim_end_matches(".md");
+        let mut candidate = filename.clone();
+        let mut suffix = 1;
+        while !self.taken.insert(candidate.to_lowercase()) {
+            suffix += 1;
+            candidate = format!("{}-{}.md", stem, suffix);
+        }
+        candidate
+    }
+}
+
+/// Path of a ticket's saved plan. Tickets whose names collided were saved with the
+/// suffixes of `TicketFilenames::claim`, so of the plans under the ticket's name, the one
+/// whose header has the ticket's ID and title is used; none or more than one such plan
+/// is an error rather than a guess.
+fn saved_plan_path(args: &Args, ticket: &Ticket) -> Result<PathBuf> {
+    let plan_path = grouped_plan_path(args, ticket, &safe_filename(ticket));
+    let stem = safe_filename(ticket).trim_end_matches(".md").to_string();
+    let mut candidates = Vec::new();
+    let mut path = plan_path.clone();
+    while path.exists() {
+        candidates.push(path);
+        path = plan_path.with_file_name(format!("{}-{}.md", stem, candidates.len() + 1));
+    }
+    if candidates.is_empty() {
+        return Ok(plan_path);
+    }
+    
+    let header = format!("# Implementation Plan: {}\n\n**Ticket ID:** {}\n", ticket.title, ticket.id);
+    let mut matching: Vec<PathBuf> = candidates
+        .into_iter()
+        .filter(|path| fs::read_to_string(path).is_ok_and(|plan| plan.starts_with(&header)))
+        .collect();
+    match matching.len() {
+        1 => Ok(matching.remove(0)),
+        0 => anyhow::bail!(
+            "None of the saved plans named after {} ({} and its numbered copies) is for \"{}\"",
+            ticket.id, plan_path.display(), ticket.title
+        ),
+        _ => anyhow::bail!(
+            "Several saved plans are for {} \"{}\": {}",
+            ticket.id,
+            ticket.title,
+            matching.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// Save a single enriched ticket as `filename` and, if requested, generate its
+/// implementation plan under the same name
+#[allow(clippy::too_many_arguments)]
+async fn process_ticket(
+    ticket: &Ticket,
+    filename: &str,
+    args: &Args,
+    anthropic_client: Option<&anthropic::AnthropicClient>,
+    app_config: &config::AppConfig,
+    position: usize,
+    total: usize,
+    reporter: &ui::Reporter,
+) -> Result<Option<String>> {
+    // Save the ticket information to the tickets directory, unless --no-save-tickets
+    if !args.no_save_tickets {
+        let ticket_file_path = args.tickets_dir.join(filename);
+        
+        // Create the file content with ticket information
+        let ticket_file_content = ticket.to_markdown(&app_config.blocker_keywords, app_config.limit_comments_chars, app_config.gfm_tables);
+        
+        reporter.info(format!("\n{}", format!("[{}/{}] Saving ticket information: {}", 
+                         position, total, ticket.title).blue()));
+        
+        write_file(&ticket_file_path, &ticket_file_content).await
+            .context("Failed to write ticket information to file")?;
+        
+        // Get absolute path
+        let abs_path = std::fs::canonicalize(&ticket_file_path)
+            .unwrap_or_else(|_| ticket_file_path.clone());
+        reporter.info(format!("✅ Ticket information saved to {}", abs_path.display()).green());
+    }
+    
+    // If an Anthropic client is available (--plan), also generate an implementation plan,
+    // or with --summary a brief summary that is returned to be saved with the others.
+    // With --describe, a plain-English explanation is saved instead.
+    if let Some(anthropic_client) = anthropic_client {
+        let ticket = &prepare_for_prompt(ticket, anthropic_client, app_config, args, reporter).await;
+        
+        if args.summary {
+            reporter.info(format!("\n{}", format!("[{}/{}] Generating summary for: {}", 
+                              position, total, ticket.title).blue()));
+            let summary = anthropic_client.generate_summary(ticket, app_config.model_for(ticket)).await?;
+            return Ok(Some(summary));
+        }
+        
+        if args.describe {
+            reporter.info(format!("\n{}", format!("[{}/{}] Generating description for: {}", 
+                              position, total, ticket.title).blue()));
+            let plan_file_path = plan_output_path(args, ticket, filename)?;
+            describe_ticket(anthropic_client, ticket, app_config, &plan_file_path, reporter).await?;
+            return Ok(None);
+        }
+        
+        reporter.info(format!("\n{}", format!("[{}/{}] Generating implementation plan for: {}", 
+                          position, total, ticket.title).blue()));
+        
+        // Path for the implementation plan
+        let plan_file_path = plan_output_path(args, ticket, filename)?;
+        
+        // Generate implementation plan
+        let implementation_plan = match generate_plan(anthropic_client, ticket, app_config.model_for(ticket), &plan_file_path, args, reporter).await? {
+            Some(plan) => plan,
+            None => {
+                reporter.info(format!("Skipped the plan for {}", ticket.id).yellow());
+                return Ok(None);
+            }
+        };
+        
+        // Write the implementation plan to the output directory
+        save_implementation_plan(ticket, &implementation_plan, &plan_file_path, args, reporter).await?;
+        
+        if args.create_subtasks {
+            if let Some(tasks) = subtasks_to_create(ticket, &implementation_plan, reporter)? {
+                create_subtasks(ticket, &tasks, app_config, args, reporter).await;
+            }
+        }
+    }
+    
+    Ok(None)
+}
+
+/// Whether to re-fetch a ticket file whose snapshot is `age_days` old before planning. With
+/// --yes there is no prompt and the ticket is always re-fetched.
+fn refetch_stale_ticket(ticket: &Ticket, age_days: i64, args: &Args, reporter: &ui::Reporter) -> bool {
+    if args.yes {
+        reporter.info(format!(
+            "⚠️ {} was last updated {} days ago; re-fetching it from Linear (--yes)", ticket.id, age_days
+        ).yellow());
+        return true;
+    }
+    ui::confirm_refetch(ticket, age_days).unwrap_or(false)
+}
+
+/// Show the estimated cost of generating plans for the tickets and, if it is above
+/// --confirm-above (and --yes wasn't given), ask whether to go ahead. Summaries and
+/// descriptions are cheap and never gated.
+fn confirm_plan_cost(
+    tickets: &[Ticket],
+    anthropic_client: &anthropic::AnthropicClient,
+    app_config: &config::AppConfig,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Result<bool> {
+    if args.summary || args.describe {
+        return Ok(true);
+    }
+    
+    let (estimate_text, needs_confirmation) = plan_cost_check(tickets, anthropic_client, app_config, args);
+    reporter.info(format!("Estimated Anthropic cost for {} plan(s): {}", tickets.len(), estimate_text));
+    if !needs_confirmation {
+        return Ok(true);
+    }
+    ui::confirm_plan_cost(&estimate_text, tickets.len())
+}
+
+/// The estimated cost of planning the tickets, as shown to the user, and whether it is
+/// above --confirm-above (and --yes wasn't given). An unknown cost is always above.
+fn plan_cost_check(
+    tickets: &[Ticket],
+    anthropic_client: &anthropic::AnthropicClient,
+    app_config: &config::AppConfig,
+    args: &Args,
+) -> (String, bool) {
+    // With --review every plan is sent back once more, roughly doubling the cost
+    let passes = if args.review { 2.0 } else { 1.0 };
+    let estimate: Option<f64> = tickets
+        .iter()
+        .map(|ticket| anthropic_client.estimate_plan_cost(ticket, app_config.model_for(ticket)))
+        .sum::<Option<f64>>()
+        .map(|cost| cost * passes);
+    
+    let estimate_text = match estimate {
+        Some(cost) => format!("up to ${:.2}", cost),
+        None => "an unknown amount (no price known for the model)".to_string(),
+    };
+    
+    let needs_confirmation = match app_config.confirm_above {
+        Some(threshold) if !args.yes => !estimate.is_some_and(|cost| cost <= threshold),
+        _ => false,
+    };
+    (estimate_text, needs_confirmation)
+}
+
+/// Check the number of tickets selected for plans against --max-tickets-per-run and, if
+/// it is above (and --yes wasn't given), ask whether to go ahead
+fn confirm_ticket_count(
+    selected: usize,
+    app_config: &config::AppConfig,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Result<bool> {
+    if !over_ticket_limit(selected, app_config, args) {
+        return Ok(true);
+    }
+    
+    let max = app_config.max_tickets_per_run;
+    reporter.info(format!("⚠️ {} tickets selected, the limit per run is {}", selected, max).yellow());
+    if args.yes {
+        return Ok(true);
+    }
+    ui::confirm_ticket_count(selected, max)
+}
+
+/// Whether more tickets were selected for plans than --max-tickets-per-run allows
+fn over_ticket_limit(selected: usize, app_config: &config::AppConfig, args: &Args) -> bool {
+    let max = app_config.max_tickets_per_run;
+    args.plan && max != 0 && selected > max
+}
+
+/// With --detect-duplicates, flag selected tickets that look alike and ask for each pair
+/// whether to plan both or only one. With --yes, or once one of a pair has been dropped,
+/// both are kept.
+fn skip_duplicates(
+    selected: Vec<Ticket>,
+    app_config: &config::AppConfig,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Result<Vec<Ticket>> {
+    let duplicates = duplicates::find_duplicates(&selected, app_config.duplicate_threshold);
+    if duplicates.is_empty() {
+        reporter.info("No likely duplicates among the selected tickets");
+        return Ok(selected);
+    }
+    
+    let mut dropped = HashSet::new();
+    for duplicate in duplicates {
+        if dropped.contains(&duplicate.first) || dropped.contains(&duplicate.second) {
+            continue;
+        }
+        let (first, second) = (&selected[duplicate.first], &selected[duplicate.second]);
+        reporter.info(format!(
+            "⚠️ {} and {} look like duplicates ({:.0}% similar)",
+            first.id, second.id, duplicate.similarity * 100.0
+        ).yellow());
+        if args.yes {
+            continue;
+        }
+        match ui::select_duplicate_to_plan(first, second)? {
+            ui::DuplicateChoice::Both => {}
+            ui::DuplicateChoice::OnlyFirst => { dropped.insert(duplicate.second); }
+            ui::DuplicateChoice::OnlySecond => { dropped.insert(duplicate.first); }
+        }
+    }
+    
+    Ok(selected.into_iter()
+        .enumerate()
+        .filter(|(i, _)| !dropped.contains(i))
+        .map(|(_, ticket)| ticket)
+        .collect())
+}
+
+/// Return the ticket prepared for the prompt: its comments (see `prepare_comments`) and, with
+/// --repo-path, an overview of the repository. A repository that can't be read is left out.
+async fn prepare_for_prompt(
+    ticket: &Ticket,
+    anthropic_client: &anthropic::AnthropicClient,
+    app_config: &config::AppConfig,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Ticket {
+    let mut prepared = prepare_comments(ticket, anthropic_client, app_config, args, reporter).await;
+    if let Some(root) = &app_config.repo_path {
+        match repo::summarize(root, &prepared, app_config.repo_summary_chars) {
+            Ok(summary) => prepared.repo_summary = Some(summary),
+            Err(e) => reporter.info(format!(
+                "⚠️ Could not summarize the repository for {}: {:#}", ticket.id, e
+            ).yellow()),
+        }
+    }
+    prepared
+}
+
+/// Return the ticket with its comments prepared for the prompt. With --comments-since, the
+/// older comments are left out. Then with --compact-comments, a long comment thread is
+/// condensed by a small model. Summaries are cached per ticket, latest comment and number
+/// of comments, so they are only regenerated once the thread changes. On failure the
+/// comments are kept as they are.
+async fn prepare_comments(
+    ticket: &Ticket,
+    anthropic_client: &anthropic::AnthropicClient,
+    app_config: &config::AppConfig,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Ticket {
+    let mut prepared = ticket.clone();
+    if let Some(since) = app_config.comments_since {
+        prepared.comments.retain(|c| c.created_at >= since);
+        let omitted = ticket.comments.len() - prepared.comments.len();
+        if omitted > 0 {
+            reporter.info(format!(
+                "Leaving out {} comment(s) on {} made before {}", omitted, ticket.id, since.format("%Y-%m-%d %H:%M")
+            ));
+        }
+    }
+    
+    if !args.compact_comments || prepared.comments.len() < COMPACT_COMMENTS_MIN {
+        return prepared;
+    }
+    
+    let latest = match prepared.comments.iter().max_by_key(|c| c.created_at) {
+        Some(latest) => latest,
+        None => return prepared,
+    };
+    let cache_name = format!("{}-{}-{}.md", ticket.id, latest.id, prepared.comments.len())
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect::<String>();
+    let cache_path = args.tickets_dir.join(COMMENT_SUMMARY_CACHE_DIR).join(cache_name);
+    
+    if let Ok(summary) = fs::read_to_string(&cache_path) {
+        reporter.info(format!("Using cached comment summary for {}", ticket.id));
+        prepared.comment_summary = Some(summary);
+        return prepared;
+    }
+    
+    reporter.info(format!("Condensing {} comments on {}...", prepared.comments.len(), ticket.id).blue());
+    match anthropic_client.summarize_comments(&prepared).await {
+        Ok(summary) => {
+            let cached = match cache_path.parent().map_or(Ok(()), fs::create_dir_all) {
+                Ok(()) => write_file(&cache_path, &summary).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = cached {
+                reporter.info(format!("⚠️ Could not cache the comment summary for {}: {}", ticket.id, e).yellow());
+            }
+            prepared.comment_summary = Some(summary);
+        }
+        Err(e) => reporter.info(format!(
+            "⚠️ Could not condense the comments on {}: {:#}. Sending them as they are", ticket.id, e
+        ).yellow()),
+    }
+    
+    prepared
+}
+
+/// Path of a ticket's plan in the output directory, inside a subdirectory per label,
+/// state, assignee or project with --group-output-by (created if needed)
+fn plan_output_path(args: &Args, ticket: &Ticket, filename: &str) -> Result<PathBuf> {
+    let path = grouped_plan_path(args, ticket, filename);
+    if let Some(dir) = path.parent().filter(|_| args.group_output_by.is_some()) {
+        std::fs::create_dir_all(dir)
+            .context(format!("Failed to create plan directory: {}", dir.display()))?;
+    }
+    Ok(path)
+}
+
+/// Where `plan_output_path` puts a ticket's plan, without creating anything
+fn grouped_plan_path(args: &Args, ticket: &Ticket, filename: &str) -> PathBuf {
+    let group = match args.group_output_by {
+        None => return args.output.join(filename),
+        Some(GroupBy::Label) => ticket.labels.first().map(String::as_str).unwrap_or_default(),
+        Some(GroupBy::State) => ticket.state.as_str(),
+        Some(GroupBy::Assignee) => ticket.assignee.as_deref().unwrap_or_default(),
+        Some(GroupBy::Project) => ticket.project.as_deref().unwrap_or_default(),
+    };
+    
+    let dir_name = if group.trim().is_empty() {
+        match args.group_output_by {
+            Some(GroupBy::Label) => "_unlabeled".to_string(),
+            Some(GroupBy::Assignee) => "_unassigned".to_string(),
+            Some(GroupBy::Project) => "_no_project".to_string(),
+            _ => "_unknown".to_string(),
+        }
+    } else {
+        group.trim().chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+            .collect()
+    };
+    
+    args.output.join(dir_name).join(filename)
+}
+
+/// Generate a plan for the ticket, or with --append-to-existing-plan revise the plan
+/// already saved at `plan_file_path`. With --review the result gets a self-review pass.
+/// Returns `None` if the user skipped the ticket during --interactive-refine.
+async fn generate_plan(
+    anthropic_client: &anthropic::AnthropicClient,
+    ticket: &Ticket,
+    model: &str,
+    plan_file_path: &Path,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Result<Option<String>> {
+    if args.append_to_existing_plan && plan_file_path.exists() {
+        let existing = fs::read_to_string(plan_file_path)
+            .context(format!("Failed to read existing plan: {}", plan_file_path.display()))?;
+        
+        // Only the plan itself is revised; the metadata header is regenerated on save
+        let existing_plan = existing
+            .split_once(PLAN_HEADER_SEPARATOR)
+            .map_or(existing.as_str(), |(_, plan)| plan);
+        
+        if args.save_prompts {
+            let prompt = anthropic_client.revision_prompt(ticket, existing_plan);
+            save_prompt(ticket, model, anthropic_client.plan_instructions(), &[models::AnthropicMessage::user(&prompt)], plan_file_path).await?;
+        }
+        let revised_plan = anthropic_client
+            .revise_implementation_plan(ticket, existing_plan, model)
+            .await?;
+        return review_plan(anthropic_client, ticket, revised_plan, model, args).await.map(Some);
+    }
+    
+    if args.save_prompts {
+        let prompt = anthropic_client.implementation_plan_prompt(ticket);
+        save_prompt(ticket, model, anthropic_client.plan_instructions(), &[models::AnthropicMessage::user(&prompt)], plan_file_path).await?;
+    }
+    let implementation_plan = anthropic_client.generate_implementation_plan(ticket, model).await?;
+    let implementation_plan = review_plan(anthropic_client, ticket, implementation_plan, model, args).await?;
+    
+    if !args.interactive_refine {
+        return Ok(Some(implementation_plan));
+    }
+    refine_plan(anthropic_client, ticket, model, implementation_plan, plan_file_path, args, reporter).await
+}
+
+/// Save the prompt of a plan request as .prompt.md next to the plan (--save-prompts). It is
+/// written before the request is sent, so it is kept even when the request fails. The
+/// --review pass, which resends the prompt with the draft, is not saved separately.
+/// Refinements save the whole conversation, one section per message.
+async fn save_prompt(
+    ticket: &Ticket,
+    model: &str,
+    system: &str,
+    messages: &[models::AnthropicMessage],
+    plan_file_path: &Path,
+) -> Result<()> {
+    let mut content = format!(
+        "# Prompt for {}: {}\n\n**Model:** {}\n\n## System\n\n{}\n",
+        ticket.id,
+        ticket.title,
+        model,
+        system
+    );
+    for message in messages {
+        let heading = if message.role == "assistant" { "Assistant" } else { "Message" };
+        for block in &message.content {
+            content.push_str(&format!("\n## {}\n\n{}\n", heading, block.text));
+        }
+    }
+    let prompt_path = plan_file_path.with_extension("prompt.md");
+    write_file(&prompt_path, content).await
+        .context(format!("Failed to write prompt to {}", prompt_path.display()))
+}
+
+/// Show the plan and let the user accept it, regenerate it or skip the ticket
+/// (--interactive-refine). An extra instruction continues the conversation: the plan is
+/// sent back as the model's reply, followed by the instruction, so later attempts keep
+/// every earlier plan and instruction in context. A plain regenerate asks for another
+/// take on the latest reply.
+async fn refine_plan(
+    anthropic_client: &anthropic::AnthropicClient,
+    ticket: &Ticket,
+    model: &str,
+    mut implementation_plan: String,
+    plan_file_path: &Path,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Result<Option<String>> {
+    let mut turns: Vec<anthropic::PlanTurn> = Vec::new();
+    let mut attempt = 1;
+    
+    loop {
+        match ui::review_generated_plan(ticket, &implementation_plan, attempt)? {
+            ui::PlanAction::Accept => return Ok(Some(implementation_plan)),
+            ui::PlanAction::Skip => return Ok(None),
+            ui::PlanAction::Regenerate => {}
+            ui::PlanAction::AddInstruction(instruction) => {
+                if !instruction.trim().is_empty() {
+                    turns.push(anthropic::PlanTurn {
+                        plan: implementation_plan.clone(),
+                        instruction: instruction.trim().to_string(),
+                    });
+                }
+            }
+        }
+        
+        attempt += 1;
+        reporter.info(format!("Regenerating the plan for {}...", ticket.id).blue());
+        if args.save_prompts {
+            let messages = anthropic_client.refinement_messages(ticket, &turns);
+            save_prompt(ticket, model, anthropic_client.plan_instructions(), &messages, plan_file_path).await?;
+        }
+        let draft_plan = anthropic_client
+            .refine_implementation_plan(ticket, &turns, model)
+            .await?;
+        implementation_plan = review_plan(anthropic_client, ticket, draft_plan, model, args).await?;
+    }
+}
+
+/// Improve a draft plan with the --review pass, or return it unchanged without --review
+async fn review_plan(
+    anthropic_client: &anthropic::AnthropicClient,
+    ticket: &Ticket,
+    draft_plan: String,
+    model: &str,
+    args: &Args,
+) -> Result<String> {
+    if !args.review {
+        return Ok(draft_plan);
+    }
+    
+    anthropic_client
+        .review_implementation_plan(ticket, &draft_plan, model)
+        .await
+        .context("Self-review of the implementation plan failed")
+}
+
+/// Remove the --single-file left by a previous run, unless --append was given, so that
+/// the file only holds this run's plans
+fn start_single_file(args: &Args) -> Result<()> {
+    let Some(single_file) = &args.single_file else {
+        return Ok(());
+    };
+    if args.append {
+        return Ok(());
+    }
+    
+    let path = args.output.join(single_file);
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).context(format!("Failed to start {} afresh", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Add a plan, with its ticket header, to the end of the --single-file
+async fn append_to_single_file(path: &Path, plan_file_content: &str) -> Result<()> {
+    let mut content = match fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).context(format!("Failed to read {}", path.display())),
+    };
+    if !content.trim().is_empty() {
+        content.truncate(content.trim_end().len());
+        content.push_str(PLAN_HEADER_SEPARATOR);
+    }
+    content.push_str(plan_file_content.trim_end());
+    content.push('\n');
+    
+    write_file(path, content).await.context(format!("Failed to add the plan to {}", path.display()))
+}
+
+/// Write an implementation plan file for a ticket. With `diff`, an existing plan is
+/// compared against the new one and only overwritten after confirmation. With
+/// --single-file, the plan is added to that file instead.
+async fn save_implementation_plan(
+    ticket: &Ticket,
+    implementation_plan: &str,
+    plan_file_path: &Path,
+    args: &Args,
+    reporter: &ui::Reporter,
+) -> Result<()> {
+    // Create the file content with implementation plan
+    let plan_file_content = format!(
+        "# Implementation Plan: {}\n\n\
+         **Ticket ID:** {}\n\
+         **State:** {}\n\
+         **Priority:** {}\n\
+         **Estimate:** {}\n\
+         **URL:** {}{}{}",
+        ticket.title,
+        ticket.id,
+        ticket.state,
+        ticket.priority,
+        models::format_estimate(ticket.estimate),
+        ticket.url,
+        PLAN_HEADER_SEPARATOR,
+        implementation_plan
+    );
+    
+    if let Some(single_file) = &args.single_file {
+        let path = args.output.join(single_file);
+        append_to_single_file(&path, &plan_file_content).await?;
+        reporter.info(format!("✅ Implementation plan added to {}", path.display()).green());
+        if args.checklist_output {
+            save_checklist(ticket, implementation_plan, plan_file_path, reporter).await?;
+        }
+        return Ok(());
+    }
+    
+    // With --diff the plan is only rewritten when it changed and the overwrite is
+    // confirmed. The other outputs follow the plan that is kept, and are then only
+    // written when they are missing.
+    let existing;
+    let mut plan = implementation_plan;
+    let mut write_plan = true;
+    if args.diff && plan_file_path.exists() {
+        existing = fs::read_to_string(plan_file_path)
+            .context(format!("Failed to read existing plan: {}", plan_file_path.display()))?;
+        
+        if existing == plan_file_content {
+            reporter.info("No changes compared to the saved plan".green());
+            write_plan = false;
+        } else {
+            ui::display_plan_diff(&existing, &plan_file_content);
+            if !ui::confirm_overwrite(plan_file_path)? {
+                reporter.info(format!("Kept existing plan at {}", plan_file_path.display()).yellow());
+                write_plan = false;
+                plan = existing
+                    .split_once(PLAN_HEADER_SEPARATOR)
+                    .map_or(existing.as_str(), |(_, plan)| plan);
+            }
+        }
+    }
+    let write_output = |path: &Path| write_plan || !path.exists();
+    
+    if write_plan {
+        // Keep the previous version next to the new one when requested
+        if args.keep_plan_versions && plan_file_path.exists() {
+            let version_path = versioned_plan_path(plan_file_path);
+            fs::copy(plan_file_path, &version_path)
+                .context(format!("Failed to keep previous plan as {}", version_path.display()))?;
+            reporter.info(format!("Previous plan kept as {}", version_path.display()));
+        }
+        
+        write_file(plan_file_path, plan_file_content).await
+            .context("Failed to write implementation plan to file")?;
+        
+        // Get absolute path
+        let abs_path = std::fs::canonicalize(plan_file_path)
+            .unwrap_or_else(|_| plan_file_path.to_path_buf());
+        reporter.info(format!("✅ Implementation plan saved to {}", abs_path.display()).green());
+    }
+    
+    let html_path = plan_file_path.with_extension("html");
+    if args.output_format == OutputFormat::Html && write_output(&html_path) {
+        write_file(&html_path, export::format_plan_html(ticket, plan)).await
+            .context("Failed to write HTML plan to file")?;
+        reporter.info(format!("✅ HTML plan saved to {}", html_path.display()).green());
+    }
+    
+    let org_path = plan_file_path.with_extension("org");
+    if args.output_format == OutputFormat::Org && write_output(&org_path) {
+        write_file(&org_path, export::format_plan_org(ticket, plan)).await
+            .context("Failed to write Org plan to file")?;
+        reporter.info(format!("✅ Org plan saved to {}", org_path.display()).green());
+    }
+    
+    if args.checklist_output && write_output(&plan_file_path.with_extension("tasks.md")) {
+        save_checklist(ticket, plan, plan_file_path, reporter).await?;
+    }
+    
+    Ok(())
+}
+
+/// Save the plan's checkbox steps to a .tasks.md file next to the plan (--checklist-output)
+async fn save_checklist(
+    ticket: &Ticket,
+    implementation_plan: &str,
+    plan_file_path: &Path,
+    reporter: &ui::Reporter,
+) -> Result<()> {
+    let tasks = anthropic::parse_checklist(implementation_plan);
+    if tasks.is_empty() {
+        reporter.info(format!("⚠️ No checklist steps found in the plan for {}", ticket.id).yellow());
+        return Ok(());
+    }
+    
+    let mut content = format!("# Tasks: {}\n\n**Ticket ID:** {}\n\n", ticket.title, ticket.id);
+    for task in &tasks {
+        content.push_str(&format!("- [ ] {}\n", task));
+    }
+    
+    let tasks_path = plan_file_path.with_extension("tasks.md");
+    write_file(&tasks_path, content).await
+        .context("Failed to write task checklist to file")?;
+    reporter.info(format!("✅ {} task(s) saved to {}", tasks.len(), tasks_path.display()).green());
+    
+    Ok(())
+}
+
+/// The plan's checklist steps to create as Linear subtasks of the ticket, once confirmed
+/// (--create-subtasks). None when the plan has no steps or creating them was declined.
+fn subtasks_to_create(ticket: &Ticket, implementation_plan: &str, reporter: &ui::Reporter) -> Result<Option<Vec<String>>> {
+    let tasks = anthropic::parse_checklist(implementation_plan);
+    if tasks.is_empty() {
+        return Ok(None);
+    }
+    
+    if !ui::confirm_subtasks(ticket, &tasks)? {
+        reporter.info(format!("Skipped creating subtasks for {}", ticket.id).yellow());
+        return Ok(None);
+    }
+    
+    Ok(Some(tasks))
+}
+
+/// Create the confirmed steps as Linear subtasks of the ticket. A failed create doesn't stop
+/// the remaining ones, and is only a warning: the ticket's plan is already saved.
+async fn create_subtasks(
+    ticket: &Ticket,
+    tasks: &[String],
+    app_config: &config::AppConfig,
+    args: &Args,
+    reporter: &ui::Reporter,
+) {
+    let linear_client = linear::LinearClient::from_config(app_config);
+    let parent = match linear_client.fetch_subtask_parent(&ticket.id, args.verbose).await {
+        Ok(parent) => parent,
+        Err(e) => {
+            reporter.info(format!("⚠️ Could not create subtasks under {}: {:#}", ticket.id, e).yellow());
+            return;
+        }
+    };
+    
+    let mut created = Vec::new();
+    let mut failed = 0;
+    for task in tasks {
+        match linear_client.create_subtask(&parent, task, args.verbose).await {
+            Ok(identifier) => {
+                reporter.info(format!("✅ Created {}: {}", identifier, task).green());
+                created.push(identifier);
+            }
+            Err(e) => {
+                reporter.info(format!("⚠️ Could not create subtask '{}': {:#}", task, e).yellow());
+                failed += 1;
+            }
+        }
+    }
+    
+    if !created.is_empty() {
+        reporter.info(format!("Created {} subtask(s) under {}: {}", created.len(), ticket.id, created.join(", ")));
+    }
+    if failed > 0 {
+        reporter.info(format!(
+            "⚠️ {} of {} subtask(s) could not be created under {}", failed, tasks.len(), ticket.id
+        ).yellow());
+    }
+}
+
+/// Explain the ticket in plain English and save it next to where its plan would go, as
+/// <plan name>.summary.md (--describe)
+async fn describe_ticket(
+    anthropic_client: &anthropic::AnthropicClient,
+    ticket: &Ticket,
+    app_config: &config::AppConfig,
+    plan_file_path: &Path,
+    reporter: &ui::Reporter,
+) -> Result<()> {
+    let description = anthropic_client
+        .describe_ticket(ticket, app_config.model_for(ticket))
+        .await?;
+    
+    let content = format!(
+        "# {}: {}\n\n**State:** {} | **URL:** {}\n\n{}\n",
+        ticket.id,
+        ticket.title,
+        ticket.state,
+        ticket.url,
+        description.trim()
+    );
+    let summary_path = plan_file_path.with_extension("summary.md");
+    write_file(&summary_path, content).await
+        .context("Failed to write ticket description to file")?;
+    
+    let abs_path = std::fs::canonicalize(&summary_path)
+        .unwrap_or_else(|_| summary_path.clone());
+    reporter.info(format!("✅ Description saved to {}", abs_path.display()).green());
+    
+    Ok(())
+}
+
+/// Write the --summary results of a run to summaries.md in the output directory
+async fn save_summaries(summaries: &[(Ticket, String)], output_dir: &Path, reporter: &ui::Reporter) -> Result<()> {
+    let mut content = String::from("# Ticket Summaries\n\n");
+    for (ticket, summary) in summaries {
+        content.push_str(&format!(
+            "## {}: {}\n\n**State:** {} | **URL:** {}\n\n{}\n\n",
+            ticket.id,
+            ticket.title,
+            ticket.state,
+            ticket.url,
+            summary.trim()
+        ));
+    }
+    
+    let summaries_path = output_dir.join("summaries.md");
+    write_file(&summaries_path, content).await
+        .context("Failed to write summaries to file")?;
+    
+    let abs_path = std::fs::canonicalize(&summaries_path)
+        .unwrap_or_else(|_| summaries_path.clone());
+    reporter.info(format!("✅ Summaries saved to {}", abs_path.display()).green());
+    
+    Ok(())
+}
+
+/// Write an output file atomically: the contents go to a temporary file next to `path`,
+/// which is then renamed over it, so an interrupted run never leaves a truncated file.
+/// Transient failures are retried a few times; others are reported straight away.
+async fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    
+    let mut attempt = 1;
+    loop {
+        let result = fs::write(&temp_path, contents.as_ref())
+            .and_then(|_| fs::rename(&temp_path, path));
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < WRITE_ATTEMPTS && is_transient_write_error(&e) => {
+                attempt += 1;
+                tokio::time::sleep(WRITE_RETRY_DELAY).await;
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&temp_path);
+                let message = if attempt > 1 {
+                    format!("{}: {} (after {} attempts)", path.display(), e, attempt)
+                } else {
+                    format!("{}: {}", path.display(), e)
+                };
+                return Err(std::io::Error::new(e.kind(), message));
+            }
+        }
+    }
+}
+
+/// Whether a failed write may succeed when tried again: an interrupted or timed out
+/// write, or on Windows a file held open by another program such as a virus scanner
+fn is_transient_write_error(e: &std::io::Error) -> bool {
+    match e.kind() {
+        std::io::ErrorKind::Interrupted
+        | std::io::ErrorKind::WouldBlock
+        | std::io::ErrorKind::TimedOut => true,
+        std::io::ErrorKind::PermissionDenied => cfg!(windows),
+        _ => false,
+    }
+}
+
+/// Path for a timestamped copy of a plan, e.g. plans/ABC-123-Title.20240101-120000.md
+fn versioned_plan_path(plan_file_path: &Path) -> PathBuf {
+    let stem = plan_file_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    plan_file_path.with_file_name(format!("{}.{}.md", stem, timestamp))
+}
+
+/// Parse every saved ticket file at `path` and report successes, failures and warnings.
+/// Returns `false` if any file failed to parse.
+fn validate_ticket_files(path: &Path, format: Option<InputFormat>, reporter: &ui::Reporter) -> Result<bool> {
+    let files = collect_ticket_files(path, format)?;
+
+    reporter.info(format!("Validating {} ticket file(s) in {}", files.len(), path.display()).blue());
+
+    let mut valid = 0;
+    let mut invalid = 0;
+    let mut with_warnings = 0;
+
+    for file in &files {
+        match load_ticket_file(file, format) {
+            Ok((ticket, _)) => {
+                let warnings = ticket.validation_warnings();
+                if warnings.is_empty() {
+                    reporter.info(format!("{} {}", "✅".green(), file.display()));
+                } else {
+                    with_warnings += 1;
+                    reporter.info(format!("{} {}", "⚠️".yellow(), file.display()));
+                    for warning in warnings {
+                        reporter.info(format!("   {}", warning.yellow()));
+                    }
+                }
+                valid += 1;
+            }
+            Err(e) => {
+                invalid += 1;
+                reporter.error(format!("{} {}", "❌".red(), file.display()));
+                reporter.error(format!("   {}", format!("{:#}", e).red()));
+            }
+        }
+    }
+
+    reporter.summary(format!(
+        "\n{} parsed, {} with warnings, {} failed",
+        valid.to_string().green(),
+        with_warnings.to_string().yellow(),
+        invalid.to_string().red()
+    ));
+
+    Ok(invalid == 0)
+}
+
+/// Check for updates by comparing the current version with the latest release on GitHub
+async fn check_for_updates() -> Result<()> {
+    println!("{}", "Checking for updates...".blue());
+    
+    // Get current version from Cargo.toml
+    let current_version = env!("CARGO_PKG_VERSION");
+    println!("Current version: {}", current_version);
+    
+    // GitHub API endpoint for the latest release
+    let github_url = "https://api.github.com/repos/oorestisime/linear-agent/releases/latest";
+    
+    // Create a client with proper user-agent header (required by GitHub API)
+    let client = reqwest::Client::builder()
+        .user_agent("linear-agent-updater")
+        .build()?;
+    
+    // Make the request to the GitHub API
+    let response = client.get(github_url).send().await;
+    
+    match response {
+        Ok(response) => {
+            if response.status().is_success() {
+                // Parse the JSON response
+                let release: serde_json::Value = response.json().await?;
+                
+                // Extract the latest version (without 'v' prefix)
+                if let Some(tag_name) = release["tag_name"].as_str() {
+                    let latest_version = tag_name.trim_start_matches('v');
+                    println!("Latest version: {}", latest_version);
+                    
+                    // Compare versions
+                    if latest_version != current_version {
+                        println!("{}", "A new version is available!".green());
+                        println!("To update, run the following command:");
+                        
+                        println!("\nDownload and install:");
+                        println!("curl -L https://github.com/oorestisime/linear-agent/releases/latest/download/linear-agent -o linear-agent && chmod +x linear-agent");
+                        
+                        println!("\nMove to your local bin directory:");
+                        println!("mkdir -p ~/.local/bin && mv linear-agent ~/.local/bin/");
+                        
+                        println!("\nOr download directly from: {}", release["html_url"].as_str().unwrap_or("https://github.com/oorestisime/linear-agent/releases"));
+                    } else {
+                        println!("{}", "You are using the latest version!".green());
+                    }
+                } else {
+                    println!("{}", "Failed to extract version from the latest release.".red());
+                }
+            } else {
+                println!("{}", format!("Failed to check for updates: HTTP {}", response.status()).red());
+            }
+        }
+        Err(err) => {
+            println!("{}", format!("Failed to check for updates: {}", err).red());
+        }
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::test_ticket as ticket;
+
+    #[test]
+    fn overlapping_sources_keep_the_first_occurrence_of_each_ticket() {
+        let first_source = vec![ticket("T-1", "From the user"), ticket("T-2", "Only once")];
+        let second_source = vec![ticket("T-3", "From the view"), ticket("T-1", "From the view")];
+
+        let (tickets, duplicates) = dedup_tickets(first_source.into_iter().chain(second_source).collect());
+
+        let listed: Vec<(&str, &str)> = tickets.iter().map(|t| (t.id.as_str(), t.title.as_str())).collect();
+        assert_eq!(listed, [("T-1", "From the user"), ("T-2", "Only once"), ("T-3", "From the view")]);
+        assert_eq!(duplicates, 1);
+    }
+
+    #[test]
+    fn tickets_whose_names_collide_get_a_numeric_suffix() {
+        let long_title = "Migrate the billing service to the new payments provider";
+        let tickets = [
+            ticket("ABC-1", &format!("{} (part 1)", long_title)),
+            ticket("ABC-1", &format!("{} (part 2)", long_title)),
+            ticket("abc-1", long_title),
+            ticket("ABC-2", "Café menu: naïve résumé parsing ✓"),
+        ];
+
+        let mut filenames = TicketFilenames::default();
+        let names: Vec<String> = tickets.iter().map(|ticket| filenames.claim(ticket)).collect();
+        assert_eq!(
+            names,
+            [
+                "ABC-1-Migrate_the_billing_service_to_the_new_payments_pr.md",
+                "ABC-1-Migrate_the_billing_service_to_the_new_payments_pr-2.md",
+                "abc-1-Migrate_the_billing_service_to_the_new_payments_pr-3.md",
+                "ABC-2-Café_menu__naïve_résumé_parsing__.md",
+            ]
+        );
+    }
+
+    #[test]
+    fn distinct_titles_with_the_same_filename_get_a_numeric_suffix() {
+        let long_title = "Migrate the billing service to the new payments provider";
+        let tickets = [
+            // Differ only in characters replaced by _
+            ticket("ABC-3", "Import a/b"),
+            ticket("ABC-3", "Import a:b"),
+            ticket("ABC-3", "Import a?b"),
+            // Differ only past the truncated part of the title
+            ticket("ABC-4", &format!("{} for invoices", long_title)),
+            ticket("ABC-4", &format!("{} for refunds", long_title)),
+            ticket("ABC-4", &format!("{} for payouts", long_title)),
+        ];
+
+        let mut filenames = TicketFilenames::default();
+        let names: Vec<String> = tickets.iter().map(|ticket| filenames.claim(ticket)).collect();
+        assert_eq!(
+            names,
+            [
+                "ABC-3-Import_a_b.md",
+                "ABC-3-Import_a_b-2.md",
+                "ABC-3-Import_a_b-3.md",
+                "ABC-4-Migrate_the_billing_service_to_the_new_payments_pr.md",
+                "ABC-4-Migrate_the_billing_service_to_the_new_payments_pr-2.md",
+                "ABC-4-Migrate_the_billing_service_to_the_new_payments_pr-3.md",
+            ]
+        );
+    }
+
+    #[test]
+    fn ticket_sources_are_mutually_exclusive() {
+        let sources: [&[&str]; 8] = [
+            &["--user", "Jane Smith"],
+            &["--me"],
+            &["--view", "view-1"],
+            &["--unassigned"],
+            &["--team-members"],
+            &["--ticket", "tickets/"],
+            &["--ticket-id", "ABC-1"],
+            &["--retry-failed"],
+        ];
+        for (i, first) in sources.iter().enumerate() {
+            assert!(Args::try_parse_from(["linear-agent"].iter().chain(first.iter())).is_ok());
+            for second in &sources[i + 1..] {
+                let error = Args::try_parse_from(["linear-agent"].iter().chain(first.iter()).chain(second.iter()))
+                    .expect_err("conflicting sources should be rejected");
+                assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict, "{:?} {:?}", first, second);
+            }
+        }
+    }
+
+    #[test]
+    fn stale_tickets_are_refetched_without_asking_with_yes() {
+        let args = Args::parse_from(["linear-agent", "--ticket", "tickets/", "--plan", "--yes"]);
+        assert!(refetch_stale_ticket(&ticket("ABC-1", "Old"), 45, &args, &ui::Reporter::new(true)));
+    }
+
+    #[test]
+    fn comments_since_needs_plan_or_describe() {
+        for mode in ["--plan", "--describe"] {
+            assert!(Args::try_parse_from(["linear-agent", "--me", mode, "--comments-since", "2w"]).is_ok(), "{}", mode);
+        }
+        let error = Args::try_parse_from(["linear-agent", "--me", "--comments-since", "2w"])
+            .expect_err("--comments-since without --plan or --describe should be rejected");
+        assert_eq!(error.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+    }
+
+    #[test]
+    fn plan_only_flags_are_reported_without_plan() {
+        let args = Args::parse_from(["linear-agent", "--me", "--diff", "--model", "claude-x", "--concise-plan-below", "1"]);
+        assert_eq!(ignored_flags(&args), ["--concise-plan-below", "--diff", "--model"]);
+
+        let args = Args::parse_from(["linear-agent", "--me", "--describe", "--diff", "--model", "claude-x"]);
+        assert_eq!(ignored_flags(&args), ["--diff"]);
+
+        let args = Args::parse_from(["linear-agent", "--me", "--plan", "--diff", "--model", "claude-x"]);
+        assert!(ignored_flags(&args).is_empty());
+
+        // --yes also answers the stale ticket prompt of --describe
+        let args = Args::parse_from(["linear-agent", "--ticket", "tickets/", "--describe", "--yes"]);
+        assert!(ignored_flags(&args).is_empty());
+        let args = Args::parse_from(["linear-agent", "--me", "--yes"]);
+        assert_eq!(ignored_flags(&args), ["--yes"]);
+    }
+
+    #[test]
+    fn single_file_is_markdown_only() {
+        assert!(Args::try_parse_from(["linear-agent", "--me", "--plan", "--single-file", "plans.md"]).is_ok());
+        for format in ["html", "org"] {
+            let error = Args::try_parse_from([
+                "linear-agent", "--me", "--plan", "--single-file", "plans.md", "--output-format", format,
+            ])
+            .expect_err("--single-file only writes markdown");
+            assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict, "{}", format);
+        }
+    }
+
+    #[tokio::test]
+    async fn single_file_plans_are_appended_in_order() {
+        let path = std::env::temp_dir().join(format!("linear-agent-single-file-{}.md", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        append_to_single_file(&path, "# Implementation Plan: First\n\nPlan one\n").await.unwrap();
+        append_to_single_file(&path, "# Implementation Plan: Second\n\nPlan two").await.unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            content,
+            "# Implementation Plan: First\n\nPlan one\n\n---\n\n# Implementation Plan: Second\n\nPlan two\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn unchanged_plans_still_get_their_missing_side_outputs() {
+        let dir = std::env::temp_dir().join(format!("linear-agent-diff-outputs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let plan_path = dir.join("T-1-Plan.md");
+        let ticket = ticket("T-1", "Plan");
+        let args = Args::parse_from(["linear-agent", "--me", "--plan", "--diff", "--output-format", "html"]);
+        let reporter = ui::Reporter::new(true);
+
+        save_implementation_plan(&ticket, "- [ ] Do it", &plan_path, &args, &reporter).await.unwrap();
+        fs::remove_file(plan_path.with_extension("html")).unwrap();
+        save_implementation_plan(&ticket, "- [ ] Do it", &plan_path, &args, &reporter).await.unwrap();
+
+        assert!(plan_path.with_extension("html").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn verify_finds_the_plan_of_a_ticket_whose_name_collided() {
+        let dir = std::env::temp_dir().join(format!("linear-agent-verify-plan-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dir_arg = dir.to_string_lossy().into_owned();
+        let args = Args::parse_from(["linear-agent", "--me", "--plan", "--output", &dir_arg]);
+        let reporter = ui::Reporter::new(true);
+        let tickets = [ticket("ABC-3", "Import a/b"), ticket("ABC-3", "Import a:b")];
+
+        // The other ticket's plan under the same name is never used
+        let mut filenames = TicketFilenames::default();
+        let first = dir.join(filenames.claim(&tickets[0]));
+        save_implementation_plan(&tickets[0], "Plan one", &first, &args, &reporter).await.unwrap();
+        assert!(saved_plan_path(&args, &tickets[1]).is_err());
+
+        let second = dir.join(filenames.claim(&tickets[1]));
+        save_implementation_plan(&tickets[1], "Plan two", &second, &args, &reporter).await.unwrap();
+        assert_eq!(saved_plan_path(&args, &tickets[0]).unwrap(), first);
+        assert_eq!(saved_plan_path(&args, &tickets[1]).unwrap(), second);
+
+        // A second copy of the same ticket's plan leaves no way to choose
+        fs::copy(&second, dir.join("ABC-3-Import_a_b-3.md")).unwrap();
+        assert!(saved_plan_path(&args, &tickets[1]).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn plans_are_grouped_by_project() {
+        let dir = std::env::temp_dir().join(format!("linear-agent-group-project-{}", std::process::id()));
+        let dir_arg = dir.to_string_lossy().into_owned();
+        let args = Args::parse_from(["linear-agent", "--me", "--plan", "--output", &dir_arg, "--group-output-by", "project"]);
+        let mut in_project = ticket("T-1", "Planned");
+        in_project.project = Some("Q3 Billing".to_string());
+
+        assert_eq!(plan_output_path(&args, &in_project, "T-1.md").unwrap(), dir.join("Q3_Billing").join("T-1.md"));
+        assert!(dir.join("Q3_Billing").is_dir());
+
+        // Looking up a plan creates no directory
+        let loose = ticket("T-2", "Loose");
+        assert_eq!(saved_plan_path(&args, &loose).unwrap(), dir.join("_no_project").join(safe_filename(&loose)));
+        assert!(!dir.join("_no_project").exists());
+        assert_eq!(plan_output_path(&args, &loose, "T-2.md").unwrap(), dir.join("_no_project").join("T-2.md"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_ticket_files_load_and_plan_without_the_markdown_parser() {
+        let dir = std::env::temp_dir().join(format!("linear-agent-input-format-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut saved = ticket("T-7", "Export | round-trip");
+        saved.description = Some("## Steps\n\n**Updated:** is not a field here".to_string());
+        saved.labels = vec!["Backend".to_string()];
+        let path = dir.join("T-7.json");
+        fs::write(&path, serde_json::to_string_pretty(&saved).unwrap()).unwrap();
+
+        assert_eq!(collect_ticket_files(&dir, None).unwrap(), std::slice::from_ref(&path));
+        let (loaded, has_timestamps) = load_ticket_file(&path, None).unwrap();
+        assert!(has_timestamps);
+        assert_eq!(loaded.title, saved.title);
+        assert_eq!(loaded.description, saved.description);
+        assert_eq!(loaded.updated_at, saved.updated_at);
+
+        // The plan request is built from the JSON ticket without calling the API
+        let prompt = anthropic::AnthropicClient::new("test-key").implementation_plan_prompt(&loaded);
+        assert!(prompt.contains("Export | round-trip"));
+        assert!(prompt.contains("Backend"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn directory_batches_are_checked_against_the_ticket_limit_and_cost() {
+        let dir = std::env::temp_dir().join(format!("linear-agent-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for id in ["T-1", "T-2"] {
+            fs::write(dir.join(format!("{}.json", id)), serde_json::to_string(&ticket(id, "Batch")).unwrap()).unwrap();
+        }
+        let tickets: Vec<Ticket> = collect_ticket_files(&dir, None)
+            .unwrap()
+            .iter()
+            .map(|path| load_ticket_file(path, None).unwrap().0)
+            .collect();
+        let app_config = config::AppConfig {
+            max_tickets_per_run: 1,
+            confirm_above: Some(0.0),
+            ..config::AppConfig::default()
+        };
+        let client = anthropic::AnthropicClient::new("test-key");
+        let dir_arg = dir.to_string_lossy().into_owned();
+
+        let args = Args::try_parse_from(["linear-agent", "--ticket", &dir_arg, "--plan"]).unwrap();
+        assert_eq!(tickets.len(), 2);
+        assert!(over_ticket_limit(tickets.len(), &app_config, &args));
+        assert!(plan_cost_check(&tickets, &client, &app_config, &args).1);
+
+        // --yes skips the cost prompt; the limit is still reported
+        let args = Args::try_parse_from(["linear-agent", "--ticket", &dir_arg, "--plan", "--yes"]).unwrap();
+        assert!(over_ticket_limit(tickets.len(), &app_config, &args));
+        assert!(!plan_cost_check(&tickets, &client, &app_config, &args).1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,11 +1,17 @@
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Written in saved ticket files for a ticket without a description (null in Linear), as
+/// opposed to one whose description is empty
+pub const NO_DESCRIPTION: &str = "_No description provided_";
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Ticket {
     pub id: String,
     pub title: String,
-    pub description: String,
+    /// `None` when the ticket has no description at all
+    pub description: Option<String>,
     pub priority: i32,
     pub estimate: Option<f64>,
     pub labels: Vec<String>,
@@ -14,13 +20,38 @@ pub struct Ticket {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub assignee: Option<String>,
+    /// Name of the Linear project the ticket belongs to
+    #[serde(default)]
+    pub project: Option<String>,
     pub comments: Vec<Comment>,
     pub parent: Option<RelatedTicket>,
     pub children: Vec<RelatedTicket>,
     pub related_tickets: Vec<RelatedTicket>,
+    /// Names of the users subscribed to the ticket
+    pub subscribers: Vec<String>,
+    /// Who the ticket was assigned to before its latest reassignment
+    pub previous_assignee: Option<String>,
+    /// Condensed comment thread used in prompts instead of the comments (--compact-comments)
+    pub comment_summary: Option<String>,
+    /// Git branch name Linear gives the ticket; the branch may not have been created yet
+    #[serde(default)]
+    pub branch_name: Option<String>,
+    /// GitHub pull requests and GitLab merge requests attached to the ticket
+    #[serde(default)]
+    pub pull_requests: Vec<PullRequestLink>,
+    /// Overview of the local repository used in prompts (--repo-path)
+    #[serde(skip)]
+    pub repo_summary: Option<String>,
+    /// Whether the ticket has a parent, when the query that listed it said so; enrichment
+    /// skips the parent query when it has none
+    #[serde(skip)]
+    pub has_parent: Option<bool>,
+    /// Whether the ticket has child tickets, when the query that listed it said so
+    #[serde(skip)]
+    pub has_children: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Comment {
     pub id: String,
     pub body: String,
@@ -28,19 +59,113 @@ pub struct Comment {
     pub user: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RelatedTicket {
     pub id: String,
     pub title: String,
     pub state: String,
     pub assignee: Option<String>,
+    /// How this ticket relates to the one it was fetched for, from that ticket's side:
+    /// "blocks", "blocked_by", "duplicate", "duplicated_by", "related" or "similar" for
+    /// relations, "parent" or "child" for the hierarchy
+    #[serde(default)]
+    pub relation_type: String,
+    /// Whether the ticket is completed or canceled; only known for relations
+    #[serde(default)]
+    pub resolved: bool,
+    /// Story points; only fetched for child tickets
+    #[serde(default)]
+    pub estimate: Option<f64>,
+    /// Only fetched for relations with --deep-relations
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// A pull or merge request attached to a ticket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PullRequestLink {
+    pub title: String,
+    pub url: String,
+    /// e.g. "open", "draft", "merged" or "closed", when the integration reports it
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+impl PullRequestLink {
+    /// The pull request as a markdown list item, e.g. "- [Add caching](https://...) (merged)",
+    /// as read back by `parse`
+    pub fn to_markdown(&self) -> String {
+        match &self.status {
+            Some(status) => format!("- [{}]({}) ({})", self.title, self.url, status),
+            None => format!("- [{}]({})", self.title, self.url),
+        }
+    }
+
+    /// Parse a list item written by `to_markdown`
+    fn parse(line: &str) -> Option<Self> {
+        let (title, rest) = line.strip_prefix("- [")?.rsplit_once("](")?;
+        let (url, status) = rest.split_once(')')?;
+        let status = status.trim().strip_prefix('(').and_then(|status| status.strip_suffix(')'));
+        Some(Self {
+            title: title.to_string(),
+            url: url.to_string(),
+            status: status.map(str::to_string),
+        })
+    }
+}
+
+/// Relation type of a ticket that blocks the one it was fetched for
+pub const RELATION_BLOCKED_BY: &str = "blocked_by";
+
+/// Relation types and how they are written in ticket files and prompts, e.g. "Blocked by: ABC-12"
+const RELATION_LABELS: [(&str, &str); 6] = [
+    ("blocks", "Blocks"),
+    (RELATION_BLOCKED_BY, "Blocked by"),
+    ("duplicate", "Duplicate of"),
+    ("duplicated_by", "Duplicated by"),
+    ("similar", "Similar to"),
+    ("related", "Related to"),
+];
+
+impl Comment {
+    /// The comment's body, shortened to `max_chars` characters if a limit is given
+    pub fn limited_body(&self, max_chars: Option<usize>) -> String {
+        match max_chars {
+            Some(max_chars) => truncate_comment(&self.body, max_chars),
+            None => self.body.clone(),
+        }
+    }
+}
+
+/// Keep the first `max_chars` characters (not bytes) of a comment, followed by a marker
+/// with the number of characters cut
+pub fn truncate_comment(body: &str, max_chars: usize) -> String {
+    match body.char_indices().nth(max_chars) {
+        Some((byte_index, _)) => format!(
+            "{} [comment truncated: {} characters omitted]",
+            body[..byte_index].trim_end(),
+            body[byte_index..].chars().count()
+        ),
+        None => body.to_string(),
+    }
+}
+
+impl RelatedTicket {
+    /// How the ticket relates to the one it was fetched for, e.g. "Blocked by"
+    pub fn relation_label(&self) -> &'static str {
+        RELATION_LABELS
+            .iter()
+            .find(|(relation_type, _)| *relation_type == self.relation_type)
+            .map_or("Related to", |(_, label)| label)
+    }
 }
 
 impl Ticket {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: String,
         title: String,
-        description: String,
+        description: Option<String>,
         priority: i32,
         estimate: Option<f64>,
         labels: Vec<String>,
@@ -62,11 +187,278 @@ impl Ticket {
             created_at,
             updated_at,
             assignee,
+            project: None,
             comments: Vec::new(),
             parent: None,
             children: Vec::new(),
             related_tickets: Vec::new(),
+            subscribers: Vec::new(),
+            previous_assignee: None,
+            comment_summary: None,
+            branch_name: None,
+            pull_requests: Vec::new(),
+            repo_summary: None,
+            has_parent: None,
+            has_children: None,
+        }
+    }
+
+    /// Comments mentioning any of the blocker keywords (case-insensitive)
+    pub fn potential_blockers(&self, keywords: &[String]) -> Vec<&Comment> {
+        let keywords: Vec<String> = keywords.iter().map(|k| k.to_lowercase()).collect();
+        self.comments
+            .iter()
+            .filter(|comment| {
+                let body = comment.body.to_lowercase();
+                keywords.iter().any(|keyword| !keyword.is_empty() && body.contains(keyword))
+            })
+            .collect()
+    }
+
+    /// Potential blockers as a markdown list of one-line comment excerpts, if there are any
+    pub fn format_potential_blockers(&self, keywords: &[String]) -> Option<String> {
+        let blockers = self.potential_blockers(keywords);
+        if blockers.is_empty() {
+            return None;
+        }
+
+        let lines: Vec<String> = blockers
+            .iter()
+            .map(|comment| {
+                let body = comment.body.split_whitespace().collect::<Vec<_>>().join(" ");
+                let excerpt = if body.chars().count() > BLOCKER_EXCERPT_CHARS {
+                    format!("{}…", body.chars().take(BLOCKER_EXCERPT_CHARS).collect::<String>())
+                } else {
+                    body
+                };
+                format!(
+                    "- {} ({}): {}",
+                    comment.user.as_deref().unwrap_or("Unknown"),
+                    comment.created_at.format("%Y-%m-%d"),
+                    excerpt
+                )
+            })
+            .collect();
+
+        Some(lines.join("\n"))
+    }
+
+    /// Render the ticket as the markdown file format read back by `from_markdown`.
+    /// Comments matching `blocker_keywords` are also listed in a Potential Blockers section.
+    /// Comments longer than `limit_comments_chars` are shortened. With `gfm_tables`, the
+    /// metadata is a two-column table instead of bold-prefixed lines.
+    pub fn to_markdown(&self, blocker_keywords: &[String], limit_comments_chars: Option<usize>, gfm_tables: bool) -> String {
+        let labels_str = if self.labels.is_empty() {
+            "None".to_string()
+        } else {
+            self.labels.join(", ")
+        };
+
+        let stakeholders_str = self.stakeholders();
+
+        // Only present when a comment matched; it repeats comments, so it isn't parsed back
+        let blockers_section = self
+            .format_potential_blockers(blocker_keywords)
+            .map(|blockers| format!("## Potential Blockers\n\n{}\n\n", blockers))
+            .unwrap_or_default();
+
+        let related_tickets_str = if self.related_tickets.is_empty() {
+            "None".to_string()
+        } else {
+            self.related_tickets
+                .iter()
+                .map(|rt| format!("- {}: {} {} (State: {})", rt.relation_label(), rt.id, rt.title, rt.state))
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+
+        let children_str = if self.children.is_empty() {
+            "None".to_string()
+        } else {
+            let mut children = self.children
+                .iter()
+                .map(|child| format!("- {} (State: {})", child.title, child.state))
+                .collect::<Vec<String>>()
+                .join("\n");
+            // Not parsed back, like the blockers section
+            if let Some(rollup) = self.children_estimate() {
+                children.push_str(&format!("\n\n{}", rollup));
+            }
+            children
+        };
+
+        // Only present when the ticket has any
+        let pull_requests_section = if self.pull_requests.is_empty() {
+            String::new()
+        } else {
+            let pull_requests: Vec<String> = self.pull_requests.iter().map(PullRequestLink::to_markdown).collect();
+            format!("## Pull Requests\n\n{}\n\n", pull_requests.join("\n"))
+        };
+
+        let comments_str = if self.comments.is_empty() {
+            "None".to_string()
+        } else {
+            self.comments
+                .iter()
+                .map(|comment| {
+                    let user = comment.user.as_deref().unwrap_or("Unknown");
+                    format!(
+                        "- {} ({}): {}",
+                        user,
+                        comment.created_at.format("%Y-%m-%d"),
+                        comment.limited_body(limit_comments_chars)
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+
+        let metadata = [
+            ("Ticket ID", self.id.clone()),
+            ("State", self.state.clone()),
+            ("Priority", self.priority.to_string()),
+            ("Estimate", format_estimate(self.estimate)),
+            ("URL", self.url.clone()),
+            ("Created", self.created_at.to_rfc3339()),
+            ("Updated", self.updated_at.to_rfc3339()),
+            ("Labels", labels_str),
+            ("Project", self.project.clone().unwrap_or_else(|| "None".to_string())),
+            ("Stakeholders", stakeholders_str),
+            ("Branch", self.branch_name.clone().unwrap_or_else(|| "None".to_string())),
+        ];
+        let metadata_str: String = if gfm_tables {
+            let rows: String = metadata
+                .iter()
+                .map(|(field, value)| format!("| {} | {} |\n", field, value.replace('|', "\\|")))
+                .collect();
+            format!("| Field | Value |\n| --- | --- |\n{}", rows)
+        } else {
+            metadata.iter().map(|(field, value)| format!("**{}:** {}\n", field, value)).collect()
+        };
+
+        format!(
+            "# Ticket: {}\n\n\
+             {}\n\
+             ## Description\n\n{}\n\n\
+             ## Comments\n\n{}\n\n\
+             {}\
+             ## Related Tickets\n\n{}\n\n\
+             ## Child Tickets\n\n{}\n\n\
+             {}",
+            self.title,
+            metadata_str,
+            self.description.as_deref().unwrap_or(NO_DESCRIPTION),
+            comments_str,
+            blockers_section,
+            related_tickets_str,
+            children_str,
+            pull_requests_section
+        )
+    }
+
+    /// The sum of the child tickets' estimates, which often says more about a parent's
+    /// scope than its own (often empty) estimate, e.g. "Children sum to 13 points (2 of 5
+    /// children not estimated)". `None` when no child is estimated.
+    pub fn children_estimate(&self) -> Option<String> {
+        let estimates: Vec<f64> = self.children.iter().filter_map(|child| child.estimate).collect();
+        if estimates.is_empty() {
+            return None;
+        }
+
+        let mut rollup = format!("Children sum to {} points", format_estimate(Some(estimates.iter().sum())));
+        let unestimated = self.children.len() - estimates.len();
+        if unestimated > 0 {
+            rollup.push_str(&format!(" ({} of {} children not estimated)", unestimated, self.children.len()));
+        }
+        Some(rollup)
+    }
+
+    /// Related tickets blocking this one that aren't completed or canceled yet
+    pub fn open_blockers(&self) -> Vec<&RelatedTicket> {
+        self.related_tickets
+            .iter()
+            .filter(|related| related.relation_type == RELATION_BLOCKED_BY && !related.resolved)
+            .collect()
+    }
+
+    /// The description, or an empty string if the ticket has none
+    pub fn description_text(&self) -> &str {
+        self.description.as_deref().unwrap_or_default()
+    }
+
+    /// Subscribers and previous assignee as a single line, e.g.
+    /// "Jane, Bob (previous assignee: Alice)", or "None"
+    pub fn stakeholders(&self) -> String {
+        let subscribers = if self.subscribers.is_empty() {
+            "None".to_string()
+        } else {
+            self.subscribers.join(", ")
+        };
+
+        match &self.previous_assignee {
+            Some(previous) => format!("{} (previous assignee: {})", subscribers, previous),
+            None => subscribers,
+        }
+    }
+
+    /// Extract the acceptance criteria section from the description, if there is one.
+    /// Recognizes headings like "## Acceptance Criteria", "AC:" and "Definition of Done",
+    /// as markdown headings, bold text or plain lines ending in a colon.
+    pub fn acceptance_criteria(&self) -> Option<String> {
+        let mut lines = self.description_text().lines();
+        let mut criteria = Vec::new();
+
+        // Find the header; text after "AC:" on the same line belongs to the section
+        for line in lines.by_ref() {
+            if let Some(rest) = acceptance_criteria_header(line) {
+                if !rest.is_empty() {
+                    criteria.push(rest.to_string());
+                }
+                break;
+            }
+        }
+
+        // The section runs until the next heading
+        for line in lines {
+            let trimmed = line.trim();
+            let is_heading = trimmed.starts_with('#')
+                || (trimmed.starts_with("**") && trimmed.ends_with("**") && trimmed.len() > 4);
+            if is_heading {
+                break;
+            }
+            criteria.push(line.to_string());
+        }
+
+        let criteria = criteria.join("\n").trim().to_string();
+        if criteria.is_empty() {
+            None
+        } else {
+            Some(criteria)
+        }
+    }
+
+    /// Headings of description sections that were left empty, as happens when a ticket is
+    /// created from a template and parts of it aren't filled in: nothing but blank lines,
+    /// HTML comments or placeholders like "TBD" before the next heading. A heading directly
+    /// followed by a subheading is a group, not an empty section.
+    pub fn empty_template_sections(&self) -> Vec<String> {
+        let lines: Vec<&str> = self.description_text().lines().collect();
+        let headings: Vec<(usize, usize, &str)> = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| description_heading(line).map(|(level, text)| (i, level, text)))
+            .collect();
+
+        let mut empty = Vec::new();
+        for (n, &(start, level, text)) in headings.iter().enumerate() {
+            let next = headings.get(n + 1);
+            let end = next.map_or(lines.len(), |&(i, _, _)| i);
+            let has_subsections = next.is_some_and(|&(_, next_level, _)| next_level > level);
+            if !text.is_empty() && !has_subsections && is_placeholder_text(&lines[start + 1..end].join("\n")) {
+                empty.push(text.to_string());
+            }
         }
+        empty
     }
 
     /// Parse a ticket from a markdown file that was saved by this tool
@@ -84,7 +476,11 @@ impl Ticket {
         let mut priority = 0;
         let mut estimate = None;
         let mut labels = Vec::new();
+        let mut subscribers = Vec::new();
+        let mut previous_assignee = None;
         let mut url = String::new();
+        let mut created_at = None;
+        let mut updated_at = None;
         let mut state = String::new();
         let mut in_description_section = false;
         let mut comment_section_start = false;
@@ -94,9 +490,27 @@ impl Ticket {
         let mut comment_date = None;
         let mut related_tickets = Vec::new();
         let mut children = Vec::new();
+        let mut in_related_section = false;
+        let mut project = None;
+        let mut branch_name = None;
+        let mut pull_requests = Vec::new();
+        let mut in_pull_requests_section = false;
+
+        // Metadata comes before the first section, as bold-prefixed lines or a table whose
+        // rows are read as the equivalent lines
+        let mut in_metadata = true;
+        let lines: Vec<std::borrow::Cow<str>> = lines
+            .map(|line| {
+                in_metadata &= !line.starts_with("## ");
+                match in_metadata.then(|| parse_metadata_row(line)).flatten() {
+                    Some(row) => std::borrow::Cow::Owned(row),
+                    None => std::borrow::Cow::Borrowed(line),
+                }
+            })
+            .collect();
 
         // Parse the rest of the file
-        for line in lines {
+        for line in lines.iter().map(|line| line.as_ref()) {
             // Parse metadata
             if line.starts_with("**Ticket ID:**") {
                 id = line.trim_start_matches("**Ticket ID:**").trim().to_string();
@@ -106,17 +520,33 @@ impl Ticket {
                 let priority_str = line.trim_start_matches("**Priority:**").trim();
                 priority = priority_str.parse().unwrap_or(0);
             } else if line.starts_with("**Estimate:**") {
-                let estimate_str = line.trim_start_matches("**Estimate:**").trim();
-                if !estimate_str.contains("Not estimated") {
-                    estimate = estimate_str.parse().ok();
-                }
+                estimate = parse_estimate(line.trim_start_matches("**Estimate:**"))?;
             } else if line.starts_with("**URL:**") {
                 url = line.trim_start_matches("**URL:**").trim().to_string();
+            } else if line.starts_with("**Created:**") {
+                created_at = Some(parse_timestamp(line.trim_start_matches("**Created:**"))?);
+            } else if line.starts_with("**Updated:**") {
+                updated_at = Some(parse_timestamp(line.trim_start_matches("**Updated:**"))?);
             } else if line.starts_with("**Labels:**") {
                 let labels_str = line.trim_start_matches("**Labels:**").trim();
                 if labels_str != "None" {
                     labels = labels_str.split(", ").map(|s| s.to_string()).collect();
                 }
+            } else if line.starts_with("**Stakeholders:**") {
+                let mut stakeholders_str = line.trim_start_matches("**Stakeholders:**").trim();
+                if let Some((rest, previous)) = stakeholders_str.split_once(" (previous assignee: ") {
+                    previous_assignee = Some(previous.trim_end_matches(')').to_string());
+                    stakeholders_str = rest;
+                }
+                if stakeholders_str != "None" {
+                    subscribers = stakeholders_str.split(", ").map(|s| s.to_string()).collect();
+                }
+            } else if line.starts_with("**Project:**") {
+                let name = line.trim_start_matches("**Project:**").trim();
+                project = (name != "None").then(|| name.to_string());
+            } else if line.starts_with("**Branch:**") {
+                let branch = line.trim_start_matches("**Branch:**").trim();
+                branch_name = (branch != "None").then(|| branch.to_string());
             }
             // Handle description section
             else if line.contains("## Description") {
@@ -126,16 +556,23 @@ impl Ticket {
                 in_description_section = false;
                 comment_section_start = true;
                 continue;
-            } else if line.contains("## Related Tickets") {
+            } else if line.contains("## Potential Blockers") || line.contains("## Related Tickets") {
                 comment_section_start = false;
+                in_related_section = line.contains("## Related Tickets");
                 continue;
             } else if line.contains("## Child Tickets") {
+                in_related_section = false;
                 continue;
+            } else if line.contains("## Pull Requests") {
+                in_pull_requests_section = true;
+                continue;
+            } else if in_pull_requests_section {
+                pull_requests.extend(PullRequestLink::parse(line));
             }
             // Process description content
             else if in_description_section {
                 if !description.is_empty() {
-                    description.push_str("\n");
+                    description.push('\n');
                 }
                 description.push_str(line);
             }
@@ -146,18 +583,13 @@ impl Ticket {
                 && line.contains("): ")
             {
                 // If we were already building a comment, save it
-                if !current_comment.is_empty() && comment_user.is_some() && comment_date.is_some() {
+                if let (false, Some(_), Some(date)) =
+                    (current_comment.is_empty(), &comment_user, comment_date)
+                {
                     comments.push(Comment {
                         id: format!("from_file_{}", comments.len()),
                         body: current_comment.trim().to_string(),
-                        created_at: chrono::DateTime::parse_from_str(
-                            comment_date.unwrap(),
-                            "%Y-%m-%d",
-                        )
-                        .unwrap_or_else(|_| {
-                            chrono::DateTime::parse_from_rfc3339("2021-01-01T00:00:00Z").unwrap()
-                        })
-                        .with_timezone(&chrono::Utc),
+                        created_at: parse_comment_date(date),
                         user: comment_user.clone(),
                     });
                     current_comment = String::new();
@@ -181,46 +613,48 @@ impl Ticket {
             }
             // Process related tickets or children
             else if line.starts_with("- ") && line.contains("(State: ") {
-                // This is either a related ticket or a child ticket
+                if in_related_section {
+                    if let Some(related) = parse_related_ticket(line, related_tickets.len()) {
+                        related_tickets.push(related);
+                    }
+                    continue;
+                }
+
                 let parts: Vec<&str> = line.trim_start_matches("- ").split(" (State: ").collect();
                 if parts.len() == 2 {
                     let ticket_title = parts[0].to_string();
                     let ticket_state = parts[1].trim_end_matches(")").to_string();
-
-                    // Based on the current section, add to related or children
-                    if line.contains("## Related Tickets") {
-                        related_tickets.push(RelatedTicket {
-                            id: format!("placeholder_{}", related_tickets.len()),
-                            title: ticket_title,
-                            state: ticket_state,
-                            assignee: None,
-                        });
-                    } else {
-                        children.push(RelatedTicket {
-                            id: format!("placeholder_{}", children.len()),
-                            title: ticket_title,
-                            state: ticket_state,
-                            assignee: None,
-                        });
-                    }
+                    children.push(RelatedTicket {
+                        id: format!("placeholder_{}", children.len()),
+                        title: ticket_title,
+                        state: ticket_state,
+                        assignee: None,
+                        relation_type: "child".to_string(),
+                        resolved: false,
+                        estimate: None,
+                        description: None,
+                    });
                 }
             }
         }
 
         // Add the last comment if any
-        if !current_comment.is_empty() && comment_user.is_some() && comment_date.is_some() {
+        if let (false, Some(_), Some(date)) =
+            (current_comment.is_empty(), &comment_user, comment_date)
+        {
             comments.push(Comment {
                 id: format!("from_file_{}", comments.len()),
                 body: current_comment.trim().to_string(),
-                created_at: chrono::DateTime::parse_from_str(comment_date.unwrap(), "%Y-%m-%d")
-                    .unwrap_or_else(|_| {
-                        chrono::DateTime::parse_from_rfc3339("2021-01-01T00:00:00Z").unwrap()
-                    })
-                    .with_timezone(&chrono::Utc),
+                created_at: parse_comment_date(date),
                 user: comment_user.clone(),
             });
         }
 
+        // Drop the blank line separating the description from the next section, and read
+        // the placeholder back as a missing description rather than as its text
+        let description = description.strip_suffix('\n').map(str::to_string).unwrap_or(description);
+        let description = (description.trim() != NO_DESCRIPTION).then_some(description);
+
         // Create the ticket with parsed information
         Ok(Self {
             id,
@@ -231,16 +665,204 @@ impl Ticket {
             labels,
             url,
             state,
-            // Use current time for created/updated
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
+            // Files saved before timestamps were recorded fall back to the current time
+            created_at: created_at.unwrap_or_else(chrono::Utc::now),
+            updated_at: updated_at.unwrap_or_else(chrono::Utc::now),
             assignee: None,
+            project,
             comments,
             parent: None,
             children,
             related_tickets,
+            subscribers,
+            previous_assignee,
+            comment_summary: None,
+            branch_name,
+            pull_requests,
+            repo_summary: None,
+            has_parent: None,
+            has_children: None,
         })
     }
+
+    /// Report fields that are missing or empty, which would degrade a generated plan
+    pub fn validation_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if self.id.is_empty() {
+            warnings.push("Missing ticket ID".to_string());
+        }
+        if self.title.is_empty() {
+            warnings.push("Missing title".to_string());
+        }
+        if self.state.is_empty() {
+            warnings.push("Missing state".to_string());
+        }
+        if self.url.is_empty() {
+            warnings.push("Missing URL".to_string());
+        }
+        match &self.description {
+            None => warnings.push("No description".to_string()),
+            Some(description) if description.trim().is_empty() => {
+                warnings.push("Empty description".to_string())
+            }
+            Some(_) => {}
+        }
+
+        warnings
+    }
+}
+
+/// Parse a related ticket line of a saved ticket file, "- Blocked by: ABC-12 Title (State: Todo)".
+/// Files saved before relation types were recorded have "- Title (State: Todo)" lines,
+/// which are read as plain relations without an ID.
+fn parse_related_ticket(line: &str, index: usize) -> Option<RelatedTicket> {
+    let (entry, state) = line.trim_start_matches("- ").rsplit_once(" (State: ")?;
+    let state = state.trim_end_matches(')').to_string();
+
+    let typed = RELATION_LABELS.iter().find_map(|(relation_type, label)| {
+        let rest = entry.strip_prefix(label)?.strip_prefix(": ")?;
+        let (id, title) = rest.split_once(' ').unwrap_or((rest, ""));
+        Some((relation_type.to_string(), id.to_string(), title.to_string()))
+    });
+    let (relation_type, id, title) = typed.unwrap_or_else(|| {
+        ("related".to_string(), format!("placeholder_{}", index), entry.to_string())
+    });
+
+    Some(RelatedTicket {
+        id,
+        title,
+        state,
+        assignee: None,
+        relation_type,
+        resolved: false,
+        estimate: None,
+        description: None,
+    })
+}
+
+/// If `line` is an acceptance criteria header, return any text following it on the same line
+fn acceptance_criteria_header(line: &str) -> Option<&str> {
+    const HEADERS: [&str; 3] = ["acceptance criteria", "definition of done", "ac"];
+
+    let text = line.trim().trim_start_matches('#').trim().trim_start_matches("**");
+    let lower = text.to_lowercase();
+    let header = HEADERS.iter().find(|header| lower.starts_with(*header))?;
+
+    // The header must be the whole line or be followed by a colon, so "Account" isn't "AC"
+    let rest = text.get(header.len()..)?.trim_start_matches("**").trim_start();
+    let is_heading = line.trim_start().starts_with('#');
+    match rest.strip_prefix(':') {
+        Some(rest) => Some(rest.trim_start_matches("**").trim()),
+        None if rest.is_empty() && (is_heading || *header != "ac") => Some(""),
+        None => None,
+    }
+}
+
+/// Level and text of a description heading: `#` to `######` markdown headings, or a line
+/// that is entirely bold (treated as the deepest level)
+fn description_heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim();
+    if trimmed.starts_with('#') {
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        return Some((level, trimmed[level..].trim().trim_end_matches(':')));
+    }
+    if trimmed.starts_with("**") && trimmed.ends_with("**") && trimmed.len() > 4 {
+        return Some((7, trimmed.trim_matches('*').trim().trim_end_matches(':')));
+    }
+    None
+}
+
+/// Whether a description section has no real content: only blank lines, HTML comments,
+/// empty list items or checkboxes, and placeholders like "TBD" or "_No response_"
+fn is_placeholder_text(text: &str) -> bool {
+    const PLACEHOLDERS: [&str; 6] = ["n/a", "tbd", "todo", "...", "…", "_no response_"];
+
+    // Template hints are often HTML comments spanning several lines
+    let mut remaining = text.to_string();
+    while let Some(start) = remaining.find("<!--") {
+        let end = remaining[start..].find("-->").map_or(remaining.len(), |end| start + end + 3);
+        remaining.replace_range(start..end, "");
+    }
+
+    remaining.lines().all(|line| {
+        let content = line
+            .trim()
+            .trim_start_matches(['-', '*', '+'])
+            .trim_start()
+            .trim_start_matches("[ ]")
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .trim_start_matches('.')
+            .trim();
+        content.is_empty() || PLACEHOLDERS.contains(&content.to_lowercase().as_str())
+    })
+}
+
+/// Read a row of the metadata table written with `gfm_tables` (`| State | Todo |`) as the
+/// bold-prefixed line it stands for (`**State:** Todo`). `None` for the header row, the
+/// separator row and anything that isn't a table row.
+fn parse_metadata_row(line: &str) -> Option<String> {
+    let row = line.trim().strip_prefix('|')?.strip_suffix('|')?;
+    // Values escape their pipes as \|
+    let (field, value) = row.replace("\\|", "\0").split_once('|').map(|(field, value)| {
+        (field.trim().to_string(), value.trim().replace('\0', "|"))
+    })?;
+    if field == "Field" || field.starts_with("---") {
+        return None;
+    }
+    Some(format!("**{}:** {}", field, value))
+}
+
+/// Maximum characters of a comment shown in the Potential Blockers list
+const BLOCKER_EXCERPT_CHARS: usize = 200;
+
+/// Parse an RFC 3339 timestamp as written by `to_markdown`
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, anyhow::Error> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| anyhow::anyhow!("Invalid timestamp '{}'", value))
+}
+
+/// Parse the date of a saved comment (`2024-05-01`) as midnight UTC. Unreadable dates
+/// fall back to a fixed date in the past rather than failing the whole file.
+fn parse_comment_date(date: &str) -> DateTime<Utc> {
+    chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .unwrap_or_else(|_| chrono::NaiveDate::from_ymd_opt(2021, 1, 1).unwrap_or_default())
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc()
+}
+
+/// Format an estimate for display and saved files: whole numbers without a trailing
+/// ".0" (`3`), fractions with at most two decimals (`2.5`), or "Not estimated"
+pub fn format_estimate(estimate: Option<f64>) -> String {
+    match estimate {
+        Some(estimate) => {
+            let formatted = format!("{:.2}", estimate);
+            formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+        }
+        None => "Not estimated".to_string(),
+    }
+}
+
+/// Parse an estimate as written by `to_markdown`: a number such as `3` or `2.5`
+/// (optionally followed by "points"), or "Not estimated". Malformed values are an
+/// error rather than being silently dropped.
+fn parse_estimate(value: &str) -> Result<Option<f64>, anyhow::Error> {
+    let value = value.trim();
+    if value.is_empty() || value.contains("Not estimated") {
+        return Ok(None);
+    }
+
+    let number = value
+        .trim_end_matches("points")
+        .trim_end_matches("point")
+        .trim();
+    match number.parse::<f64>() {
+        Ok(estimate) if estimate.is_finite() && estimate >= 0.0 => Ok(Some(estimate)),
+        _ => anyhow::bail!("Invalid estimate '{}'", value),
+    }
 }
 
 // Linear GraphQL response types
@@ -250,14 +872,6 @@ pub struct LinearResponse<T> {
     pub data: T,
 }
 
-// Using an empty struct for simplicity since we don't actually use the content
-#[derive(Debug, Deserialize)]
-pub struct LinearErrorResponse {}
-
-// Using an empty struct for simplicity since we don't actually use the content
-#[derive(Debug, Deserialize)]
-pub struct LinearError {}
-
 #[derive(Debug, Deserialize)]
 pub struct LinearViewerResponse {
     pub viewer: LinearViewer,
@@ -265,6 +879,7 @@ pub struct LinearViewerResponse {
 
 #[derive(Debug, Deserialize)]
 pub struct LinearViewer {
+    pub id: String,
     pub name: String,
 }
 
@@ -286,6 +901,16 @@ pub struct LinearUser {
 #[serde(rename_all = "camelCase")]
 pub struct LinearNodesContainer<T> {
     pub nodes: Vec<T>,
+    /// Only present when the query selects it, for connections that are paginated
+    #[serde(default)]
+    pub page_info: Option<LinearPageInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinearPageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -300,20 +925,42 @@ pub struct LinearIssue {
     pub state: LinearState,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-    // Note: Fields 'assignee' and 'labels' from the API response are intentionally omitted
-    // as they are not used directly. Instead, we fetch these separately in enrich_ticket.
+    /// Labels are requested in the listing queries so they can be shown and exported
+    /// before enrichment; queries that don't select them leave this empty.
+    #[serde(default)]
+    pub labels: Option<LinearNodesContainer<LinearLabel>>,
+    /// Only selected by queries that mix assignees (such as custom views); the
+    /// per-user queries use the requested user as assignee instead.
+    #[serde(default)]
+    pub assignee: Option<LinearAssignee>,
+    #[serde(default)]
+    pub project: Option<LinearProject>,
+    /// Cheap hints of whether the issue has a parent and children, selected together by
+    /// the listing queries. `children` is only present when they were selected.
+    #[serde(default)]
+    pub parent: Option<serde::de::IgnoredAny>,
+    #[serde(default)]
+    pub children: Option<LinearNodesContainer<serde::de::IgnoredAny>>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct LinearState {
+pub struct LinearLabel {
     pub name: String,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct LinearLabel {
-    // This struct is used as a placeholder for deserialization
-    // The actual label name is extracted in fetch_ticket_labels
-    // using a local Label struct
+pub struct LinearAssignee {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinearProject {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinearState {
+    pub name: String,
 }
 
 // Anthropic types
@@ -322,21 +969,339 @@ pub struct LinearLabel {
 pub struct AnthropicRequest {
     pub model: String,
     pub max_tokens: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub system: Vec<AnthropicContentBlock>,
     pub messages: Vec<AnthropicMessage>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct AnthropicMessage {
     pub role: String,
-    pub content: String,
+    pub content: Vec<AnthropicContentBlock>,
+}
+
+impl AnthropicMessage {
+    pub fn user(text: &str) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: vec![AnthropicContentBlock::text(text)],
+        }
+    }
+
+    /// An earlier reply of the model, for multi-turn conversations
+    pub fn assistant(text: &str) -> Self {
+        Self {
+            role: "assistant".to_string(),
+            content: vec![AnthropicContentBlock::text(text)],
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnthropicContentBlock {
+    #[serde(rename = "type")]
+    pub block_type: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<AnthropicCacheControl>,
+}
+
+impl AnthropicContentBlock {
+    pub fn text(text: &str) -> Self {
+        Self {
+            block_type: "text".to_string(),
+            text: text.to_string(),
+            cache_control: None,
+        }
+    }
+
+    /// Mark this block as the end of a cacheable prompt prefix
+    pub fn cached(mut self) -> Self {
+        self.cache_control = Some(AnthropicCacheControl {
+            cache_type: "ephemeral".to_string(),
+        });
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnthropicCacheControl {
+    #[serde(rename = "type")]
+    pub cache_type: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AnthropicResponse {
     pub content: Vec<AnthropicContent>,
+    #[serde(default)]
+    pub usage: AnthropicUsage,
 }
 
+/// Token counts reported for a single request
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AnthropicUsage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+}
+
+/// A block of a response's content. Only text blocks have text; other types (tool use,
+/// thinking, types added later) are kept with their type so that they don't fail to parse.
 #[derive(Debug, Deserialize)]
 pub struct AnthropicContent {
-    pub text: String,
+    #[serde(rename = "type", default)]
+    pub block_type: String,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+impl AnthropicResponse {
+    /// Text of all the text blocks, in order; `None` when no block has text
+    pub fn text(&self) -> Option<String> {
+        let texts: Vec<&str> = self.content.iter().filter_map(|block| block.text.as_deref()).collect();
+        (!texts.is_empty()).then(|| texts.concat())
+    }
+}
+
+/// A "Todo" ticket with nothing but an ID and a title, for tests to fill in
+#[cfg(test)]
+pub fn test_ticket(id: &str, title: &str) -> Ticket {
+    Ticket::new(
+        id.to_string(),
+        title.to_string(),
+        None,
+        0,
+        None,
+        Vec::new(),
+        String::new(),
+        "Todo".to_string(),
+        Utc::now(),
+        Utc::now(),
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_parse_with_or_without_points() {
+        assert_eq!(parse_estimate("3").unwrap(), Some(3.0));
+        assert_eq!(parse_estimate("2.5 points").unwrap(), Some(2.5));
+        assert_eq!(parse_estimate("1 point").unwrap(), Some(1.0));
+        assert_eq!(parse_estimate("0").unwrap(), Some(0.0));
+        assert_eq!(parse_estimate("Not estimated").unwrap(), None);
+    }
+
+    #[test]
+    fn malformed_estimates_are_rejected() {
+        for value in ["three", "-1", "NaN", "2.5.1"] {
+            assert!(parse_estimate(value).is_err(), "{}", value);
+        }
+    }
+
+    fn ticket_with_description(description: &str) -> Ticket {
+        ticket_with(Some(description.to_string()))
+    }
+
+    fn ticket_with(description: Option<String>) -> Ticket {
+        Ticket { description, ..test_ticket("T-1", "Title") }
+    }
+
+    #[test]
+    fn empty_template_sections_are_detected() {
+        let ticket = ticket_with_description(
+            "## Bug report\n\n### Steps to reproduce\n\n<!-- List the steps\n     one per line -->\n\n\
+             ### Expected behavior\nThe export finishes.\n\n**Logs**\n_No response_\n\n\
+             ### Environment\n- \n- [ ] \nTBD\n",
+        );
+
+        assert_eq!(ticket.empty_template_sections(), vec!["Steps to reproduce", "Logs", "Environment"]);
+    }
+
+    #[test]
+    fn filled_in_descriptions_have_no_empty_sections() {
+        let ticket = ticket_with_description("## Context\nUsers can't export.\n\n## Plan\n1. Fix it\n");
+        assert!(ticket.empty_template_sections().is_empty());
+    }
+
+    #[test]
+    fn missing_descriptions_round_trip_as_missing() {
+        let markdown = ticket_with(None).to_markdown(&[], None, false);
+        assert!(markdown.contains(&format!("## Description\n\n{}\n\n## Comments", NO_DESCRIPTION)));
+
+        let parsed = Ticket::from_markdown(&markdown).unwrap();
+        assert_eq!(parsed.description, None);
+        assert_eq!(parsed.to_markdown(&[], None, false), markdown);
+    }
+
+    #[test]
+    fn metadata_round_trips_as_lines_and_as_a_table() {
+        let mut ticket = ticket_with_description("| Step | Owner |\n| --- | --- |\n| Migrate | Sam |");
+        ticket.estimate = Some(2.5);
+        ticket.labels = vec!["Bug".to_string(), "Needs|Review".to_string()];
+        ticket.subscribers = vec!["Pat".to_string()];
+        ticket.branch_name = Some("sam/t-1-title".to_string());
+        ticket.project = Some("Q3 Billing".to_string());
+
+        for gfm_tables in [false, true] {
+            let markdown = ticket.to_markdown(&[], None, gfm_tables);
+            assert_eq!(markdown.contains("| State | Todo |\n"), gfm_tables);
+            assert_eq!(markdown.contains("**State:** Todo\n"), !gfm_tables);
+
+            let parsed = Ticket::from_markdown(&markdown).unwrap();
+            assert_eq!(parsed.id, "T-1");
+            assert_eq!(parsed.estimate, Some(2.5));
+            assert_eq!(parsed.labels, ticket.labels);
+            assert_eq!(parsed.subscribers, ["Pat"]);
+            assert_eq!(parsed.branch_name, ticket.branch_name);
+            assert_eq!(parsed.project, ticket.project);
+            assert_eq!(parsed.description, ticket.description);
+            assert_eq!(parsed.to_markdown(&[], None, gfm_tables), markdown);
+        }
+    }
+
+    #[test]
+    fn empty_and_filled_descriptions_round_trip_unchanged() {
+        for description in ["", "Users can't export.\n\n## Steps\n1. Open the page"] {
+            let markdown = ticket_with_description(description).to_markdown(&[], None, false);
+            let parsed = Ticket::from_markdown(&markdown).unwrap();
+            assert_eq!(parsed.description.as_deref(), Some(description));
+        }
+    }
+
+    #[test]
+    fn relation_types_round_trip_through_ticket_files() {
+        let related = |id: &str, relation_type: &str| RelatedTicket {
+            id: id.to_string(),
+            title: format!("Title of {}", id),
+            state: "Todo".to_string(),
+            assignee: None,
+            relation_type: relation_type.to_string(),
+            resolved: false,
+            estimate: None,
+            description: None,
+        };
+        let mut ticket = ticket_with_description("Export fails");
+        ticket.related_tickets = vec![related("ABC-12", RELATION_BLOCKED_BY), related("ABC-3", "duplicate")];
+        ticket.children = vec![related("ABC-20", "child")];
+
+        let markdown = ticket.to_markdown(&[], None, false);
+        assert!(markdown.contains("- Blocked by: ABC-12 Title of ABC-12 (State: Todo)"));
+
+        let parsed = Ticket::from_markdown(&markdown).unwrap();
+        let relations: Vec<(&str, &str, &str)> = parsed
+            .related_tickets
+            .iter()
+            .map(|r| (r.relation_type.as_str(), r.id.as_str(), r.title.as_str()))
+            .collect();
+        assert_eq!(relations, [("blocked_by", "ABC-12", "Title of ABC-12"), ("duplicate", "ABC-3", "Title of ABC-3")]);
+        assert_eq!(parsed.children.len(), 1);
+    }
+
+    #[test]
+    fn branch_and_pull_requests_round_trip_through_ticket_files() {
+        let mut ticket = ticket_with_description("Export fails");
+        assert!(ticket.to_markdown(&[], None, false).contains("**Branch:** None"));
+
+        ticket.branch_name = Some("jane/t-1-export-fails".to_string());
+        ticket.pull_requests = vec![
+            PullRequestLink {
+                title: "Fix export (part 1)".to_string(),
+                url: "https://github.com/acme/app/pull/42".to_string(),
+                status: Some("merged".to_string()),
+            },
+            PullRequestLink {
+                title: "Fix export".to_string(),
+                url: "https://gitlab.com/acme/app/-/merge_requests/7".to_string(),
+                status: None,
+            },
+        ];
+
+        let parsed = Ticket::from_markdown(&ticket.to_markdown(&[], None, false)).unwrap();
+        assert_eq!(parsed.branch_name, ticket.branch_name);
+        assert_eq!(parsed.pull_requests, ticket.pull_requests);
+        assert!(parsed.children.is_empty());
+    }
+
+    #[test]
+    fn child_estimates_roll_up_to_the_parent() {
+        let child = |id: &str, estimate: Option<f64>| RelatedTicket {
+            id: id.to_string(),
+            title: format!("Title of {}", id),
+            state: "Todo".to_string(),
+            assignee: None,
+            relation_type: "child".to_string(),
+            resolved: false,
+            estimate,
+            description: None,
+        };
+        let mut ticket = ticket_with_description("Epic");
+        ticket.children = vec![child("ABC-1", None), child("ABC-2", None)];
+        assert_eq!(ticket.children_estimate(), None);
+
+        ticket.children = vec![child("ABC-1", Some(8.0)), child("ABC-2", Some(5.0))];
+        assert_eq!(ticket.children_estimate().as_deref(), Some("Children sum to 13 points"));
+
+        ticket.children.push(child("ABC-3", None));
+        assert_eq!(
+            ticket.children_estimate().as_deref(),
+            Some("Children sum to 13 points (1 of 3 children not estimated)")
+        );
+
+        // The rollup line in ticket files isn't read back as a child
+        let parsed = Ticket::from_markdown(&ticket.to_markdown(&[], None, false)).unwrap();
+        assert_eq!(parsed.children.len(), 3);
+    }
+
+    #[test]
+    fn whole_estimates_have_no_trailing_zero() {
+        assert_eq!(format_estimate(Some(3.0)), "3");
+        assert_eq!(format_estimate(Some(0.0)), "0");
+    }
+
+    #[test]
+    fn fractional_estimates_keep_their_decimals() {
+        assert_eq!(format_estimate(Some(2.5)), "2.5");
+        assert_eq!(format_estimate(Some(0.25)), "0.25");
+    }
+
+    #[test]
+    fn missing_estimates_are_not_estimated() {
+        assert_eq!(format_estimate(None), "Not estimated");
+    }
+
+    #[test]
+    fn formatted_estimates_parse_back() {
+        for estimate in [Some(3.0), Some(2.5), Some(0.0), None] {
+            assert_eq!(parse_estimate(&format_estimate(estimate)).unwrap(), estimate);
+        }
+        assert_eq!(format_estimate(Some(0.0)), "0");
+    }
+
+    #[test]
+    fn long_comments_are_cut_on_character_boundaries() {
+        // Multibyte characters straddle every byte offset a byte slice could cut at
+        let body = "héllo wörld 日本語のログ";
+        assert_eq!(
+            truncate_comment(body, 8),
+            "héllo wö [comment truncated: 10 characters omitted]"
+        );
+        assert_eq!(
+            truncate_comment("日本語のログ", 2),
+            "日本 [comment truncated: 4 characters omitted]"
+        );
+    }
+
+    #[test]
+    fn short_comments_are_kept_whole() {
+        assert_eq!(truncate_comment("日本語", 3), "日本語");
+        assert_eq!(truncate_comment("", 0), "");
+    }
 }
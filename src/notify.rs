@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde_json::json;
+
+use crate::models::Ticket;
+
+/// Post a run summary to a Slack incoming webhook
+pub async fn notify_slack(
+    client: &Client,
+    webhook_url: &str,
+    processed: &[&Ticket],
+    failures: &[(Ticket, anyhow::Error)],
+) -> Result<()> {
+    let text = build_summary(processed, failures);
+
+    let response = client
+        .post(webhook_url)
+        .json(&json!({ "text": text }))
+        .send()
+        .await
+        .context("Failed to send Slack notification")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_default();
+        anyhow::bail!("Slack webhook returned status {}: {}", status, error_text);
+    }
+
+    Ok(())
+}
+
+/// Build the Slack message text listing processed and failed tickets
+fn build_summary(processed: &[&Ticket], failures: &[(Ticket, anyhow::Error)]) -> String {
+    let mut text = format!(
+        "*linear-agent finished*: {} ticket(s) processed, {} failed",
+        processed.len(),
+        failures.len()
+    );
+
+    for ticket in processed {
+        text.push_str(&format!("\n• {}", slack_link(ticket)));
+    }
+
+    if !failures.is_empty() {
+        text.push_str("\n\n*Failures:*");
+        for (ticket, error) in failures {
+            text.push_str(&format!("\n• {}: {}", slack_link(ticket), escape(&format!("{:#}", error))));
+        }
+    }
+
+    text
+}
+
+/// Format a ticket as a Slack link, falling back to plain text when it has no URL
+fn slack_link(ticket: &Ticket) -> String {
+    let text = escape(&format!("{} {}", ticket.id, ticket.title));
+    if ticket.url.is_empty() {
+        text
+    } else {
+        format!("<{}|{}>", ticket.url, text)
+    }
+}
+
+/// Escape the characters Slack reads as markup in message text
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticket_titles_cannot_break_their_link() {
+        let mut ticket = Ticket {
+            url: "https://linear.app/acme/issue/T-1".to_string(),
+            ..crate::models::test_ticket("T-1", "Show <b> & |pipes| > once")
+        };
+        assert_eq!(
+            slack_link(&ticket),
+            "<https://linear.app/acme/issue/T-1|T-1 Show &lt;b&gt; &amp; |pipes| &gt; once>"
+        );
+
+        ticket.url.clear();
+        assert_eq!(slack_link(&ticket), "T-1 Show &lt;b&gt; &amp; |pipes| &gt; once");
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Paces requests under per-minute limits on requests and tokens, as Anthropic's tiers set
+/// them (--anthropic-rpm, --anthropic-tpm). Each limit is a token bucket holding up to a
+/// minute's worth, refilled continuously. A request takes what it needs right away, even
+/// into debt, and waits until the bucket would be back at zero, so concurrent callers are
+/// served in the order they asked and never fail for lack of budget.
+#[derive(Debug)]
+pub struct RateLimiter {
+    buckets: Mutex<Buckets>,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    per_minute: f64,
+    /// Negative while callers are waiting for budget they already took
+    available: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u32, now: Instant) -> Self {
+        Self {
+            per_minute: f64::from(per_minute),
+            available: f64::from(per_minute),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * self.per_minute / 60.0).min(self.per_minute);
+        self.updated = self.updated.max(now);
+    }
+
+    /// Take `amount` and return how long to wait until the bucket would have held it
+    fn take(&mut self, amount: f64, now: Instant) -> Duration {
+        self.refill(now);
+        self.available -= amount;
+        if self.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.available * 60.0 / self.per_minute)
+        }
+    }
+
+    fn give_back(&mut self, amount: f64) {
+        self.available = (self.available + amount).min(self.per_minute);
+    }
+}
+
+impl RateLimiter {
+    /// A limiter for the given limits; `None` when there are none
+    pub fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Option<Self> {
+        if requests_per_minute.is_none() && tokens_per_minute.is_none() {
+            return None;
+        }
+        let now = Instant::now();
+        Some(Self {
+            buckets: Mutex::new(Buckets {
+                requests: requests_per_minute.map(|limit| Bucket::new(limit, now)),
+                tokens: tokens_per_minute.map(|limit| Bucket::new(limit, now)),
+            }),
+        })
+    }
+
+    /// Wait until a request of about `tokens` tokens fits under the limits, then count it
+    pub async fn acquire(&self, tokens: u32) {
+        let delay = self.reserve(tokens, Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Correct the tokens counted for a request once its actual usage is known: `reserved`
+    /// were counted by `acquire` and `used` were used (0 for a request that failed)
+    pub fn settle(&self, reserved: u32, used: u32) {
+        if let Ok(mut buckets) = self.buckets.lock() {
+            if let Some(bucket) = &mut buckets.tokens {
+                bucket.give_back(f64::from(reserved) - f64::from(used));
+            }
+        }
+    }
+
+    /// Count a request of `tokens` tokens made at `now`, returning how long it must wait
+    fn reserve(&self, tokens: u32, now: Instant) -> Duration {
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return Duration::ZERO;
+        };
+        let request_delay = buckets.requests.as_mut().map(|bucket| bucket.take(1.0, now)).unwrap_or_default();
+        let token_delay = buckets.tokens.as_mut().map(|bucket| bucket.take(f64::from(tokens), now)).unwrap_or_default();
+        request_delay.max(token_delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waits(limiter: &RateLimiter, start: Instant) -> impl Fn(u32, u64) -> f64 + '_ {
+        move |tokens, at| limiter.reserve(tokens, start + Duration::from_secs(at)).as_secs_f64().round()
+    }
+
+    #[test]
+    fn requests_wait_once_a_minute_of_requests_is_used() {
+        let limiter = RateLimiter::new(Some(2), None).unwrap();
+        let wait = waits(&limiter, Instant::now());
+
+        // The third request waits for one to refill, the fourth for two
+        assert_eq!(wait(0, 0), 0.0);
+        assert_eq!(wait(0, 0), 0.0);
+        assert_eq!(wait(0, 0), 30.0);
+        assert_eq!(wait(0, 0), 60.0);
+        // Refilled by then, but only up to a minute's worth
+        assert_eq!(wait(0, 600), 0.0);
+        assert_eq!(wait(0, 600), 0.0);
+        assert_eq!(wait(0, 600), 30.0);
+
+        assert!(RateLimiter::new(None, None).is_none());
+    }
+
+    #[test]
+    fn tokens_counted_beyond_actual_usage_are_given_back() {
+        let limiter = RateLimiter::new(None, Some(1000)).unwrap();
+        let wait = waits(&limiter, Instant::now());
+
+        assert_eq!(wait(600, 0), 0.0);
+        assert_eq!(wait(600, 0), 12.0);
+        // The second request used only 200 of its 600 tokens
+        limiter.settle(600, 200);
+        assert_eq!(wait(200, 0), 0.0);
+        assert_eq!(wait(100, 6), 0.0);
+        assert_eq!(wait(100, 6), 6.0);
+    }
+}
@@ -0,0 +1,204 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+
+/// Written in place of API keys found in recorded requests and responses
+const REDACTED: &str = "[REDACTED]";
+
+/// Order of the requests recorded in this run, across clients
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+/// One API request and the raw response it got, as saved by --record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Exchange {
+    /// "linear" or "anthropic"
+    service: String,
+    url: String,
+    request: serde_json::Value,
+    status: u16,
+    response: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecordingError {
+    #[error("No recorded {service} response left for this request in {dir}")]
+    NoMatch { service: String, dir: PathBuf },
+    #[error("Failed to write recording {path}: {source}")]
+    Write { path: PathBuf, source: std::io::Error },
+    #[error("Failed to read recordings from {path}: {message}")]
+    Read { path: PathBuf, message: String },
+}
+
+enum Mode {
+    Record,
+    /// Recorded exchanges not served yet, loaded on the first request
+    Replay { remaining: Mutex<Option<Vec<Exchange>>> },
+}
+
+/// Saves API exchanges to a directory (--record), or answers requests from such a
+/// directory instead of the network (--replay). Each API client holds one.
+pub struct Recorder {
+    dir: PathBuf,
+    mode: Mode,
+    secrets: Vec<String>,
+}
+
+impl Recorder {
+    /// The recorder for --record or --replay, if either was given. The configured API
+    /// keys are redacted from everything recorded.
+    pub fn from_config(app_config: &AppConfig) -> Option<Arc<Self>> {
+        let (dir, mode) = match (&app_config.record_dir, &app_config.replay_dir) {
+            (Some(dir), _) => (dir, Mode::Record),
+            (None, Some(dir)) => (dir, Mode::Replay { remaining: Mutex::new(None) }),
+            (None, None) => return None,
+        };
+        let secrets = [Some(&app_config.linear_api_key), app_config.anthropic_api_key.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter(|secret| !secret.is_empty())
+            .cloned()
+            .collect();
+
+        Some(Arc::new(Self { dir: dir.clone(), mode, secrets }))
+    }
+
+    /// Whether responses come from the recording instead of the network
+    pub fn is_replaying(&self) -> bool {
+        matches!(self.mode, Mode::Replay { .. })
+    }
+
+    /// Answer a request from the recording: the first exchange not served yet with the
+    /// same service and request body. The URL isn't compared, so a recording made
+    /// against one API base replays against any other.
+    pub fn replay(&self, service: &str, request: &serde_json::Value) -> Result<(StatusCode, String), RecordingError> {
+        let Mode::Replay { remaining } = &self.mode else {
+            return Err(self.no_match(service));
+        };
+        let request = self.redact_json(request);
+
+        let mut remaining = remaining.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if remaining.is_none() {
+            *remaining = Some(load_exchanges(&self.dir)?);
+        }
+        let exchanges = remaining.get_or_insert_with(Vec::new);
+        let index = exchanges
+            .iter()
+            .position(|exchange| exchange.service == service && exchange.request == request)
+            .ok_or_else(|| self.no_match(service))?;
+        let exchange = exchanges.remove(index);
+
+        let status = StatusCode::from_u16(exchange.status).map_err(|e| RecordingError::Read {
+            path: self.dir.clone(),
+            message: e.to_string(),
+        })?;
+        Ok((status, exchange.response))
+    }
+
+    /// Save a request and its raw response to a new file named after the time, order
+    /// and service, e.g. `20240501T120000.123-0007-linear.json`
+    pub fn record(
+        &self,
+        service: &str,
+        url: &str,
+        request: &serde_json::Value,
+        status: StatusCode,
+        response: &str,
+    ) -> Result<(), RecordingError> {
+        let Mode::Record = self.mode else {
+            return Ok(());
+        };
+        let exchange = Exchange {
+            service: service.to_string(),
+            url: url.to_string(),
+            request: self.redact_json(request),
+            status: status.as_u16(),
+            response: self.redact(response),
+        };
+
+        let path = self.dir.join(format!(
+            "{}-{:04}-{}.json",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            SEQUENCE.fetch_add(1, Ordering::SeqCst),
+            service
+        ));
+        let json = serde_json::to_string_pretty(&exchange).unwrap_or_default();
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&path, json))
+            .map_err(|source| RecordingError::Write { path, source })
+    }
+
+    fn redact(&self, text: &str) -> String {
+        self.secrets
+            .iter()
+            .fold(text.to_string(), |text, secret| text.replace(secret.as_str(), REDACTED))
+    }
+
+    fn redact_json(&self, value: &serde_json::Value) -> serde_json::Value {
+        let redacted = self.redact(&value.to_string());
+        serde_json::from_str(&redacted).unwrap_or_else(|_| value.clone())
+    }
+
+    fn no_match(&self, service: &str) -> RecordingError {
+        RecordingError::NoMatch { service: service.to_string(), dir: self.dir.clone() }
+    }
+}
+
+/// Every exchange recorded in a directory, oldest first
+fn load_exchanges(dir: &Path) -> Result<Vec<Exchange>, RecordingError> {
+    let read_error = |path: &Path, message: String| RecordingError::Read { path: path.to_path_buf(), message };
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| read_error(dir, e.to_string()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    // File names start with the time and sequence number, so they sort in recording order
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let json = std::fs::read_to_string(path).map_err(|e| read_error(path, e.to_string()))?;
+            serde_json::from_str(&json).map_err(|e| read_error(path, e.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn recorder(dir: &Path, replay: bool) -> Arc<Recorder> {
+        let app_config = AppConfig {
+            linear_api_key: "lin_secret".to_string(),
+            record_dir: (!replay).then(|| dir.to_path_buf()),
+            replay_dir: replay.then(|| dir.to_path_buf()),
+            ..AppConfig::default()
+        };
+        Recorder::from_config(&app_config).unwrap()
+    }
+
+    #[test]
+    fn recorded_exchanges_replay_in_order_without_secrets() {
+        let dir = std::env::temp_dir().join(format!("linear-agent-recording-{}", std::process::id()));
+        let request = json!({ "query": "{ viewer { id } }", "variables": {} });
+
+        let recording = recorder(&dir, false);
+        recording.record("linear", "http://linear", &request, StatusCode::TOO_MANY_REQUESTS, "slow down").unwrap();
+        recording.record("linear", "http://linear", &request, StatusCode::OK, "{\"key\":\"lin_secret\"}").unwrap();
+
+        let replay = recorder(&dir, true);
+        assert_eq!(replay.replay("linear", &request).unwrap(), (StatusCode::TOO_MANY_REQUESTS, "slow down".to_string()));
+        assert_eq!(replay.replay("linear", &request).unwrap(), (StatusCode::OK, "{\"key\":\"[REDACTED]\"}".to_string()));
+        assert!(matches!(replay.replay("linear", &request), Err(RecordingError::NoMatch { .. })));
+        assert!(matches!(replay.replay("anthropic", &request), Err(RecordingError::NoMatch { .. })));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
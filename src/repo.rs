@@ -0,0 +1,271 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::models::Ticket;
+
+/// Default size in characters of the repository overview added to prompts (--repo-path)
+pub const DEFAULT_REPO_SUMMARY_CHARS: usize = 6000;
+
+/// Files looked at in a repository at most, so that huge monorepos stay quick
+const MAX_FILES: usize = 20_000;
+
+/// Files larger than this aren't searched for the ticket's keywords
+const MAX_SEARCHED_FILE_BYTES: u64 = 256 * 1024;
+
+/// Related files listed at most
+const MAX_RELATED_FILES: usize = 25;
+
+/// File names listed per top-level directory at most
+const MAX_FILES_PER_DIR: usize = 10;
+
+/// Keywords taken from a ticket at most
+const MAX_KEYWORDS: usize = 8;
+
+/// Directories skipped when the repository isn't read through git
+const SKIPPED_DIRS: [&str; 6] = ["target", "node_modules", "vendor", "dist", "build", "__pycache__"];
+
+/// Title words too common to point at any file
+const STOP_WORDS: [&str; 16] = [
+    "when", "with", "from", "that", "this", "should", "into", "about", "after", "before", "have", "does",
+    "some", "make", "support", "add",
+];
+
+/// A bounded overview of the repository at `root` for the ticket's prompt: the top-level
+/// structure and the files whose path or contents mention the ticket's keywords. File
+/// contents are never included. The overview is cut at `max_chars`.
+pub fn summarize(root: &Path, ticket: &Ticket, max_chars: usize) -> Result<String> {
+    let files = list_files(root)?;
+
+    let mut summary = String::from("Top-level structure:\n");
+    summary.push_str(&top_level_structure(&files));
+
+    let keywords = ticket_keywords(ticket);
+    if !keywords.is_empty() {
+        let related = related_files(root, &files, &keywords);
+        summary.push_str(&format!("\nFiles mentioning the ticket's keywords ({}):\n", keywords.join(", ")));
+        if related.is_empty() {
+            summary.push_str("None\n");
+        }
+        for file in related {
+            let mut matches = Vec::new();
+            if file.name_matches {
+                matches.push("name matches".to_string());
+            }
+            if file.mentions > 0 {
+                matches.push(format!("{} mention(s)", file.mentions));
+            }
+            summary.push_str(&format!("- {} ({})\n", file.path.display(), matches.join(", ")));
+        }
+    }
+
+    Ok(truncate_lines(&summary, max_chars))
+}
+
+/// Paths of the repository's files relative to `root`: the files git tracks when `root`
+/// is in a git repository, so ignored files are left out, or else every file not hidden
+/// or in a build directory
+fn list_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let git = Command::new("git").arg("-C").arg(root).args(["ls-files", "-z"]).output();
+    if let Ok(output) = git {
+        if output.status.success() && !output.stdout.is_empty() {
+            return Ok(output
+                .stdout
+                .split(|&byte| byte == 0)
+                .filter(|path| !path.is_empty())
+                .take(MAX_FILES)
+                .map(|path| PathBuf::from(String::from_utf8_lossy(path).into_owned()))
+                .collect());
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        let entries = std::fs::read_dir(root.join(&dir))
+            .with_context(|| format!("Failed to read {}", root.join(&dir).display()))?;
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = dir.join(&name);
+            let Ok(file_type) = entry.file_type() else { continue };
+            if name.starts_with('.') {
+                continue;
+            }
+            if file_type.is_dir() {
+                if !SKIPPED_DIRS.contains(&name.as_str()) {
+                    dirs.push(path);
+                }
+            } else if file_type.is_file() {
+                files.push(path);
+                if files.len() >= MAX_FILES {
+                    return Ok(files);
+                }
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Files at the root, then each top-level directory with its number of files and the
+/// names of the first files directly inside it
+fn top_level_structure(files: &[PathBuf]) -> String {
+    let mut root_files = Vec::new();
+    let mut dirs: BTreeMap<String, (usize, Vec<String>)> = BTreeMap::new();
+    for file in files {
+        let mut components = file.components().map(|c| c.as_os_str().to_string_lossy().into_owned());
+        let Some(first) = components.next() else { continue };
+        let rest: Vec<String> = components.collect();
+        if rest.is_empty() {
+            root_files.push(first);
+            continue;
+        }
+        let (count, names) = dirs.entry(first).or_default();
+        *count += 1;
+        if rest.len() == 1 && names.len() < MAX_FILES_PER_DIR {
+            names.push(rest[0].clone());
+        }
+    }
+
+    let mut structure = String::new();
+    for (dir, (count, names)) in dirs {
+        if names.is_empty() {
+            structure.push_str(&format!("- {}/ ({} files)\n", dir, count));
+        } else {
+            structure.push_str(&format!("- {}/ ({} files): {}\n", dir, count, names.join(", ")));
+        }
+    }
+    for file in root_files {
+        structure.push_str(&format!("- {}\n", file));
+    }
+    structure
+}
+
+/// Lowercase words of the title that may name code (four letters or more, not common
+/// words), then `code spans` of the description
+fn ticket_keywords(ticket: &Ticket) -> Vec<String> {
+    let title_words = ticket
+        .title
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .map(str::to_lowercase)
+        .filter(|word| word.chars().count() >= 4 && !STOP_WORDS.contains(&word.as_str()));
+    let code_spans = ticket
+        .description_text()
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|span| span.trim().to_lowercase())
+        .filter(|span| span.chars().count() >= 3 && !span.contains('\n'));
+
+    let mut keywords: Vec<String> = Vec::new();
+    for keyword in title_words.chain(code_spans) {
+        if !keywords.contains(&keyword) {
+            keywords.push(keyword);
+        }
+    }
+    keywords.truncate(MAX_KEYWORDS);
+    keywords
+}
+
+/// A file whose path or contents mention the ticket's keywords
+struct RelatedFile {
+    path: PathBuf,
+    name_matches: bool,
+    mentions: usize,
+}
+
+/// Files whose path or contents mention any keyword: files named after a keyword first,
+/// then the most mentioned
+fn related_files(root: &Path, files: &[PathBuf], keywords: &[String]) -> Vec<RelatedFile> {
+    let mut related: Vec<RelatedFile> = files
+        .iter()
+        .filter_map(|file| {
+            let path = file.to_string_lossy().to_lowercase();
+            let name_matches = keywords.iter().any(|keyword| path.contains(keyword.as_str()));
+
+            let full_path = root.join(file);
+            let small = std::fs::metadata(&full_path).is_ok_and(|metadata| metadata.len() <= MAX_SEARCHED_FILE_BYTES);
+            // Binary files fail to read as text and are only matched by name
+            let mentions = small
+                .then(|| std::fs::read_to_string(&full_path).ok())
+                .flatten()
+                .map(|contents| {
+                    let contents = contents.to_lowercase();
+                    keywords.iter().map(|keyword| contents.matches(keyword.as_str()).count()).sum()
+                })
+                .unwrap_or(0);
+
+            (name_matches || mentions > 0).then(|| RelatedFile { path: file.clone(), name_matches, mentions })
+        })
+        .collect();
+
+    related.sort_by(|a, b| {
+        b.name_matches.cmp(&a.name_matches).then(b.mentions.cmp(&a.mentions)).then_with(|| a.path.cmp(&b.path))
+    });
+    related.truncate(MAX_RELATED_FILES);
+    related
+}
+
+/// Whole lines of `text` up to `max_chars`, noting when lines were left out
+fn truncate_lines(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let mut truncated = String::new();
+    for line in text.lines() {
+        if truncated.chars().count() + line.chars().count() + 1 > max_chars {
+            break;
+        }
+        truncated.push_str(line);
+        truncated.push('\n');
+    }
+    truncated.push_str("[repository overview truncated]\n");
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overview_lists_the_structure_and_files_related_to_the_ticket() {
+        let root = std::env::temp_dir().join(format!("linear-agent-repo-{}", std::process::id()));
+        for (path, contents) in [
+            ("src/main.rs", "fn main() { export::run(); }"),
+            ("src/export.rs", "pub fn run() {}"),
+            ("src/ui.rs", "pub fn show() {}"),
+            ("docs/guide.md", "How to use the CSV export"),
+            ("target/debug/export.d", "build output"),
+            (".git-like/export", "hidden"),
+            ("Cargo.toml", "[package]"),
+        ] {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        let ticket = Ticket {
+            description: Some("Called from `main`".to_string()),
+            ..crate::models::test_ticket("ABC-1", "Export should support the CSV format")
+        };
+
+        let summary = summarize(&root, &ticket, DEFAULT_REPO_SUMMARY_CHARS).unwrap();
+        assert!(summary.contains("- src/ (3 files): export.rs, main.rs, ui.rs\n"), "{}", summary);
+        assert!(summary.contains("- Cargo.toml\n"));
+        assert!(!summary.contains("target") && !summary.contains(".git-like"));
+        assert!(summary.contains("keywords (export, format, main)"), "{}", summary);
+        let related: Vec<&str> = summary.lines().skip_while(|line| !line.starts_with("Files")).skip(1).collect();
+        assert_eq!(
+            related,
+            ["- src/main.rs (name matches, 2 mention(s))", "- src/export.rs (name matches)", "- docs/guide.md (1 mention(s))"]
+        );
+
+        let short = summarize(&root, &ticket, 40).unwrap();
+        assert!(short.ends_with("[repository overview truncated]\n"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,264 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::anthropic::RequestUsage;
+use crate::config::AppConfig;
+use crate::models::Ticket;
+
+/// Machine-readable record of a run, written as JSON for auditing (--run-report)
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Linear selection the tickets were fetched with; absent for local ticket files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_filter: Option<String>,
+    pub processed: usize,
+    pub failed: usize,
+    pub models: Vec<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// `None` when a model without a known price was used
+    pub estimated_cost: Option<f64>,
+    pub tickets: Vec<TicketReport>,
+}
+
+/// Outcome and Anthropic usage of one ticket in the run
+#[derive(Debug, Serialize)]
+pub struct TicketReport {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub status: TicketStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub models: Vec<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub estimated_cost: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketStatus {
+    Processed,
+    Failed,
+}
+
+impl RunReport {
+    /// Build the report of a run that has just finished. `selection` is the config the
+    /// tickets were fetched from Linear with, if any. Usage not attributed to a ticket
+    /// (such as the connection test) only counts towards the totals.
+    pub fn new(
+        started_at: DateTime<Utc>,
+        selection: Option<&AppConfig>,
+        processed: &[&Ticket],
+        failures: &[(Ticket, anyhow::Error)],
+        usage: &[RequestUsage],
+    ) -> Self {
+        let mut tickets: Vec<TicketReport> = processed
+            .iter()
+            .map(|ticket| TicketReport::new(ticket, TicketStatus::Processed, None, usage))
+            .collect();
+        tickets.extend(failures.iter().map(|(ticket, error)| {
+            TicketReport::new(ticket, TicketStatus::Failed, Some(format!("{:#}", error)), usage)
+        }));
+
+        let all_usage: Vec<&RequestUsage> = usage.iter().collect();
+
+        Self {
+            started_at,
+            finished_at: Utc::now(),
+            user: selection.map(|config| config.linear_agent_user.clone()),
+            team: selection.map(|config| config.linear_team_name.clone()),
+            state_filter: selection.map(|config| config.state_filter().to_string()),
+            processed: processed.len(),
+            failed: failures.len(),
+            models: models_used(&all_usage),
+            input_tokens: all_usage.iter().map(|u| u.tokens.input_tokens).sum(),
+            output_tokens: all_usage.iter().map(|u| u.tokens.output_tokens).sum(),
+            estimated_cost: all_usage.iter().map(|u| u.cost()).sum(),
+            tickets,
+        }
+    }
+
+    /// What the run generated and what it cost on one line, e.g. "Generated 8 plan(s),
+    /// 45,200 input tokens, 31,000 output tokens, est. $0.42, 0 failed"
+    pub fn usage_summary(&self, kind: &str) -> String {
+        let cost = match self.estimated_cost {
+            Some(cost) => format!("est. ${:.2}", cost),
+            None => "cost unknown for these models".to_string(),
+        };
+        format!(
+            "Generated {} {}, {} input tokens, {} output tokens, {}, {} failed",
+            self.processed,
+            kind,
+            thousands(self.input_tokens),
+            thousands(self.output_tokens),
+            cost,
+            self.failed
+        )
+    }
+
+    /// Serialize the report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize run report")
+    }
+}
+
+impl TicketReport {
+    fn new(ticket: &Ticket, status: TicketStatus, error: Option<String>, usage: &[RequestUsage]) -> Self {
+        let ticket_usage: Vec<&RequestUsage> = usage
+            .iter()
+            .filter(|u| u.ticket_id.as_deref() == Some(ticket.id.as_str()))
+            .collect();
+
+        Self {
+            id: ticket.id.clone(),
+            title: ticket.title.clone(),
+            url: ticket.url.clone(),
+            status,
+            error,
+            models: models_used(&ticket_usage),
+            input_tokens: ticket_usage.iter().map(|u| u.tokens.input_tokens).sum(),
+            output_tokens: ticket_usage.iter().map(|u| u.tokens.output_tokens).sum(),
+            estimated_cost: ticket_usage.iter().map(|u| u.cost()).sum(),
+        }
+    }
+}
+
+/// File next to the run report listing the tickets left failed, for --retry-failed
+pub const FAILURES_FILENAME: &str = "failures.json";
+
+/// Tickets that failed in a run and haven't been processed since (failures.json)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FailedTickets {
+    pub tickets: Vec<FailedTicket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedTicket {
+    pub id: String,
+    pub title: String,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl FailedTickets {
+    /// The failed tickets listed at `path`; none if the file doesn't exist
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Record the outcome of a run: processed tickets are cleared and failed ones added,
+    /// or their error updated if they were already listed
+    pub fn update(&mut self, processed: &[&Ticket], failures: &[(Ticket, anyhow::Error)]) {
+        self.tickets.retain(|failed| {
+            !processed.iter().any(|ticket| ticket.id == failed.id)
+                && !failures.iter().any(|(ticket, _)| ticket.id == failed.id)
+        });
+        self.tickets.extend(failures.iter().map(|(ticket, error)| FailedTicket {
+            id: ticket.id.clone(),
+            title: ticket.title.clone(),
+            error: format!("{:#}", error),
+            failed_at: Utc::now(),
+        }));
+    }
+
+    /// Write the list to `path`, or remove the file once no failed ticket is left
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if self.tickets.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+            return Ok(());
+        }
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize failed tickets")?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Distinct models of the requests, in the order they were first used
+fn models_used(usage: &[&RequestUsage]) -> Vec<String> {
+    let mut models: Vec<String> = Vec::new();
+    for request in usage {
+        if !models.contains(&request.model) {
+            models.push(request.model.clone());
+        }
+    }
+    models
+}
+
+/// A count with comma thousands separators, e.g. 45,200
+fn thousands(count: u64) -> String {
+    let digits = count.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_summary_fits_on_one_line() {
+        let report = RunReport {
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+            user: None,
+            team: None,
+            state_filter: None,
+            processed: 8,
+            failed: 1,
+            models: Vec::new(),
+            input_tokens: 45_200,
+            output_tokens: 1_031_000,
+            estimated_cost: Some(0.4213),
+            tickets: Vec::new(),
+        };
+        assert_eq!(
+            report.usage_summary("plan(s)"),
+            "Generated 8 plan(s), 45,200 input tokens, 1,031,000 output tokens, est. $0.42, 1 failed"
+        );
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(999), "999");
+    }
+
+    #[test]
+    fn failed_tickets_are_cleared_once_processed() {
+        let ticket = |id: &str| crate::models::test_ticket(id, &format!("Title of {}", id));
+        let ids = |failed: &FailedTickets| failed.tickets.iter().map(|t| t.id.clone()).collect::<Vec<_>>();
+
+        let mut failed = FailedTickets::default();
+        failed.update(&[&ticket("ABC-1")], &[(ticket("ABC-2"), anyhow::anyhow!("timeout")), (ticket("ABC-3"), anyhow::anyhow!("overloaded"))]);
+        assert_eq!(ids(&failed), ["ABC-2", "ABC-3"]);
+
+        // A retry where one ticket succeeds and the other fails again
+        failed.update(&[&ticket("ABC-2")], &[(ticket("ABC-3"), anyhow::anyhow!("rate limited"))]);
+        assert_eq!(ids(&failed), ["ABC-3"]);
+        assert_eq!(failed.tickets[0].error, "rate limited");
+
+        let path = std::env::temp_dir().join(format!("linear-agent-failures-{}.json", std::process::id()));
+        failed.save(&path).unwrap();
+        assert_eq!(ids(&FailedTickets::load(&path).unwrap()), ["ABC-3"]);
+        FailedTickets::default().save(&path).unwrap();
+        assert!(!path.exists());
+        assert!(FailedTickets::load(&path).unwrap().tickets.is_empty());
+    }
+}
@@ -1,27 +1,170 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
 use dialoguer::{Input, Select, MultiSelect, Confirm};
-use std::path::PathBuf;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use similar::{ChangeTag, TextDiff};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
 
-use crate::models::Ticket;
-use crate::config::AppConfig;
+use crate::models::{format_estimate, Ticket};
+use crate::config::{AppConfig, PriorityColors};
+use crate::linear::{MemberWorkload, UserNotFound};
 
-/// Display a list of tickets in a user-friendly format
-pub fn display_tickets(tickets: &[Ticket]) {
+/// Gate for console output, so that --quiet suppresses everything except
+/// errors and the final summary without checks at every call site
+#[derive(Debug, Clone)]
+pub struct Reporter {
+    quiet: bool,
+    /// Bar cleared while a line is printed, so that output doesn't tear it
+    progress: Option<ProgressBar>,
+}
+
+impl Reporter {
+    pub fn new(quiet: bool) -> Self {
+        Self { quiet, progress: None }
+    }
+    
+    pub fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+    
+    /// A reporter whose lines are printed above `bar` (and the other bars of its
+    /// MultiProgress) instead of through it
+    pub fn above(&self, bar: &ProgressBar) -> Self {
+        Self { quiet: self.quiet, progress: Some(bar.clone()) }
+    }
+    
+    fn print(&self, print: impl FnOnce()) {
+        match &self.progress {
+            Some(bar) => bar.suspend(print),
+            None => print(),
+        }
+    }
+    
+    /// Progress and decorative output, hidden in quiet mode
+    pub fn info(&self, message: impl Display) {
+        if !self.quiet {
+            self.print(|| println!("{}", message));
+        }
+    }
+    
+    /// Errors are always printed, to stderr
+    pub fn error(&self, message: impl Display) {
+        self.print(|| eprintln!("{}", message));
+    }
+    
+    /// The final summary line is always printed, to stderr so that it never mixes with
+    /// data written to stdout
+    pub fn summary(&self, message: impl Display) {
+        self.print(|| eprintln!("{}", message));
+    }
+    
+    /// A progress bar that stays hidden in quiet mode
+    pub fn progress_bar(&self, len: usize) -> ProgressBar {
+        if self.quiet {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new(len as u64)
+        }
+    }
+    
+    /// A ticking spinner with a message and the time elapsed, for a single slow step;
+    /// hidden in quiet mode
+    pub fn spinner(&self, message: String) -> ProgressBar {
+        if self.quiet {
+            return ProgressBar::hidden();
+        }
+        let spinner = ProgressBar::new_spinner().with_message(message);
+        if let Ok(style) = ProgressStyle::with_template("{spinner} {msg} {elapsed}") {
+            spinner.set_style(style);
+        }
+        spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+        spinner
+    }
+    
+    /// A set of bars rendered together, for concurrent tasks; hidden in quiet mode
+    pub fn multi_progress(&self) -> MultiProgress {
+        if self.quiet {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        } else {
+            MultiProgress::new()
+        }
+    }
+}
+
+/// Label for a Linear priority value (0 = No priority, 1 = Urgent ... 4 = Low)
+pub fn priority_label(priority: i32) -> &'static str {
+    match priority {
+        1 => "⚠️ Urgent",
+        2 => "High",
+        3 => "Medium",
+        4 => "Low",
+        _ => "No priority",
+    }
+}
+
+/// Print how many of the team's tickets in the selected states each person has
+/// (--team-members), as a two-column table
+pub fn display_team_workload(team_name: &str, states: &str, workload: &[MemberWorkload]) {
+    println!("\n{}", "=".repeat(80));
+    println!("Tickets per person in {}, in {}", team_name, states);
+    println!("{}", "=".repeat(80));
+    
+    let name_width = workload
+        .iter()
+        .map(|member| member.name.chars().count())
+        .chain(std::iter::once("Member".len()))
+        .max()
+        .unwrap_or_default();
+    println!("{:<width$}  {:>12}", "Member".bold(), "Open tickets".bold(), width = name_width);
+    for member in workload {
+        let count = member.open_tickets.to_string();
+        let count = if member.open_tickets == 0 { count.dimmed() } else { count.normal() };
+        println!("{:<width$}  {:>12}", member.name, count, width = name_width);
+    }
+    
+    let total: usize = workload.iter().map(|member| member.open_tickets).sum();
+    println!("{}", "-".repeat(80));
+    println!("{} tickets across {} people", total, workload.len());
+}
+
+/// Truncate text to at most `max_chars` characters (not bytes), adding an ellipsis if cut
+pub fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((byte_index, _)) => format!("{}…", &text[..byte_index]),
+        None => text.to_string(),
+    }
+}
+
+/// "⛔ Blocked by ABC-12, ABC-13" for a ticket with open blockers
+fn blocked_badge(ticket: &Ticket) -> Option<String> {
+    let blockers = ticket.open_blockers();
+    if blockers.is_empty() {
+        return None;
+    }
+    let ids: Vec<&str> = blockers.iter().map(|blocker| blocker.id.as_str()).collect();
+    Some(format!("⛔ Blocked by {}", ids.join(", ")))
+}
+
+/// Display a list of tickets in a user-friendly format.
+/// With `description_preview`, the first N characters of each description are shown too.
+pub fn display_tickets(
+    tickets: &[Ticket],
+    priority_colors: &PriorityColors,
+    description_preview: Option<usize>,
+) {
     println!("\n{}", "=".repeat(80));
     println!("Found {} tickets", tickets.len());
     println!("{}", "=".repeat(80));
     
+    let label_assignees = has_several_assignees(tickets);
     for (i, ticket) in tickets.iter().enumerate() {
-        let priority_str = match ticket.priority {
-            3..=4 => "⚠️ High".red(),
-            2 => "Medium".yellow(),
-            _ => "Low".green(),
-        };
+        let priority_str = priority_label(ticket.priority)
+            .color(priority_colors.color_for(ticket.priority));
         
         let estimate_str = match ticket.estimate {
-            Some(est) => format!("{} points", est),
-            None => "Not estimated".to_string(),
+            Some(_) => format!("{} points", format_estimate(ticket.estimate)),
+            None => format_estimate(None),
         };
         
         let labels_str = if ticket.labels.is_empty() {
@@ -31,17 +174,64 @@ pub fn display_tickets(tickets: &[Ticket]) {
         };
         
         println!("{}. [{}] {}", i + 1, ticket.state.blue(), ticket.title.bright_white());
-        println!("   Priority: {} | Estimate: {} | Labels: {}", priority_str, estimate_str, labels_str);
+        if let Some(badge) = blocked_badge(ticket) {
+            println!("   {}", badge.red().bold());
+        }
+        if label_assignees {
+            println!("   Priority: {} | Estimate: {} | Labels: {} | Assignee: {}",
+                     priority_str, estimate_str, labels_str, ticket.assignee.as_deref().unwrap_or("None"));
+        } else {
+            println!("   Priority: {} | Estimate: {} | Labels: {}", priority_str, estimate_str, labels_str);
+        }
+        // Children are only known here once fetched, like relations below
+        if let Some(rollup) = ticket.children_estimate() {
+            println!("   {}", rollup);
+        }
         println!("   URL: {}", ticket.url);
         
-        // Description is now hidden in the listing to simplify output
+        // Relations are only known here once looked up, e.g. with --sort-by-blocked
+        if !ticket.related_tickets.is_empty() {
+            let relations: Vec<String> = ticket.related_tickets.iter()
+                .map(|related| format!("{} {}", related.relation_label(), related.id))
+                .collect();
+            println!("   Relations: {}", relations.join(", "));
+        }
+        
+        // Description is hidden by default to keep the listing simple
+        if let Some(max_chars) = description_preview {
+            let description = ticket.description_text().split_whitespace().collect::<Vec<_>>().join(" ");
+            if description.is_empty() {
+                println!("   {}", "No description".dimmed());
+            } else {
+                println!("   {}", truncate_chars(&description, max_chars).dimmed());
+            }
+        }
         
         println!("{}", "-".repeat(80));
     }
 }
 
-/// Get user selection of tickets to process
-pub fn get_user_selection(tickets: &[Ticket], generate_plans: bool) -> Result<Vec<usize>> {
+/// Whether the tickets belong to more than one assignee
+fn has_several_assignees(tickets: &[Ticket]) -> bool {
+    tickets.windows(2).any(|pair| pair[0].assignee != pair[1].assignee)
+}
+
+/// Display a summary of tickets that failed during a batch run
+pub fn display_failures(failures: &[(Ticket, anyhow::Error)]) {
+    eprintln!("\n{}", "=".repeat(80));
+    eprintln!("{}", format!("❌ {} ticket(s) failed", failures.len()).red());
+    eprintln!("{}", "=".repeat(80));
+    
+    for (ticket, error) in failures {
+        eprintln!("- {} {}", ticket.id.bright_white(), ticket.title);
+        eprintln!("   {}", format!("{:#}", error).red());
+    }
+}
+
+/// Get user selection of tickets to process. The tickets are listed in `order` (indices
+/// into `tickets`, e.g. sorted with --sort-selection); the returned indices refer to
+/// `tickets`, in ascending order whatever order they were listed in.
+pub fn get_user_selection(tickets: &[Ticket], order: &[usize], generate_plans: bool) -> Result<Vec<usize>> {
     // Different prompt based on whether we're generating plans or just fetching info
     let prompt = if generate_plans {
         "Select tickets to generate implementation plans for:".blue()
@@ -51,8 +241,26 @@ pub fn get_user_selection(tickets: &[Ticket], generate_plans: bool) -> Result<Ve
     
     println!("\n{}", prompt);
     
+    // With several assignees (e.g. --user "Jane, Bob"), show whose ticket each one is
+    let several_assignees = has_several_assignees(tickets);
+    let items: Vec<String> = order.iter()
+        .map(|&i| {
+            let ticket = &tickets[i];
+            let item = if several_assignees {
+                format!("{} ({})", ticket.title, ticket.assignee.as_deref().unwrap_or("unassigned"))
+            } else {
+                ticket.title.clone()
+            };
+            // Blocked tickets keep their badge in the selection list
+            match blocked_badge(ticket) {
+                Some(badge) => format!("{} {}", item, badge.red()),
+                None => item,
+            }
+        })
+        .collect();
+    
     let selections = MultiSelect::new()
-        .items(&tickets.iter().map(|t| &t.title).collect::<Vec<_>>())
+        .items(&items)
         .defaults(&vec![false; tickets.len()])
         .interact()?;
     
@@ -71,7 +279,206 @@ pub fn get_user_selection(tickets: &[Ticket], generate_plans: bool) -> Result<Ve
         }
     }
     
-    Ok(selections)
+    Ok(ticket_indices(order, &selections))
+}
+
+/// The tickets at the selected positions of a list shown in `order`, in ascending order
+fn ticket_indices(order: &[usize], positions: &[usize]) -> Vec<usize> {
+    let mut indices: Vec<usize> = positions.iter().map(|&position| order[position]).collect();
+    indices.sort_unstable();
+    indices
+}
+
+/// Print a unified diff between a saved plan and a newly generated one
+pub fn display_plan_diff(old: &str, new: &str) {
+    let diff = TextDiff::from_lines(old, new);
+    
+    println!("\n{}", "=".repeat(80));
+    println!("{}", "Changes compared to the saved plan".bright_white());
+    println!("{}", "=".repeat(80));
+    
+    for (i, group) in diff.grouped_ops(3).iter().enumerate() {
+        if i > 0 {
+            println!("{}", "-".repeat(80));
+        }
+        for op in group {
+            for change in diff.iter_changes(op) {
+                let line = change.to_string_lossy();
+                let line = line.trim_end_matches('\n');
+                match change.tag() {
+                    ChangeTag::Delete => println!("{}", format!("-{}", line).red()),
+                    ChangeTag::Insert => println!("{}", format!("+{}", line).green()),
+                    ChangeTag::Equal => println!(" {}", line),
+                }
+            }
+        }
+    }
+    
+    println!("{}", "=".repeat(80));
+}
+
+/// Ask whether an existing file should be overwritten
+pub fn confirm_overwrite(path: &Path) -> Result<bool> {
+    let confirmed = Confirm::new()
+        .with_prompt(format!("Overwrite {}?", path.display()))
+        .default(false)
+        .interact()?;
+    
+    Ok(confirmed)
+}
+
+/// Ask whether to go ahead with plan generation estimated to cost more than the threshold
+pub fn confirm_plan_cost(estimate: &str, plans: usize) -> Result<bool> {
+    let confirmed = Confirm::new()
+        .with_prompt(format!("Generating {} plan(s) may cost {}. Continue?", plans, estimate))
+        .default(false)
+        .interact()?;
+    
+    Ok(confirmed)
+}
+
+/// Ask whether to go ahead with planning more tickets than --max-tickets-per-run allows
+pub fn confirm_ticket_count(selected: usize, max: usize) -> Result<bool> {
+    let confirmed = Confirm::new()
+        .with_prompt(format!(
+            "{} tickets selected, more than the limit of {} per run. Plan all of them?",
+            selected, max
+        ))
+        .default(false)
+        .interact()?;
+    
+    Ok(confirmed)
+}
+
+/// What to plan of two tickets that look like duplicates
+pub enum DuplicateChoice {
+    Both,
+    OnlyFirst,
+    OnlySecond,
+}
+
+/// Ask whether to plan both of two likely duplicates or only one of them
+pub fn select_duplicate_to_plan(first: &Ticket, second: &Ticket) -> Result<DuplicateChoice> {
+    let items = [
+        "Plan both".to_string(),
+        format!("Plan only {}: {}", first.id, first.title),
+        format!("Plan only {}: {}", second.id, second.title),
+    ];
+    let index = Select::new()
+        .with_prompt("Which of these tickets do you want to plan?")
+        .default(0)
+        .items(&items)
+        .interact()?;
+    
+    Ok(match index {
+        1 => DuplicateChoice::OnlyFirst,
+        2 => DuplicateChoice::OnlySecond,
+        _ => DuplicateChoice::Both,
+    })
+}
+
+/// Ask whether to re-fetch a ticket whose saved snapshot is `age_days` old
+pub fn confirm_refetch(ticket: &Ticket, age_days: i64) -> Result<bool> {
+    println!(
+        "{}",
+        format!(
+            "⚠️ {} was last updated {} days ago; a plan from this snapshot may be out of date",
+            ticket.id, age_days
+        )
+        .yellow()
+    );
+    let confirmed = Confirm::new()
+        .with_prompt(format!("Re-fetch {} from Linear before planning?", ticket.id))
+        .default(true)
+        .interact()?;
+    
+    Ok(confirmed)
+}
+
+/// Preview the subtasks that would be created under a ticket and ask for confirmation
+pub fn confirm_subtasks(ticket: &Ticket, tasks: &[String]) -> Result<bool> {
+    println!("\n{}", format!("Subtasks to create under {}: {}", ticket.id, ticket.title).blue());
+    for (i, task) in tasks.iter().enumerate() {
+        println!("  {}. {}", i + 1, task);
+    }
+    
+    let confirmed = Confirm::new()
+        .with_prompt(format!("Create {} subtask(s) in Linear?", tasks.len()))
+        .default(false)
+        .interact()?;
+    
+    Ok(confirmed)
+}
+
+/// What to do with a plan shown during --interactive-refine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanAction {
+    Accept,
+    Regenerate,
+    AddInstruction(String),
+    Skip,
+}
+
+/// Show a generated plan and ask whether to keep it, regenerate it or skip the ticket
+pub fn review_generated_plan(ticket: &Ticket, plan: &str, attempt: usize) -> Result<PlanAction> {
+    println!("\n{}", "=".repeat(80));
+    println!("{}", format!("Plan for {}: {} (attempt {})", ticket.id, ticket.title, attempt).bright_green());
+    println!("{}", "=".repeat(80));
+    println!("{}", plan.trim());
+    println!("{}", "=".repeat(80));
+    
+    let items = [
+        "Accept and save this plan",
+        "Regenerate",
+        "Regenerate with an extra instruction",
+        "Skip this ticket",
+    ];
+    let index = Select::new()
+        .with_prompt("What do you want to do with this plan?")
+        .default(0)
+        .items(&items)
+        .interact()?;
+    
+    let action = match index {
+        0 => PlanAction::Accept,
+        1 => PlanAction::Regenerate,
+        2 => PlanAction::AddInstruction(
+            Input::new()
+                .with_prompt("Instruction for the next attempt")
+                .interact_text()?,
+        ),
+        _ => PlanAction::Skip,
+    };
+    
+    Ok(action)
+}
+
+/// Offer the closest user-name matches after a failed lookup.
+/// Returns `None` if the user declines to pick one.
+pub fn select_suggested_user(not_found: &UserNotFound) -> Result<Option<String>> {
+    println!("\n{}", format!("⚠️ User '{}' not found", not_found.name).yellow());
+    
+    let mut items: Vec<&str> = not_found.suggestions.iter().map(String::as_str).collect();
+    items.push("None of these");
+    
+    let index = Select::new()
+        .with_prompt("Did you mean one of these users?")
+        .default(0)
+        .items(&items)
+        .interact()?;
+    
+    Ok(not_found.suggestions.get(index).cloned())
+}
+
+/// Ask which of the user's teams to use when none is configured
+pub fn select_team(teams: &[String]) -> Result<String> {
+    let index = Select::new()
+        .with_prompt("No team configured. Which team do you want to use?")
+        .default(0)
+        .items(teams)
+        .interact()?;
+    
+    Ok(teams[index].clone())
 }
 
 /// Run the setup wizard to configure API keys and settings
@@ -79,14 +486,16 @@ pub async fn setup_wizard() -> Result<AppConfig> {
     println!("\n{}", "📝 Linear Agent Setup".bright_green());
     println!("{}", "Let's set up your configuration.".blue());
     
-    // Start with default config
-    let mut config = AppConfig::default();
+    // Start with default config, asking for the Linear API key first
+    let mut config = AppConfig {
+        linear_api_key: Input::new()
+            .with_prompt("Linear API Key")
+            .allow_empty(false)
+            .interact_text()?,
+        ..AppConfig::default()
+    };
     
-    // Ask for API keys
-    config.linear_api_key = Input::new()
-        .with_prompt("Linear API Key")
-        .allow_empty(false)
-        .interact_text()?;
+    // Ask for the Anthropic API key
     
     let anthropic_key: String = Input::new()
         .with_prompt("Anthropic API Key (leave empty to skip if not using plan generation)")
@@ -142,14 +551,10 @@ pub async fn setup_wizard() -> Result<AppConfig> {
         .with_prompt("Save this configuration for future use?")
         .interact()?
     {
-        let home = dirs::home_dir().context("Failed to find home directory")?;
-        let default_config_dir = home.join(".linear-agent");
+        let default_path = AppConfig::default_env_path().context("Failed to find home directory")?;
         
         // Convert PathBuf to String for interact_text
-        let default_path_str = default_config_dir
-            .join(".env")
-            .to_string_lossy()
-            .to_string();
+        let default_path_str = default_path.to_string_lossy().to_string();
         
         let config_path_str: String = Input::new()
             .with_prompt("Config file path")
@@ -164,4 +569,60 @@ pub async fn setup_wizard() -> Result<AppConfig> {
     }
     
     Ok(config)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{selection_order, SelectionSort};
+
+    fn ticket(id: &str, title: &str, priority: i32, estimate: Option<f64>) -> Ticket {
+        Ticket { priority, estimate, ..crate::models::test_ticket(id, title) }
+    }
+
+    #[test]
+    fn sorted_selections_still_refer_to_the_chosen_tickets() {
+        let tickets = [
+            ticket("ABC-10", "Dark mode", 0, None),
+            ticket("ABC-2", "export fails", 3, Some(2.0)),
+            ticket("ABC-7", "Crash on login", 1, Some(5.0)),
+            ticket("ABC-9", "Billing page", 3, None),
+        ];
+        let ids = |indices: &[usize]| indices.iter().map(|&i| tickets[i].id.as_str()).collect::<Vec<_>>();
+
+        let by_priority = selection_order(&tickets, Some(SelectionSort::Priority));
+        assert_eq!(ids(&by_priority), ["ABC-7", "ABC-2", "ABC-9", "ABC-10"]);
+        // Picking the first two entries shown selects the two most urgent tickets
+        assert_eq!(ids(&ticket_indices(&by_priority, &[0, 1])), ["ABC-2", "ABC-7"]);
+
+        let by_title = selection_order(&tickets, Some(SelectionSort::Title));
+        assert_eq!(ids(&by_title), ["ABC-9", "ABC-7", "ABC-10", "ABC-2"]);
+        assert_eq!(ids(&ticket_indices(&by_title, &[3])), ["ABC-2"]);
+
+        assert_eq!(ids(&selection_order(&tickets, Some(SelectionSort::Id))), ["ABC-2", "ABC-7", "ABC-9", "ABC-10"]);
+        assert_eq!(ids(&selection_order(&tickets, Some(SelectionSort::Estimate))), ["ABC-7", "ABC-2", "ABC-10", "ABC-9"]);
+        assert_eq!(selection_order(&tickets, None), [0, 1, 2, 3]);
+        assert_eq!(ticket_indices(&[0, 1, 2, 3], &[2, 0]), [0, 2]);
+    }
+
+    #[test]
+    fn each_priority_has_its_label_and_color() {
+        use colored::Color;
+
+        let colors = PriorityColors::default();
+        for (priority, label, color) in [
+            (0, "No priority", Color::White),
+            (1, "⚠️ Urgent", Color::Red),
+            (2, "High", Color::Red),
+            (3, "Medium", Color::Yellow),
+            (4, "Low", Color::Green),
+        ] {
+            assert_eq!(priority_label(priority), label, "{}", priority);
+            assert_eq!(colors.color_for(priority), color, "{}", priority);
+        }
+
+        let custom = PriorityColors::parse("urgent=magenta,none=blue").unwrap();
+        assert_eq!(custom.color_for(1), Color::Magenta);
+        assert_eq!(custom.color_for(0), Color::Blue);
+    }
+}